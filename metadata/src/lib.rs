@@ -19,6 +19,8 @@
 mod from_into;
 mod utils;
 
+pub mod merkleized;
+
 use scale_info::{form::PortableForm, PortableRegistry, Variant};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -148,6 +150,13 @@ impl Metadata {
         MetadataHasher::new(self)
     }
 
+    /// Merkleize this metadata, in order to compute the metadata hash used by the
+    /// `CheckMetadataHash` signed extension, or generate proofs for offline signers.
+    /// See [`merkleized`] for more.
+    pub fn merkleize(&self) -> Result<merkleized::MerkleizedMetadata, merkleized::MerkleizeError> {
+        merkleized::MerkleizedMetadata::new(self)
+    }
+
     /// Filter out any pallets that we don't want to keep, retaining only those that we do.
     pub fn retain<F, G>(&mut self, pallet_filter: F, api_filter: G)
     where
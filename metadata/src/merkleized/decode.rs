@@ -0,0 +1,261 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Decode extrinsics using merkleized types, noting which types were needed to do so.
+
+use super::merkle_tree::LeafId;
+use super::types::{ExtrinsicMetadata, Type, TypeDef, TypeRef};
+use super::{MerkleizeError, SignedExtrinsicData};
+use codec::{Compact, Decode};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Decoding is recursive, so bail out at some depth rather than overflowing the stack.
+const MAX_DEPTH: usize = 1000;
+
+/// Decode the given extrinsic and return the leaves which were needed to do so.
+pub fn extrinsic_leaf_ids(
+    extrinsic_metadata: &ExtrinsicMetadata,
+    types: &[Type],
+    extrinsic: &[u8],
+    additional_signed: Option<&[u8]>,
+) -> Result<BTreeSet<LeafId>, MerkleizeError> {
+    let mut decoder = Decoder::new(types);
+    let input = &mut &*extrinsic;
+
+    let _len = Compact::<u32>::decode(input)
+        .map_err(|e| MerkleizeError::Decode("extrinsic length", e.to_string()))?;
+    let version = u8::decode(input)
+        .map_err(|e| MerkleizeError::Decode("extrinsic version", e.to_string()))?;
+    let is_signed = version & 0b1000_0000 != 0;
+    if version & 0b0111_1111 != extrinsic_metadata.version {
+        return Err(MerkleizeError::Decode(
+            "extrinsic version",
+            format!("unsupported version {}", version & 0b0111_1111),
+        ));
+    }
+
+    if is_signed {
+        decoder.decode("address", extrinsic_metadata.address_ty, input)?;
+        decoder.decode("signature", extrinsic_metadata.signature_ty, input)?;
+        for ext in &extrinsic_metadata.signed_extensions {
+            decoder.decode("signed extensions", ext.included_in_extrinsic, input)?;
+        }
+    }
+    decoder.decode("call", extrinsic_metadata.call_ty, input)?;
+    if !input.is_empty() {
+        return Err(MerkleizeError::LeftoverBytes("extrinsic"));
+    }
+
+    if let Some(additional) = additional_signed {
+        let input = &mut &*additional;
+        for ext in &extrinsic_metadata.signed_extensions {
+            decoder.decode("signed data", ext.included_in_signed_data, input)?;
+        }
+    }
+
+    Ok(decoder.accessed)
+}
+
+/// Decode the given parts of an extrinsic and return the leaves which were needed to do so,
+/// as well as every leaf which may be needed to decode an address and signature.
+pub fn extrinsic_parts_leaf_ids(
+    extrinsic_metadata: &ExtrinsicMetadata,
+    types: &[Type],
+    call_data: &[u8],
+    signed_ext_data: Option<SignedExtrinsicData<'_>>,
+) -> Result<BTreeSet<LeafId>, MerkleizeError> {
+    let mut decoder = Decoder::new(types);
+
+    let input = &mut &*call_data;
+    decoder.decode("call", extrinsic_metadata.call_ty, input)?;
+    if !input.is_empty() {
+        return Err(MerkleizeError::LeftoverBytes("call"));
+    }
+
+    if let Some(data) = signed_ext_data {
+        decoder.access_all(extrinsic_metadata.address_ty);
+        decoder.access_all(extrinsic_metadata.signature_ty);
+
+        let extra = &mut &*data.included_in_extrinsic;
+        let additional = &mut &*data.included_in_signed_data;
+        for ext in &extrinsic_metadata.signed_extensions {
+            decoder.decode("signed extensions", ext.included_in_extrinsic, extra)?;
+            decoder.decode("signed data", ext.included_in_signed_data, additional)?;
+        }
+    }
+
+    Ok(decoder.accessed)
+}
+
+struct Decoder<'a> {
+    /// Each type ID, and the leaves (more than one for enums) with that ID.
+    types: BTreeMap<u32, Vec<&'a Type>>,
+    accessed: BTreeSet<LeafId>,
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(types: &'a [Type]) -> Self {
+        let mut by_id = BTreeMap::<u32, Vec<&Type>>::new();
+        for ty in types {
+            by_id.entry(ty.type_id.0).or_default().push(ty);
+        }
+        Decoder {
+            types: by_id,
+            accessed: BTreeSet::new(),
+            depth: 0,
+        }
+    }
+
+    fn decode(
+        &mut self,
+        what: &'static str,
+        ty: TypeRef,
+        input: &mut &[u8],
+    ) -> Result<(), MerkleizeError> {
+        self.decode_type(ty, input)
+            .map_err(|e| MerkleizeError::Decode(what, e))
+    }
+
+    /// Mark every leaf reachable from the given type as accessed.
+    fn access_all(&mut self, ty: TypeRef) {
+        let Some(id) = ty.id() else { return };
+        let Some(leaves) = self.types.get(&id).cloned() else {
+            return;
+        };
+        for leaf in leaves {
+            if !self.accessed.insert(leaf_id(leaf)) {
+                continue;
+            }
+            match &leaf.type_def {
+                TypeDef::Composite(fields) => fields.iter().for_each(|f| self.access_all(f.ty)),
+                TypeDef::Enumeration(v) => v.fields.iter().for_each(|f| self.access_all(f.ty)),
+                TypeDef::Sequence(ty) => self.access_all(*ty),
+                TypeDef::Array(a) => self.access_all(a.type_param),
+                TypeDef::Tuple(tys) => tys.iter().for_each(|ty| self.access_all(*ty)),
+                TypeDef::BitSequence(_) => {}
+            }
+        }
+    }
+
+    fn decode_type(&mut self, ty: TypeRef, input: &mut &[u8]) -> Result<(), String> {
+        let id = match ty {
+            TypeRef::Bool | TypeRef::U8 | TypeRef::I8 => return skip(input, 1),
+            TypeRef::U16 | TypeRef::I16 => return skip(input, 2),
+            TypeRef::Char | TypeRef::U32 | TypeRef::I32 => return skip(input, 4),
+            TypeRef::U64 | TypeRef::I64 => return skip(input, 8),
+            TypeRef::U128 | TypeRef::I128 => return skip(input, 16),
+            TypeRef::U256 | TypeRef::I256 => return skip(input, 32),
+            TypeRef::Str => {
+                let len = decode_len(input)?;
+                return skip(input, len);
+            }
+            TypeRef::CompactU8 => return decode_compact::<u8>(input),
+            TypeRef::CompactU16 => return decode_compact::<u16>(input),
+            TypeRef::CompactU32 => return decode_compact::<u32>(input),
+            TypeRef::CompactU64 => return decode_compact::<u64>(input),
+            TypeRef::CompactU128 => return decode_compact::<u128>(input),
+            TypeRef::CompactU256 => return Err("compact u256 values are not supported".into()),
+            TypeRef::Void => return Ok(()),
+            TypeRef::ById(id) => id.0,
+        };
+
+        if self.depth >= MAX_DEPTH {
+            return Err("type is nested too deeply".into());
+        }
+        self.depth += 1;
+        let res = self.decode_by_id(id, input);
+        self.depth -= 1;
+        res
+    }
+
+    fn decode_by_id(&mut self, id: u32, input: &mut &[u8]) -> Result<(), String> {
+        let leaves = self
+            .types
+            .get(&id)
+            .ok_or_else(|| format!("type {id} not found"))?;
+
+        let leaf = if let TypeDef::Enumeration(_) = &leaves[0].type_def {
+            let index = u8::decode(input).map_err(|e| e.to_string())?;
+            leaves
+                .iter()
+                .find(
+                    |l| matches!(&l.type_def, TypeDef::Enumeration(v) if v.index.0 == index as u32),
+                )
+                .ok_or_else(|| format!("variant {index} of type {id} not found"))?
+        } else {
+            &leaves[0]
+        };
+        self.accessed.insert(leaf_id(leaf));
+
+        match &leaf.type_def {
+            TypeDef::Composite(fields) => {
+                for f in fields {
+                    self.decode_type(f.ty, input)?;
+                }
+            }
+            TypeDef::Enumeration(v) => {
+                for f in &v.fields {
+                    self.decode_type(f.ty, input)?;
+                }
+            }
+            TypeDef::Sequence(ty) => {
+                for _ in 0..decode_len(input)? {
+                    self.decode_type(*ty, input)?;
+                }
+            }
+            TypeDef::Array(a) => {
+                for _ in 0..a.len {
+                    self.decode_type(a.type_param, input)?;
+                }
+            }
+            TypeDef::Tuple(tys) => {
+                for ty in tys {
+                    self.decode_type(*ty, input)?;
+                }
+            }
+            TypeDef::BitSequence(b) => {
+                let bits = decode_len(input)?;
+                let bits_per_item = 8 * b.num_bytes as usize;
+                skip(
+                    input,
+                    (bits + bits_per_item - 1) / bits_per_item * b.num_bytes as usize,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn leaf_id(ty: &Type) -> LeafId {
+    LeafId {
+        type_id: ty.type_id.0,
+        variant: match &ty.type_def {
+            TypeDef::Enumeration(v) => Some(v.index.0),
+            _ => None,
+        },
+    }
+}
+
+fn skip(input: &mut &[u8], len: usize) -> Result<(), String> {
+    if input.len() < len {
+        return Err("not enough bytes".into());
+    }
+    *input = &input[len..];
+    Ok(())
+}
+
+fn decode_len(input: &mut &[u8]) -> Result<usize, String> {
+    let len = Compact::<u32>::decode(input).map_err(|e| e.to_string())?;
+    Ok(len.0 as usize)
+}
+
+fn decode_compact<T>(input: &mut &[u8]) -> Result<(), String>
+where
+    Compact<T>: Decode,
+{
+    Compact::<T>::decode(input)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,326 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use super::types::{hash, Hash, Type};
+use super::MerkleizeError;
+use codec::{Decode, Encode};
+use std::collections::{BTreeMap, VecDeque};
+use std::iter::Peekable;
+
+/// Identifies a leaf of the tree. Each variant of an enum is a separate leaf, and so
+/// these are ordered by type ID and then by variant index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct LeafId {
+    pub type_id: u32,
+    pub variant: Option<u32>,
+}
+
+/// The index of a node in the tree. The root is at index 0, and the children of the node at
+/// index `n` are at `2n + 1` and `2n + 2`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct NodeIndex(usize);
+
+impl NodeIndex {
+    fn is_root(self) -> bool {
+        self.0 == 0
+    }
+
+    fn parent(self) -> Self {
+        if self.is_root() {
+            self
+        } else {
+            NodeIndex((self.0 - 1) / 2)
+        }
+    }
+
+    fn is_left_child(self) -> bool {
+        self.0 % 2 == 1
+    }
+
+    fn left_child(self) -> Self {
+        NodeIndex(self.0 * 2 + 1)
+    }
+
+    fn right_child(self) -> Self {
+        NodeIndex(self.0 * 2 + 2)
+    }
+
+    fn level(self) -> u32 {
+        (self.0 + 1).ilog2()
+    }
+
+    /// Is `other` this node, or some descendant of it?
+    fn is_ancestor_of(self, other: Self) -> bool {
+        if self.0 > other.0 {
+            return false;
+        }
+        self.0 + 1 == (other.0 + 1) >> (other.level() - self.level())
+    }
+}
+
+/// A proof that some types are part of the merkleized metadata. This contains the types
+/// needed to decode a specific extrinsic, along with the hashes needed to recompute the
+/// root of the type tree from them (see [`Proof::root()`]).
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct Proof {
+    /// The leaves of the tree that are included in the proof, leftmost first.
+    pub leaves: Vec<Type>,
+    /// The index in the tree of each leaf, in the same order as `leaves`.
+    pub leaf_indices: Vec<u32>,
+    /// The hashes of every node that can't be calculated from the leaves, ordered
+    /// from left to right and from the root down.
+    pub nodes: Vec<Hash>,
+}
+
+impl Proof {
+    /// Recompute the root hash of the type tree from this proof. If this matches the
+    /// `types_tree_root` of the [`super::MetadataDigest`] then the types in this proof
+    /// are part of the metadata. Returns `None` if the proof is malformed.
+    pub fn root(&self) -> Option<Hash> {
+        if self.leaves.len() != self.leaf_indices.len() {
+            return None;
+        }
+        let mut leaves = self
+            .leaf_indices
+            .iter()
+            .map(|&i| NodeIndex(i as usize))
+            .zip(&self.leaves)
+            .peekable();
+        let mut nodes = self.nodes.iter();
+
+        let root = proof_node_hash(NodeIndex(0), &mut leaves, &mut nodes)?;
+
+        // Everything in the proof should have been used.
+        if leaves.next().is_some() || nodes.next().is_some() {
+            return None;
+        }
+        Some(root)
+    }
+}
+
+fn proof_node_hash<'a>(
+    node_index: NodeIndex,
+    leaves: &mut Peekable<impl Iterator<Item = (NodeIndex, &'a Type)>>,
+    nodes: &mut impl Iterator<Item = &'a Hash>,
+) -> Option<Hash> {
+    let next_leaf = leaves.peek().map(|(index, _)| *index);
+    match next_leaf {
+        Some(leaf_index) if leaf_index == node_index => {
+            let (_, leaf) = leaves.next()?;
+            Some(leaf.hash())
+        }
+        Some(leaf_index) if node_index.is_ancestor_of(leaf_index) => {
+            let left = proof_node_hash(node_index.left_child(), leaves, nodes)?;
+            let right = proof_node_hash(node_index.right_child(), leaves, nodes)?;
+            Some(hash(&(left, right).encode()))
+        }
+        _ => nodes.next().copied(),
+    }
+}
+
+/// A complete binary tree whose leaves are the hashes of each type.
+pub(super) struct MerkleTree {
+    /// Every leaf, in order.
+    leaves: Vec<Type>,
+    /// The position of each leaf in `leaves`.
+    leaf_positions: BTreeMap<LeafId, usize>,
+    /// The hash of every node in the tree, indexed by [`NodeIndex`].
+    hashes: Vec<Hash>,
+}
+
+impl MerkleTree {
+    /// Build a tree from the given leaves, which are expected to be in order.
+    pub fn new(leaves: impl IntoIterator<Item = (LeafId, Type)>) -> Self {
+        let (leaf_ids, leaves): (Vec<_>, Vec<_>) = leaves.into_iter().unzip();
+        let leaf_positions = leaf_ids
+            .into_iter()
+            .enumerate()
+            .map(|(i, id)| (id, i))
+            .collect();
+
+        // Repeatedly combine the last two nodes into a new node at the front, until
+        // only the root is left. This gives us every node from the root down.
+        let mut pending: VecDeque<Hash> = leaves.iter().map(Type::hash).collect();
+        let mut hashes = VecDeque::new();
+        while pending.len() > 1 {
+            let right = pending.pop_back().expect("more than one node; qed");
+            let left = pending.pop_back().expect("more than one node; qed");
+            hashes.push_front(right);
+            hashes.push_front(left);
+            pending.push_front(hash(&(left, right).encode()));
+        }
+        hashes.push_front(pending.pop_back().unwrap_or_default());
+
+        MerkleTree {
+            leaves,
+            leaf_positions,
+            hashes: hashes.into(),
+        }
+    }
+
+    /// The root hash of the tree.
+    pub fn root(&self) -> Hash {
+        self.hashes[0]
+    }
+
+    /// Every leaf of the tree, in order.
+    pub fn leaves(&self) -> &[Type] {
+        &self.leaves
+    }
+
+    /// Build a proof containing the given leaves.
+    pub fn build_proof(
+        &self,
+        leaf_ids: impl IntoIterator<Item = LeafId>,
+    ) -> Result<Proof, MerkleizeError> {
+        // Leaves are the last nodes in the tree.
+        let first_leaf_node = self.hashes.len() - self.leaves.len();
+        let mut leaf_nodes = leaf_ids
+            .into_iter()
+            .map(|id| {
+                let position = self
+                    .leaf_positions
+                    .get(&id)
+                    .ok_or(MerkleizeError::TypeNotFound(id.type_id))?;
+                Ok(NodeIndex(first_leaf_node + position))
+            })
+            .collect::<Result<Vec<_>, MerkleizeError>>()?;
+
+        // Leftmost leaves first; leaves on the lowest level of the tree are leftmost.
+        leaf_nodes.sort_by(|a, b| b.level().cmp(&a.level()).then(a.0.cmp(&b.0)));
+        leaf_nodes.dedup();
+
+        let mut nodes = Vec::new();
+        let mut remaining = leaf_nodes.iter().peekable();
+        if let Some(first) = remaining.next().filter(|n| !n.is_root()) {
+            self.collect_node_hashes(NodeIndex(0), *first, &mut remaining, &mut nodes);
+        }
+
+        Ok(Proof {
+            leaves: leaf_nodes
+                .iter()
+                .map(|n| self.leaves[n.0 - first_leaf_node].clone())
+                .collect(),
+            leaf_indices: leaf_nodes.iter().map(|n| n.0 as u32).collect(),
+            nodes,
+        })
+    }
+
+    /// Walk up from `leaf` to `stop_at`, collecting the hashes of any nodes which can't be
+    /// computed from the leaves, and descending into right hand subtrees containing more
+    /// of the leaves that we want to prove.
+    fn collect_node_hashes<'a>(
+        &self,
+        stop_at: NodeIndex,
+        leaf: NodeIndex,
+        remaining: &mut Peekable<impl Iterator<Item = &'a NodeIndex>>,
+        nodes: &mut Vec<Hash>,
+    ) {
+        let mut node = leaf;
+        // Hashes of left siblings belong before anything else collected from here.
+        let leftmost_position = nodes.len();
+
+        loop {
+            let parent = node.parent();
+
+            if node.is_left_child() {
+                let right = parent.right_child();
+                match remaining.peek() {
+                    Some(&&next) if next == right => {
+                        remaining.next();
+                    }
+                    Some(&&next) if right.is_ancestor_of(next) => {
+                        remaining.next();
+                        self.collect_node_hashes(right, next, remaining, nodes);
+                    }
+                    _ => nodes.push(self.hashes[right.0]),
+                }
+            } else {
+                // Leaves are visited left to right, so the left sibling can't be a leaf that
+                // we want to prove (otherwise we'd have arrived here from it).
+                let left = parent.left_child();
+                nodes.insert(leftmost_position, self.hashes[left.0]);
+            }
+
+            if parent == stop_at {
+                return;
+            }
+            node = parent;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::merkleized::types::{TypeDef, TypeDefArray, TypeRef};
+
+    fn tree(num_leaves: u32) -> MerkleTree {
+        MerkleTree::new((0..num_leaves).map(|n| {
+            let id = LeafId {
+                type_id: n,
+                variant: None,
+            };
+            let ty = Type {
+                path: vec![],
+                type_def: TypeDef::Array(TypeDefArray {
+                    len: n,
+                    type_param: TypeRef::U8,
+                }),
+                type_id: n.into(),
+            };
+            (id, ty)
+        }))
+    }
+
+    fn proof(tree: &MerkleTree, ids: &[u32]) -> Proof {
+        tree.build_proof(ids.iter().map(|&type_id| LeafId {
+            type_id,
+            variant: None,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn proofs_reproduce_the_root() {
+        for num_leaves in [1, 2, 5, 8, 10, 23, 37] {
+            let tree = tree(num_leaves);
+            assert_eq!(tree.hashes.len() as u32, num_leaves * 2 - 1);
+
+            let all: Vec<u32> = (0..num_leaves).collect();
+            let subsets = [
+                vec![0],
+                vec![num_leaves - 1],
+                vec![0, num_leaves - 1],
+                all.iter().copied().step_by(3).collect(),
+                all.clone(),
+            ];
+            for mut ids in subsets {
+                ids.dedup();
+                let proof = proof(&tree, &ids);
+                assert_eq!(proof.leaves.len(), ids.len());
+                assert_eq!(
+                    proof.root(),
+                    Some(tree.root()),
+                    "{num_leaves} leaves: {ids:?}"
+                );
+            }
+
+            // Proving every leaf needs no other hashes:
+            assert!(proof(&tree, &all).nodes.is_empty());
+        }
+    }
+
+    #[test]
+    fn tampered_proofs_do_not_reproduce_the_root() {
+        let tree = tree(10);
+        let mut proof = proof(&tree, &[2, 7]);
+        proof.leaves[0].path.push("Nope".into());
+        assert_ne!(proof.root(), Some(tree.root()));
+
+        let mut proof = self::proof(&tree, &[2, 7]);
+        proof.nodes.pop();
+        assert_eq!(proof.root(), None);
+    }
+}
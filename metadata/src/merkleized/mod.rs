@@ -0,0 +1,725 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Merkleized metadata, as described in
+//! [RFC-0078](https://polkadot-fellows.github.io/RFCs/approved/0078-merkleized-metadata.html).
+//!
+//! The types needed to decode extrinsics are arranged into a merkle tree, whose root is part of
+//! a [`MetadataDigest`]. The hash of this digest (the "metadata hash") can be included in the
+//! data that is signed for a transaction via the `CheckMetadataHash` signed extension. An
+//! offline signer can then be given a [`Proof`] containing only the types needed to decode the
+//! transaction that it's signing, and can check these against the metadata hash without
+//! needing the full metadata.
+//!
+//! Use [`crate::Metadata::merkleize()`] to obtain a [`MerkleizedMetadata`].
+
+mod decode;
+mod merkle_tree;
+pub mod types;
+
+use crate::Metadata;
+use merkle_tree::{LeafId, MerkleTree};
+use scale_info::{form::PortableForm, PortableRegistry, TypeDef, TypeDefPrimitive};
+use std::collections::{BTreeMap, BTreeSet};
+use types::{EnumerationVariant, Field, Type, TypeDefArray, TypeDefBitSequence, TypeRef};
+
+pub use merkle_tree::Proof;
+pub use types::{ExtrinsicMetadata, Hash, MetadataDigest, SignedExtensionMetadata};
+
+/// An error merkleizing metadata or generating proofs from it.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum MerkleizeError {
+    /// Type missing from type registry
+    #[error("Type {0} is expected but not found in the type registry")]
+    TypeNotFound(u32),
+    /// A type cannot be represented in merkleized metadata.
+    #[error("Type {0} is not supported in merkleized metadata: {1}")]
+    UnsupportedType(u32, String),
+    /// Something could not be decoded.
+    #[error("Could not decode the {0}: {1}")]
+    Decode(&'static str, String),
+    /// Bytes were left over after decoding something.
+    #[error("Bytes were left over after decoding the {0}")]
+    LeftoverBytes(&'static str),
+}
+
+/// Information about the chain which is included in the [`MetadataDigest`] alongside the
+/// details derived from the metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtraInfo {
+    /// The spec version of the runtime.
+    pub spec_version: u32,
+    /// The spec name of the runtime.
+    pub spec_name: String,
+    /// The SS58 prefix used for addresses on the chain.
+    pub base58_prefix: u16,
+    /// The number of decimals that the native token has.
+    pub decimals: u8,
+    /// The symbol of the native token.
+    pub token_symbol: String,
+}
+
+/// The parts of a signed extrinsic which come from its signed extensions.
+#[derive(Debug, Clone, Copy)]
+pub struct SignedExtrinsicData<'a> {
+    /// The signed extension data which is included in the extrinsic.
+    pub included_in_extrinsic: &'a [u8],
+    /// The signed extension data which is signed but not included in the extrinsic.
+    pub included_in_signed_data: &'a [u8],
+}
+
+/// The merkleized form of some [`Metadata`], from which a [`MetadataDigest`] and
+/// proofs for individual extrinsics can be produced.
+pub struct MerkleizedMetadata {
+    extrinsic_metadata: ExtrinsicMetadata,
+    tree: MerkleTree,
+}
+
+impl std::fmt::Debug for MerkleizedMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MerkleizedMetadata")
+            .field("extrinsic_metadata", &self.extrinsic_metadata)
+            .field("types_tree_root", &self.tree.root())
+            .finish()
+    }
+}
+
+impl MerkleizedMetadata {
+    /// Merkleize the given metadata.
+    pub fn new(metadata: &Metadata) -> Result<Self, MerkleizeError> {
+        let types = metadata.types();
+        let extrinsic = metadata.extrinsic();
+
+        // Only the types needed to decode extrinsics are merkleized.
+        let mut accessible = BTreeSet::new();
+        let roots = [
+            extrinsic.call_ty(),
+            extrinsic.address_ty(),
+            extrinsic.signature_ty(),
+        ];
+        let signed_extension_tys = extrinsic
+            .signed_extensions()
+            .iter()
+            .flat_map(|e| [e.extra_ty(), e.additional_ty()]);
+        for id in roots.into_iter().chain(signed_extension_tys) {
+            collect_accessible_types(id, types, &mut accessible)?;
+        }
+
+        // Types are given new, sequential IDs. Types which are referenced inline don't get one.
+        let mut ids = BTreeMap::new();
+        for id in accessible {
+            if is_referenced_by_id(resolve(types, id)?) {
+                let new_id = ids.len() as u32;
+                ids.insert(id, new_id);
+            }
+        }
+        let cx = Context { types, ids: &ids };
+
+        let extrinsic_metadata = ExtrinsicMetadata {
+            version: extrinsic.version(),
+            address_ty: cx.type_ref(extrinsic.address_ty())?,
+            call_ty: cx.type_ref(extrinsic.call_ty())?,
+            signature_ty: cx.type_ref(extrinsic.signature_ty())?,
+            signed_extensions: extrinsic
+                .signed_extensions()
+                .iter()
+                .map(|e| {
+                    Ok(SignedExtensionMetadata {
+                        identifier: e.identifier().to_owned(),
+                        included_in_extrinsic: cx.type_ref(e.extra_ty())?,
+                        included_in_signed_data: cx.type_ref(e.additional_ty())?,
+                    })
+                })
+                .collect::<Result<_, MerkleizeError>>()?,
+        };
+
+        let mut leaves = Vec::new();
+        for (&id, &new_id) in &ids {
+            cx.push_leaves(id, new_id, &mut leaves)?;
+        }
+
+        Ok(MerkleizedMetadata {
+            extrinsic_metadata,
+            tree: MerkleTree::new(leaves),
+        })
+    }
+
+    /// The root hash of the merkle tree of types.
+    pub fn types_tree_root(&self) -> Hash {
+        self.tree.root()
+    }
+
+    /// Every type in the merkle tree, in order.
+    pub fn types(&self) -> &[Type] {
+        self.tree.leaves()
+    }
+
+    /// The details needed to decode an extrinsic, given the types that it refers to.
+    pub fn extrinsic_metadata(&self) -> &ExtrinsicMetadata {
+        &self.extrinsic_metadata
+    }
+
+    /// Produce the [`MetadataDigest`] for this metadata. Call [`MetadataDigest::hash()`] on
+    /// this to obtain the metadata hash.
+    pub fn digest(&self, extra_info: ExtraInfo) -> MetadataDigest {
+        MetadataDigest::V1 {
+            types_tree_root: self.types_tree_root(),
+            extrinsic_metadata_hash: self.extrinsic_metadata.hash(),
+            spec_version: extra_info.spec_version,
+            spec_name: extra_info.spec_name,
+            base58_prefix: extra_info.base58_prefix,
+            decimals: extra_info.decimals,
+            token_symbol: extra_info.token_symbol,
+        }
+    }
+
+    /// Produce a proof containing the types needed to decode the given extrinsic bytes and,
+    /// if given, the signed extension data which is signed but not included in the extrinsic.
+    pub fn proof_for_extrinsic(
+        &self,
+        extrinsic: &[u8],
+        additional_signed: Option<&[u8]>,
+    ) -> Result<Proof, MerkleizeError> {
+        let leaf_ids = decode::extrinsic_leaf_ids(
+            &self.extrinsic_metadata,
+            self.types(),
+            extrinsic,
+            additional_signed,
+        )?;
+        self.tree.build_proof(leaf_ids)
+    }
+
+    /// Produce a proof containing the types needed to decode an extrinsic with the given call
+    /// data and, if it's to be signed, the given signed extension data. This can be used
+    /// before the extrinsic has been signed. Since the address and signature aren't known, all
+    /// of the types which could be needed to decode them are included.
+    pub fn proof_for_extrinsic_parts(
+        &self,
+        call_data: &[u8],
+        signed_ext_data: Option<SignedExtrinsicData<'_>>,
+    ) -> Result<Proof, MerkleizeError> {
+        let leaf_ids = decode::extrinsic_parts_leaf_ids(
+            &self.extrinsic_metadata,
+            self.types(),
+            call_data,
+            signed_ext_data,
+        )?;
+        self.tree.build_proof(leaf_ids)
+    }
+}
+
+/// Check that the types in a proof are enough to decode the given extrinsic. This is what an
+/// offline signer would do, having also checked that [`Proof::root()`] matches the types tree
+/// root in the [`MetadataDigest`].
+pub fn verify_proof(
+    proof: &Proof,
+    extrinsic_metadata: &ExtrinsicMetadata,
+    extrinsic: &[u8],
+    additional_signed: Option<&[u8]>,
+) -> Result<(), MerkleizeError> {
+    decode::extrinsic_leaf_ids(
+        extrinsic_metadata,
+        &proof.leaves,
+        extrinsic,
+        additional_signed,
+    )
+    .map(|_| ())
+}
+
+fn resolve(
+    types: &PortableRegistry,
+    id: u32,
+) -> Result<&scale_info::Type<PortableForm>, MerkleizeError> {
+    types.resolve(id).ok_or(MerkleizeError::TypeNotFound(id))
+}
+
+/// Primitive, compact and empty types are referenced inline rather than by ID.
+fn is_referenced_by_id(ty: &scale_info::Type<PortableForm>) -> bool {
+    match &ty.type_def {
+        TypeDef::Primitive(_) | TypeDef::Compact(_) => false,
+        TypeDef::Composite(c) => !c.fields.is_empty(),
+        TypeDef::Variant(v) => !v.variants.is_empty(),
+        TypeDef::Tuple(t) => !t.fields.is_empty(),
+        TypeDef::Sequence(_) | TypeDef::Array(_) | TypeDef::BitSequence(_) => true,
+    }
+}
+
+/// Collect the IDs of all of the types reachable from the given one. Compact and bit
+/// sequence types are turned into something primitive-like, and so we don't need to look
+/// inside them.
+fn collect_accessible_types(
+    id: u32,
+    types: &PortableRegistry,
+    accessible: &mut BTreeSet<u32>,
+) -> Result<(), MerkleizeError> {
+    if !accessible.insert(id) {
+        return Ok(());
+    }
+
+    match &resolve(types, id)?.type_def {
+        TypeDef::Composite(c) => {
+            for f in &c.fields {
+                collect_accessible_types(f.ty.id, types, accessible)?;
+            }
+        }
+        TypeDef::Variant(v) => {
+            for f in v.variants.iter().flat_map(|v| &v.fields) {
+                collect_accessible_types(f.ty.id, types, accessible)?;
+            }
+        }
+        TypeDef::Sequence(s) => collect_accessible_types(s.type_param.id, types, accessible)?,
+        TypeDef::Array(a) => collect_accessible_types(a.type_param.id, types, accessible)?,
+        TypeDef::Tuple(t) => {
+            for f in &t.fields {
+                collect_accessible_types(f.id, types, accessible)?;
+            }
+        }
+        TypeDef::Primitive(_) | TypeDef::Compact(_) | TypeDef::BitSequence(_) => {}
+    }
+    Ok(())
+}
+
+/// Collect every primitive type found by descending from the given one.
+fn collect_primitives(
+    id: u32,
+    types: &PortableRegistry,
+    visited: &mut BTreeSet<u32>,
+    found: &mut Vec<TypeDefPrimitive>,
+) -> Result<(), MerkleizeError> {
+    let mut visit = |id| {
+        if visited.insert(id) {
+            collect_primitives(id, types, visited, found)
+        } else {
+            Ok(())
+        }
+    };
+
+    match &resolve(types, id)?.type_def {
+        TypeDef::Primitive(p) => found.push(p.clone()),
+        TypeDef::Composite(c) => c.fields.iter().try_for_each(|f| visit(f.ty.id))?,
+        TypeDef::Variant(v) => v
+            .variants
+            .iter()
+            .flat_map(|v| &v.fields)
+            .try_for_each(|f| visit(f.ty.id))?,
+        TypeDef::Sequence(s) => visit(s.type_param.id)?,
+        TypeDef::Array(a) => visit(a.type_param.id)?,
+        TypeDef::Tuple(t) => t.fields.iter().try_for_each(|f| visit(f.id))?,
+        TypeDef::Compact(c) => visit(c.type_param.id)?,
+        TypeDef::BitSequence(b) => {
+            visit(b.bit_order_type.id)?;
+            visit(b.bit_store_type.id)?;
+        }
+    }
+    Ok(())
+}
+
+/// The type registry, and the new ID of every type which is referenced by ID.
+struct Context<'a> {
+    types: &'a PortableRegistry,
+    ids: &'a BTreeMap<u32, u32>,
+}
+
+impl Context<'_> {
+    fn type_ref(&self, id: u32) -> Result<TypeRef, MerkleizeError> {
+        let ty = resolve(self.types, id)?;
+        let type_ref = match &ty.type_def {
+            TypeDef::Primitive(p) => primitive_type_ref(p),
+            TypeDef::Compact(c) => {
+                let mut found = Vec::new();
+                collect_primitives(
+                    c.type_param.id,
+                    self.types,
+                    &mut BTreeSet::new(),
+                    &mut found,
+                )?;
+                match found.as_slice() {
+                    [] => TypeRef::Void,
+                    [TypeDefPrimitive::U8] => TypeRef::CompactU8,
+                    [TypeDefPrimitive::U16] => TypeRef::CompactU16,
+                    [TypeDefPrimitive::U32] => TypeRef::CompactU32,
+                    [TypeDefPrimitive::U64] => TypeRef::CompactU64,
+                    [TypeDefPrimitive::U128] => TypeRef::CompactU128,
+                    _ => {
+                        return Err(MerkleizeError::UnsupportedType(
+                            id,
+                            format!("cannot compact encode {found:?}"),
+                        ))
+                    }
+                }
+            }
+            _ if !is_referenced_by_id(ty) => TypeRef::Void,
+            _ => {
+                let new_id = self.ids.get(&id).ok_or(MerkleizeError::TypeNotFound(id))?;
+                TypeRef::ById((*new_id).into())
+            }
+        };
+        Ok(type_ref)
+    }
+
+    fn fields(
+        &self,
+        fields: &[scale_info::Field<PortableForm>],
+    ) -> Result<Vec<Field>, MerkleizeError> {
+        fields
+            .iter()
+            .map(|f| {
+                Ok(Field {
+                    name: f.name.clone(),
+                    ty: self.type_ref(f.ty.id)?,
+                    type_name: f.type_name.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Push the leaf (or for enums, one leaf per variant) for the given type.
+    fn push_leaves(
+        &self,
+        id: u32,
+        new_id: u32,
+        leaves: &mut Vec<(LeafId, Type)>,
+    ) -> Result<(), MerkleizeError> {
+        let ty = resolve(self.types, id)?;
+        let leaf = |type_def| Type {
+            path: ty.path.segments.clone(),
+            type_def,
+            type_id: new_id.into(),
+        };
+        let leaf_id = LeafId {
+            type_id: new_id,
+            variant: None,
+        };
+
+        let type_def = match &ty.type_def {
+            TypeDef::Variant(v) => {
+                let mut variants: Vec<_> = v.variants.iter().collect();
+                variants.sort_by_key(|v| v.index);
+                for v in variants {
+                    let variant = EnumerationVariant {
+                        name: v.name.clone(),
+                        fields: self.fields(&v.fields)?,
+                        index: (v.index as u32).into(),
+                    };
+                    let leaf_id = LeafId {
+                        type_id: new_id,
+                        variant: Some(v.index as u32),
+                    };
+                    leaves.push((leaf_id, leaf(types::TypeDef::Enumeration(variant))));
+                }
+                return Ok(());
+            }
+            TypeDef::Composite(c) => types::TypeDef::Composite(self.fields(&c.fields)?),
+            TypeDef::Sequence(s) => types::TypeDef::Sequence(self.type_ref(s.type_param.id)?),
+            TypeDef::Array(a) => types::TypeDef::Array(TypeDefArray {
+                len: a.len,
+                type_param: self.type_ref(a.type_param.id)?,
+            }),
+            TypeDef::Tuple(t) => types::TypeDef::Tuple(
+                t.fields
+                    .iter()
+                    .map(|f| self.type_ref(f.id))
+                    .collect::<Result<_, _>>()?,
+            ),
+            TypeDef::BitSequence(b) => types::TypeDef::BitSequence(self.bit_sequence(id, b)?),
+            TypeDef::Primitive(_) | TypeDef::Compact(_) => {
+                unreachable!("only types referenced by ID are given leaves; qed")
+            }
+        };
+        leaves.push((leaf_id, leaf(type_def)));
+        Ok(())
+    }
+
+    fn bit_sequence(
+        &self,
+        id: u32,
+        b: &scale_info::TypeDefBitSequence<PortableForm>,
+    ) -> Result<TypeDefBitSequence, MerkleizeError> {
+        let mut found = Vec::new();
+        collect_primitives(
+            b.bit_store_type.id,
+            self.types,
+            &mut BTreeSet::new(),
+            &mut found,
+        )?;
+        let num_bytes = match found.as_slice() {
+            [TypeDefPrimitive::U8] => 1,
+            [TypeDefPrimitive::U16] => 2,
+            [TypeDefPrimitive::U32] => 4,
+            [TypeDefPrimitive::U64] => 8,
+            _ => {
+                return Err(MerkleizeError::UnsupportedType(
+                    id,
+                    format!("invalid bit sequence store type {found:?}"),
+                ))
+            }
+        };
+
+        let order = resolve(self.types, b.bit_order_type.id)?;
+        let least_significant_bit_first = match order
+            .path
+            .segments
+            .iter()
+            .find(|s| *s == "Lsb0" || *s == "Msb0")
+        {
+            Some(order) => order == "Lsb0",
+            None => {
+                return Err(MerkleizeError::UnsupportedType(
+                    id,
+                    "bit sequence order should be Lsb0 or Msb0".to_owned(),
+                ))
+            }
+        };
+
+        Ok(TypeDefBitSequence {
+            num_bytes,
+            least_significant_bit_first,
+        })
+    }
+}
+
+fn primitive_type_ref(p: &TypeDefPrimitive) -> TypeRef {
+    match p {
+        TypeDefPrimitive::Bool => TypeRef::Bool,
+        TypeDefPrimitive::Char => TypeRef::Char,
+        TypeDefPrimitive::Str => TypeRef::Str,
+        TypeDefPrimitive::U8 => TypeRef::U8,
+        TypeDefPrimitive::U16 => TypeRef::U16,
+        TypeDefPrimitive::U32 => TypeRef::U32,
+        TypeDefPrimitive::U64 => TypeRef::U64,
+        TypeDefPrimitive::U128 => TypeRef::U128,
+        TypeDefPrimitive::U256 => TypeRef::U256,
+        TypeDefPrimitive::I8 => TypeRef::I8,
+        TypeDefPrimitive::I16 => TypeRef::I16,
+        TypeDefPrimitive::I32 => TypeRef::I32,
+        TypeDefPrimitive::I64 => TypeRef::I64,
+        TypeDefPrimitive::I128 => TypeRef::I128,
+        TypeDefPrimitive::I256 => TypeRef::I256,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use codec::{Compact, Decode, Encode};
+
+    fn metadata() -> Metadata {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_full.scale").unwrap();
+        Metadata::decode(&mut &*bytes).unwrap()
+    }
+
+    fn extra_info() -> ExtraInfo {
+        ExtraInfo {
+            spec_version: 1,
+            spec_name: "polkadot".into(),
+            base58_prefix: 0,
+            decimals: 10,
+            token_symbol: "DOT".into(),
+        }
+    }
+
+    fn call_data(metadata: &Metadata, pallet: &str, call: &str, args: impl Encode) -> Vec<u8> {
+        let pallet = metadata.pallet_by_name(pallet).unwrap();
+        let call = pallet.call_variant_by_name(call).unwrap();
+        let mut bytes = vec![pallet.index(), call.index];
+        args.encode_to(&mut bytes);
+        bytes
+    }
+
+    fn unsigned_extrinsic(call_data: &[u8]) -> Vec<u8> {
+        let mut inner = vec![4u8];
+        inner.extend(call_data);
+        let mut bytes = Compact(inner.len() as u32).encode();
+        bytes.extend(inner);
+        bytes
+    }
+
+    #[test]
+    fn digest_matches_reference_implementation() {
+        // These hashes were generated from the same metadata and extra info using
+        // `merkleized_metadata::generate_metadata_digest` from the reference implementation
+        // of RFC-0078 (the `merkleized-metadata` crate, version 0.5.1).
+        let test_vectors = [
+            (
+                "polkadot_metadata_full.scale",
+                "efb622f0276dc1534ec8262196a459e6be6c70881dcea750bd4c749120075db2",
+            ),
+            (
+                "polkadot_metadata_small.scale",
+                "a871d230a1d25e409eafd8b0186a16f982feb95b07dd99bc807e140a6ad60e5d",
+            ),
+            (
+                "polkadot_metadata_tiny.scale",
+                "b9ceee6f96754964927fbacf3ce629ec045fc0b34b66a81585b7f9f4e648e8aa",
+            ),
+        ];
+
+        for (file, expected_hash) in test_vectors {
+            let bytes = std::fs::read(format!("../artifacts/{file}")).unwrap();
+            let metadata = Metadata::decode(&mut &*bytes).unwrap();
+            let digest = metadata.merkleize().unwrap().digest(extra_info());
+            let hash: String = digest.hash().iter().map(|b| format!("{b:02x}")).collect();
+            assert_eq!(hash, expected_hash, "unexpected metadata hash for {file}");
+        }
+    }
+
+    #[test]
+    fn digest_depends_on_extra_info() {
+        let merkleized = metadata().merkleize().unwrap();
+        let digest = merkleized.digest(extra_info());
+
+        // The extra info is part of the digest:
+        let other = merkleized.digest(ExtraInfo {
+            spec_version: 2,
+            ..extra_info()
+        });
+        assert_ne!(other.hash(), digest.hash());
+    }
+
+    #[test]
+    fn types_are_ordered_by_id_and_variant() {
+        let merkleized = metadata().merkleize().unwrap();
+        let ids: Vec<_> = merkleized
+            .types()
+            .iter()
+            .map(|ty| match &ty.type_def {
+                types::TypeDef::Enumeration(v) => (ty.type_id.0, v.index.0),
+                _ => (ty.type_id.0, 0),
+            })
+            .collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+
+        // Every type referenced by the extrinsic metadata is there:
+        let extrinsic = merkleized.extrinsic_metadata();
+        for ty in [
+            extrinsic.call_ty,
+            extrinsic.address_ty,
+            extrinsic.signature_ty,
+        ] {
+            let id = ty.id().unwrap();
+            assert!(merkleized.types().iter().any(|t| t.type_id.0 == id));
+        }
+    }
+
+    #[test]
+    fn proofs_contain_the_types_needed_to_decode() {
+        let metadata = metadata();
+        let merkleized = metadata.merkleize().unwrap();
+        let remark = call_data(&metadata, "System", "remark", vec![1u8, 2, 3]);
+        let extrinsic = unsigned_extrinsic(&remark);
+
+        let proof = merkleized.proof_for_extrinsic(&extrinsic, None).unwrap();
+        assert_eq!(proof.root(), Some(merkleized.types_tree_root()));
+        assert!(proof.leaves.len() < merkleized.types().len());
+        verify_proof(&proof, merkleized.extrinsic_metadata(), &extrinsic, None).unwrap();
+
+        let parts_proof = merkleized.proof_for_extrinsic_parts(&remark, None).unwrap();
+        assert_eq!(parts_proof, proof);
+
+        // The proof doesn't contain what's needed to decode some other call:
+        let other = unsigned_extrinsic(&call_data(&metadata, "System", "set_heap_pages", 1u64));
+        assert!(matches!(
+            verify_proof(&proof, merkleized.extrinsic_metadata(), &other, None),
+            Err(MerkleizeError::Decode("call", _))
+        ));
+
+        // Nor is anything but the exact extrinsic accepted:
+        let mut trailing = extrinsic.clone();
+        trailing.push(0);
+        assert!(matches!(
+            merkleized.proof_for_extrinsic(&trailing, None),
+            Err(MerkleizeError::LeftoverBytes("extrinsic"))
+        ));
+    }
+
+    #[test]
+    fn proofs_for_extrinsic_parts_contain_the_signing_types() {
+        let metadata = metadata();
+        let merkleized = metadata.merkleize().unwrap();
+        let remark = call_data(&metadata, "System", "remark", vec![1u8, 2, 3]);
+
+        let call_proof = merkleized.proof_for_extrinsic_parts(&remark, None).unwrap();
+
+        // The signed extension data must decode correctly:
+        let missing = SignedExtrinsicData {
+            included_in_extrinsic: &[],
+            included_in_signed_data: &[],
+        };
+        assert!(matches!(
+            merkleized.proof_for_extrinsic_parts(&remark, Some(missing)),
+            Err(MerkleizeError::Decode(_, _))
+        ));
+
+        let extrinsic = merkleized.extrinsic_metadata();
+        let (mut extra, mut additional) = (Vec::new(), Vec::new());
+        for ext in &extrinsic.signed_extensions {
+            encode_zero(ext.included_in_extrinsic, merkleized.types(), &mut extra);
+            encode_zero(
+                ext.included_in_signed_data,
+                merkleized.types(),
+                &mut additional,
+            );
+        }
+        let signed_ext_data = SignedExtrinsicData {
+            included_in_extrinsic: &extra,
+            included_in_signed_data: &additional,
+        };
+        let proof = merkleized
+            .proof_for_extrinsic_parts(&remark, Some(signed_ext_data))
+            .unwrap();
+        assert_eq!(proof.root(), Some(merkleized.types_tree_root()));
+
+        // Every address and signature type is needed, since we don't know which will be used:
+        let has_type = |proof: &Proof, ty: TypeRef| {
+            let id = ty.id().unwrap();
+            let in_proof = proof.leaves.iter().filter(|l| l.type_id.0 == id).count();
+            let in_tree = merkleized
+                .types()
+                .iter()
+                .filter(|l| l.type_id.0 == id)
+                .count();
+            in_proof == in_tree
+        };
+        assert!(has_type(&proof, extrinsic.address_ty));
+        assert!(has_type(&proof, extrinsic.signature_ty));
+        assert!(!has_type(&call_proof, extrinsic.address_ty));
+        assert!(call_proof.leaves.iter().all(|l| proof.leaves.contains(l)));
+    }
+
+    /// Encode the "zero" value of some type: zero for numbers and the first variant of enums.
+    fn encode_zero(ty: TypeRef, types: &[Type], out: &mut Vec<u8>) {
+        let Some(id) = ty.id() else {
+            let len = match ty {
+                TypeRef::Void => 0,
+                TypeRef::Bool | TypeRef::U8 | TypeRef::I8 => 1,
+                TypeRef::U16 | TypeRef::I16 => 2,
+                TypeRef::Char | TypeRef::U32 | TypeRef::I32 => 4,
+                TypeRef::U64 | TypeRef::I64 => 8,
+                TypeRef::U128 | TypeRef::I128 => 16,
+                TypeRef::U256 | TypeRef::I256 => 32,
+                // Compact zero and empty strings are a single zero byte:
+                _ => 1,
+            };
+            out.extend(std::iter::repeat(0).take(len));
+            return;
+        };
+
+        let ty = types.iter().find(|t| t.type_id.0 == id).unwrap();
+        match &ty.type_def {
+            types::TypeDef::Composite(fields) => {
+                fields.iter().for_each(|f| encode_zero(f.ty, types, out))
+            }
+            types::TypeDef::Enumeration(v) => {
+                out.push(v.index.0 as u8);
+                v.fields.iter().for_each(|f| encode_zero(f.ty, types, out));
+            }
+            types::TypeDef::Array(a) => {
+                (0..a.len).for_each(|_| encode_zero(a.type_param, types, out))
+            }
+            types::TypeDef::Tuple(tys) => tys.iter().for_each(|t| encode_zero(*t, types, out)),
+            types::TypeDef::Sequence(_) | types::TypeDef::BitSequence(_) => out.push(0),
+        }
+    }
+}
@@ -0,0 +1,250 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! The types which make up merkleized metadata. These are a simplified form of the types
+//! found in the metadata type registry, and are SCALE encoded and hashed as described in
+//! RFC-0078.
+
+use codec::{Compact, Decode, Encode};
+
+/// A 32 byte hash, as produced by `blake3`.
+pub type Hash = [u8; 32];
+
+/// Hash some bytes in the way that merkleized metadata is hashed.
+pub(super) fn hash(bytes: &[u8]) -> Hash {
+    blake3::hash(bytes).into()
+}
+
+/// A reference to a type. Primitive, compact and empty types are referenced inline, and
+/// everything else is referenced by its ID in the merkleized type list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
+pub enum TypeRef {
+    /// A `bool`.
+    #[codec(index = 0)]
+    Bool,
+    /// A `char`.
+    #[codec(index = 1)]
+    Char,
+    /// A `str`.
+    #[codec(index = 2)]
+    Str,
+    /// A `u8`.
+    #[codec(index = 3)]
+    U8,
+    /// A `u16`.
+    #[codec(index = 4)]
+    U16,
+    /// A `u32`.
+    #[codec(index = 5)]
+    U32,
+    /// A `u64`.
+    #[codec(index = 6)]
+    U64,
+    /// A `u128`.
+    #[codec(index = 7)]
+    U128,
+    /// A `u256`.
+    #[codec(index = 8)]
+    U256,
+    /// An `i8`.
+    #[codec(index = 9)]
+    I8,
+    /// An `i16`.
+    #[codec(index = 10)]
+    I16,
+    /// An `i32`.
+    #[codec(index = 11)]
+    I32,
+    /// An `i64`.
+    #[codec(index = 12)]
+    I64,
+    /// An `i128`.
+    #[codec(index = 13)]
+    I128,
+    /// An `i256`.
+    #[codec(index = 14)]
+    I256,
+    /// A compact encoded `u8`.
+    #[codec(index = 15)]
+    CompactU8,
+    /// A compact encoded `u16`.
+    #[codec(index = 16)]
+    CompactU16,
+    /// A compact encoded `u32`.
+    #[codec(index = 17)]
+    CompactU32,
+    /// A compact encoded `u64`.
+    #[codec(index = 18)]
+    CompactU64,
+    /// A compact encoded `u128`.
+    #[codec(index = 19)]
+    CompactU128,
+    /// A compact encoded `u256`.
+    #[codec(index = 20)]
+    CompactU256,
+    /// A type which takes no bytes to encode.
+    #[codec(index = 21)]
+    Void,
+    /// A reference to some [`Type`] by its ID.
+    #[codec(index = 22)]
+    ById(Compact<u32>),
+}
+
+impl TypeRef {
+    /// The ID of the type being referenced, if it's referenced by ID.
+    pub fn id(&self) -> Option<u32> {
+        match self {
+            TypeRef::ById(id) => Some(id.0),
+            _ => None,
+        }
+    }
+}
+
+/// A single type. Enums are split into one [`Type`] per variant, each with the same ID.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct Type {
+    /// The path to the type. This is empty for built-in types.
+    pub path: Vec<String>,
+    /// The shape of the type.
+    pub type_def: TypeDef,
+    /// The ID of the type.
+    pub type_id: Compact<u32>,
+}
+
+impl Type {
+    /// The hash of this type, which is a leaf of the merkle tree.
+    pub fn hash(&self) -> Hash {
+        hash(&self.encode())
+    }
+}
+
+/// The shape of a [`Type`].
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum TypeDef {
+    /// A struct or tuple struct with some fields.
+    #[codec(index = 0)]
+    Composite(Vec<Field>),
+    /// A single variant of an enum.
+    #[codec(index = 1)]
+    Enumeration(EnumerationVariant),
+    /// A sequence of some other type, whose length is only known at runtime.
+    #[codec(index = 2)]
+    Sequence(TypeRef),
+    /// An array of some other type, whose length is known up front.
+    #[codec(index = 3)]
+    Array(TypeDefArray),
+    /// A tuple of other types.
+    #[codec(index = 4)]
+    Tuple(Vec<TypeRef>),
+    /// A sequence of bits.
+    #[codec(index = 5)]
+    BitSequence(TypeDefBitSequence),
+}
+
+/// A field in a composite type or enum variant.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct Field {
+    /// The name of the field, if it has one.
+    pub name: Option<String>,
+    /// The type of the field.
+    pub ty: TypeRef,
+    /// The name of the field's type as written in the source, if known.
+    pub type_name: Option<String>,
+}
+
+/// A single variant of an enum.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct EnumerationVariant {
+    /// The name of the variant.
+    pub name: String,
+    /// The fields of the variant.
+    pub fields: Vec<Field>,
+    /// The index of the variant.
+    pub index: Compact<u32>,
+}
+
+/// An array of some other type.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct TypeDefArray {
+    /// The length of the array.
+    pub len: u32,
+    /// The type of each item in the array.
+    pub type_param: TypeRef,
+}
+
+/// A sequence of bits.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct TypeDefBitSequence {
+    /// The number of bytes in each store item.
+    pub num_bytes: u8,
+    /// Whether the bits are stored least significant bit first (`Lsb0`) or not (`Msb0`).
+    pub least_significant_bit_first: bool,
+}
+
+/// The information needed to decode an extrinsic, given the types it refers to.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct ExtrinsicMetadata {
+    /// The extrinsic version.
+    pub version: u8,
+    /// The type of the address that signs the extrinsic.
+    pub address_ty: TypeRef,
+    /// The type of the outermost call enum.
+    pub call_ty: TypeRef,
+    /// The type of the extrinsic's signature.
+    pub signature_ty: TypeRef,
+    /// The signed extensions, in the order that they appear in the extrinsic.
+    pub signed_extensions: Vec<SignedExtensionMetadata>,
+}
+
+impl ExtrinsicMetadata {
+    /// The hash of this extrinsic metadata.
+    pub fn hash(&self) -> Hash {
+        hash(&self.encode())
+    }
+}
+
+/// The types of a single signed extension.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct SignedExtensionMetadata {
+    /// The name of the signed extension.
+    pub identifier: String,
+    /// The type of the data which is included in the extrinsic.
+    pub included_in_extrinsic: TypeRef,
+    /// The type of the data which is signed but not included in the extrinsic.
+    pub included_in_signed_data: TypeRef,
+}
+
+/// A digest of the metadata. The hash of this, given by [`MetadataDigest::hash()`], is the
+/// "metadata hash" which is used in the `CheckMetadataHash` signed extension.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum MetadataDigest {
+    /// The metadata hash is not in use.
+    #[codec(index = 0)]
+    Disabled,
+    /// Version 1 of the metadata digest.
+    #[codec(index = 1)]
+    V1 {
+        /// The root hash of the merkle tree of types.
+        types_tree_root: Hash,
+        /// The hash of the [`ExtrinsicMetadata`].
+        extrinsic_metadata_hash: Hash,
+        /// The spec version of the runtime.
+        spec_version: u32,
+        /// The spec name of the runtime.
+        spec_name: String,
+        /// The SS58 prefix used for addresses on the chain.
+        base58_prefix: u16,
+        /// The number of decimals that the native token has.
+        decimals: u8,
+        /// The symbol of the native token.
+        token_symbol: String,
+    },
+}
+
+impl MetadataDigest {
+    /// The hash of this digest; the "metadata hash".
+    pub fn hash(&self) -> Hash {
+        hash(&self.encode())
+    }
+}
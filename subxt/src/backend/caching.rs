@@ -0,0 +1,553 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! This module exposes a [`CachingBackend`], which wraps some other [`Backend`] and
+//! memoizes the results of requests made at a given block hash. Such results cannot change
+//! once a block exists, and so can be handed back again without asking the underlying
+//! backend, which is useful when the same blocks are processed several times.
+
+use crate::backend::{
+    Backend, BlockRef, RuntimeVersion, StorageResponse, StreamOf, StreamOfResults,
+    TransactionStatus,
+};
+use crate::error::Error;
+use crate::Config;
+use async_trait::async_trait;
+use codec::{Decode, Encode};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// Configure and build a [`CachingBackend`].
+#[derive(Debug, Clone)]
+pub struct CachingBackendBuilder<T> {
+    storage_capacity: usize,
+    block_header_capacity: usize,
+    block_body_capacity: usize,
+    call_capacity: usize,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Config> Default for CachingBackendBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config> CachingBackendBuilder<T> {
+    /// Create a new [`CachingBackendBuilder`] with some sensible default cache sizes.
+    pub fn new() -> Self {
+        Self {
+            storage_capacity: 10_000,
+            block_header_capacity: 1_000,
+            block_body_capacity: 256,
+            call_capacity: 1_000,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// The maximum number of storage values to cache. Each value fetched at each block
+    /// hash counts as one entry. Setting this to 0 disables storage caching.
+    pub fn storage_capacity(mut self, capacity: usize) -> Self {
+        self.storage_capacity = capacity;
+        self
+    }
+
+    /// The maximum number of block headers to cache. Setting this to 0 disables header caching.
+    pub fn block_header_capacity(mut self, capacity: usize) -> Self {
+        self.block_header_capacity = capacity;
+        self
+    }
+
+    /// The maximum number of block bodies to cache. Setting this to 0 disables body caching.
+    pub fn block_body_capacity(mut self, capacity: usize) -> Self {
+        self.block_body_capacity = capacity;
+        self
+    }
+
+    /// The maximum number of runtime API call results to cache. Setting this to 0
+    /// disables runtime API call caching.
+    pub fn call_capacity(mut self, capacity: usize) -> Self {
+        self.call_capacity = capacity;
+        self
+    }
+
+    /// Build a [`CachingBackend`] which wraps the given backend.
+    pub fn build<B: Backend<T>>(self, backend: B) -> CachingBackend<T, B> {
+        let caches = Caches {
+            storage: LruCache::new(self.storage_capacity),
+            block_headers: LruCache::new(self.block_header_capacity),
+            block_bodies: LruCache::new(self.block_body_capacity),
+            calls: LruCache::new(self.call_capacity),
+        };
+
+        CachingBackend {
+            inner: backend,
+            caches: Arc::new(Mutex::new(caches)),
+        }
+    }
+}
+
+/// A [`Backend`] which wraps some other [`Backend`], and caches the results of calls to
+/// [`Backend::storage_fetch_values`], [`Backend::block_header`], [`Backend::block_body`]
+/// and [`Backend::call`]. Each cache holds a limited number of entries, and the least
+/// recently used entries are discarded first once this limit is reached.
+///
+/// All other calls are passed straight through to the wrapped backend.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::sync::Arc;
+/// use subxt::backend::{caching::CachingBackend, legacy::LegacyBackend, rpc::RpcClient};
+/// use subxt::{OnlineClient, PolkadotConfig};
+///
+/// let rpc_client = RpcClient::from_url("ws://127.0.0.1:9944").await.unwrap();
+/// let backend = CachingBackend::builder()
+///     .storage_capacity(50_000)
+///     .build(LegacyBackend::<PolkadotConfig>::new(rpc_client));
+///
+/// let api = OnlineClient::<PolkadotConfig>::from_backend(Arc::new(backend))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub struct CachingBackend<T: Config, B> {
+    inner: B,
+    caches: Arc<Mutex<Caches<T>>>,
+}
+
+impl<T: Config, B: Clone> Clone for CachingBackend<T, B> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            caches: self.caches.clone(),
+        }
+    }
+}
+
+impl<T: Config, B: std::fmt::Debug> std::fmt::Debug for CachingBackend<T, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachingBackend")
+            .field("inner", &self.inner)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl<T: Config> CachingBackend<T, ()> {
+    /// Configure and construct a [`CachingBackend`].
+    pub fn builder() -> CachingBackendBuilder<T> {
+        CachingBackendBuilder::new()
+    }
+}
+
+impl<T: Config, B> CachingBackend<T, B> {
+    /// Return the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Return the current hit and miss statistics for each of the caches.
+    pub fn stats(&self) -> CachingBackendStats {
+        let caches = self.caches.lock().unwrap();
+        CachingBackendStats {
+            storage: caches.storage.stats(),
+            block_headers: caches.block_headers.stats(),
+            block_bodies: caches.block_bodies.stats(),
+            calls: caches.calls.stats(),
+        }
+    }
+
+    /// Remove everything from the caches. Statistics are not reset.
+    pub fn clear(&self) {
+        let mut caches = self.caches.lock().unwrap();
+        caches.storage.clear();
+        caches.block_headers.clear();
+        caches.block_bodies.clear();
+        caches.calls.clear();
+    }
+}
+
+/// Statistics about each of the caches in a [`CachingBackend`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CachingBackendStats {
+    /// Statistics for cached storage values.
+    pub storage: CacheStats,
+    /// Statistics for cached block headers.
+    pub block_headers: CacheStats,
+    /// Statistics for cached block bodies.
+    pub block_bodies: CacheStats,
+    /// Statistics for cached runtime API call results.
+    pub calls: CacheStats,
+}
+
+/// Statistics about a single cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// How many times a value was found in the cache.
+    pub hits: u64,
+    /// How many times a value was not found in the cache and had to be fetched.
+    pub misses: u64,
+    /// How many entries are currently in the cache.
+    pub len: usize,
+}
+
+struct Caches<T: Config> {
+    // Values which don't exist are cached too, as `None`.
+    storage: LruCache<(T::Hash, Vec<u8>), Option<Vec<u8>>>,
+    // Headers are stored encoded since they aren't necessarily `Clone`.
+    block_headers: LruCache<T::Hash, Vec<u8>>,
+    block_bodies: LruCache<T::Hash, Vec<Vec<u8>>>,
+    calls: LruCache<(T::Hash, String, Vec<u8>), Vec<u8>>,
+}
+
+#[async_trait]
+impl<T: Config + Send + Sync + 'static, B: Backend<T>> Backend<T> for CachingBackend<T, B> {
+    async fn storage_fetch_values(
+        &self,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        // Hand back what we can from the cache, noting which keys we need to fetch.
+        let mut responses = Vec::with_capacity(keys.len());
+        let mut missing = Vec::new();
+        {
+            let mut caches = self.caches.lock().unwrap();
+            for key in keys {
+                let cache_key = (at, key);
+                match caches.storage.get(&cache_key) {
+                    Some(Some(value)) => {
+                        let value = value.clone();
+                        responses.push(Ok(StorageResponse {
+                            key: cache_key.1,
+                            value,
+                        }));
+                    }
+                    Some(None) => {}
+                    None => missing.push(cache_key.1),
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            let mut fetched = HashMap::new();
+            let mut stream = self.inner.storage_fetch_values(missing.clone(), at).await?;
+            while let Some(res) = stream.next().await {
+                let StorageResponse { key, value } = res?;
+                fetched.insert(key, value);
+            }
+
+            let mut caches = self.caches.lock().unwrap();
+            for key in missing {
+                let value = fetched.remove(&key);
+                if let Some(value) = &value {
+                    responses.push(Ok(StorageResponse {
+                        key: key.clone(),
+                        value: value.clone(),
+                    }));
+                }
+                caches.storage.insert((at, key), value);
+            }
+        }
+
+        Ok(StreamOf::new(Box::pin(futures::stream::iter(responses))))
+    }
+
+    async fn storage_fetch_descendant_keys(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<Vec<u8>>, Error> {
+        self.inner.storage_fetch_descendant_keys(key, at).await
+    }
+
+    async fn storage_fetch_descendant_values(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        self.inner.storage_fetch_descendant_values(key, at).await
+    }
+
+    async fn genesis_hash(&self) -> Result<T::Hash, Error> {
+        self.inner.genesis_hash().await
+    }
+
+    async fn block_header(&self, at: T::Hash) -> Result<Option<T::Header>, Error> {
+        if let Some(header) = self.caches.lock().unwrap().block_headers.get(&at) {
+            return Ok(Some(T::Header::decode(&mut &**header)?));
+        }
+
+        // Only headers that exist are cached; one that doesn't may show up later.
+        let header = self.inner.block_header(at).await?;
+        if let Some(header) = &header {
+            let mut caches = self.caches.lock().unwrap();
+            caches.block_headers.insert(at, header.encode());
+        }
+        Ok(header)
+    }
+
+    async fn block_body(&self, at: T::Hash) -> Result<Option<Vec<Vec<u8>>>, Error> {
+        if let Some(body) = self.caches.lock().unwrap().block_bodies.get(&at) {
+            return Ok(Some(body.clone()));
+        }
+
+        let body = self.inner.block_body(at).await?;
+        if let Some(body) = &body {
+            let mut caches = self.caches.lock().unwrap();
+            caches.block_bodies.insert(at, body.clone());
+        }
+        Ok(body)
+    }
+
+    async fn latest_finalized_block_ref(&self) -> Result<BlockRef<T::Hash>, Error> {
+        self.inner.latest_finalized_block_ref().await
+    }
+
+    async fn latest_best_block_ref(&self) -> Result<BlockRef<T::Hash>, Error> {
+        self.inner.latest_best_block_ref().await
+    }
+
+    async fn current_runtime_version(&self) -> Result<RuntimeVersion, Error> {
+        self.inner.current_runtime_version().await
+    }
+
+    async fn stream_runtime_version(&self) -> Result<StreamOfResults<RuntimeVersion>, Error> {
+        self.inner.stream_runtime_version().await
+    }
+
+    async fn stream_all_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        self.inner.stream_all_block_headers().await
+    }
+
+    async fn stream_best_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        self.inner.stream_best_block_headers().await
+    }
+
+    async fn stream_finalized_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        self.inner.stream_finalized_block_headers().await
+    }
+
+    async fn submit_transaction(
+        &self,
+        extrinsic: &[u8],
+    ) -> Result<StreamOfResults<TransactionStatus<T::Hash>>, Error> {
+        self.inner.submit_transaction(extrinsic).await
+    }
+
+    async fn call(
+        &self,
+        method: &str,
+        call_parameters: Option<&[u8]>,
+        at: T::Hash,
+    ) -> Result<Vec<u8>, Error> {
+        let cache_key = (
+            at,
+            method.to_owned(),
+            call_parameters.unwrap_or_default().to_vec(),
+        );
+        if let Some(output) = self.caches.lock().unwrap().calls.get(&cache_key) {
+            return Ok(output.clone());
+        }
+
+        let output = self.inner.call(method, call_parameters, at).await?;
+        let mut caches = self.caches.lock().unwrap();
+        caches.calls.insert(cache_key, output.clone());
+        Ok(output)
+    }
+}
+
+/// A simple cache which holds up to some number of entries, discarding the
+/// least recently used entry to make room for new ones.
+struct LruCache<K, V> {
+    capacity: usize,
+    // Incremented on each access, so that smaller ticks are less recently used.
+    tick: u64,
+    entries: HashMap<K, (u64, V)>,
+    by_tick: BTreeMap<u64, K>,
+    hits: u64,
+    misses: u64,
+}
+
+impl<K: std::hash::Hash + Eq + Clone, V> LruCache<K, V> {
+    fn new(capacity: usize) -> Self {
+        LruCache {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            by_tick: BTreeMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Return the value for some key, marking it as recently used.
+    fn get(&mut self, key: &K) -> Option<&V> {
+        let Some((tick, value)) = self.entries.get_mut(key) else {
+            self.misses += 1;
+            return None;
+        };
+
+        self.hits += 1;
+        self.tick += 1;
+        let key = self
+            .by_tick
+            .remove(tick)
+            .expect("every entry has a tick; qed");
+        *tick = self.tick;
+        self.by_tick.insert(self.tick, key);
+        Some(value)
+    }
+
+    /// Insert some value, removing the least recently used value if we're at capacity.
+    fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.tick += 1;
+        if let Some((old_tick, _)) = self.entries.insert(key.clone(), (self.tick, value)) {
+            self.by_tick.remove(&old_tick);
+        }
+        self.by_tick.insert(self.tick, key);
+
+        while self.entries.len() > self.capacity {
+            let Some((_, oldest_key)) = self.by_tick.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest_key);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.by_tick.clear();
+    }
+
+    fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            len: self.entries.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::test_utils::InMemoryBackend;
+    use crate::config::Header;
+    use crate::SubstrateConfig;
+
+    #[test]
+    fn lru_cache_discards_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+
+        // Using 1 means that 2 is now the least recently used.
+        assert_eq!(cache.get(&1), Some(&"a"));
+        cache.insert(3, "c");
+
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&1), Some(&"a"));
+        assert_eq!(cache.get(&3), Some(&"c"));
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 3,
+                misses: 1,
+                len: 2
+            }
+        );
+    }
+
+    #[test]
+    fn lru_cache_with_zero_capacity_stores_nothing() {
+        let mut cache = LruCache::new(0);
+        cache.insert(1, "a");
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.stats().len, 0);
+    }
+
+    #[tokio::test]
+    async fn repeated_requests_are_served_from_cache() {
+        let inner = InMemoryBackend::new();
+        let at = inner.header.hash();
+        let backend = CachingBackend::<SubstrateConfig, _>::builder().build(inner.clone());
+
+        for _ in 0..3 {
+            backend.block_header(at).await.unwrap().unwrap();
+            backend.block_body(at).await.unwrap().unwrap();
+            backend.call("Core_version", None, at).await.unwrap();
+        }
+
+        assert_eq!(inner.request_count(), 3);
+        let stats = backend.stats();
+        assert_eq!(stats.block_headers.hits, 2);
+        assert_eq!(stats.block_bodies.hits, 2);
+        assert_eq!(stats.calls.hits, 2);
+        assert_eq!(stats.calls.misses, 1);
+    }
+
+    #[tokio::test]
+    async fn storage_values_are_cached_per_key() {
+        let inner = InMemoryBackend::new();
+        let at = inner.header.hash();
+        let backend = CachingBackend::<SubstrateConfig, _>::builder().build(inner.clone());
+
+        let fetch = |keys: Vec<Vec<u8>>| {
+            let backend = backend.clone();
+            async move {
+                let mut stream = backend.storage_fetch_values(keys, at).await.unwrap();
+                let mut values = Vec::new();
+                while let Some(res) = stream.next().await {
+                    let res = res.unwrap();
+                    values.push((res.key, res.value));
+                }
+                values.sort();
+                values
+            }
+        };
+
+        // [9, 9, 9] doesn't exist, and that should be cached too.
+        let values = fetch(vec![vec![1, 2, 3], vec![9, 9, 9]]).await;
+        assert_eq!(values, vec![(vec![1, 2, 3], vec![1])]);
+        assert_eq!(inner.request_count(), 1);
+
+        // Only the key that we've not seen before is fetched.
+        let values = fetch(vec![vec![1, 2, 3], vec![9, 9, 9], vec![1, 2, 4]]).await;
+        assert_eq!(
+            values,
+            vec![(vec![1, 2, 3], vec![1]), (vec![1, 2, 4], vec![2])]
+        );
+        assert_eq!(inner.request_count(), 2);
+
+        // Everything is now cached.
+        fetch(vec![vec![1, 2, 4], vec![9, 9, 9]]).await;
+        assert_eq!(inner.request_count(), 2);
+        assert_eq!(
+            backend.stats().storage,
+            CacheStats {
+                hits: 4,
+                misses: 3,
+                len: 3
+            }
+        );
+
+        // Clearing the cache means that we'll fetch again.
+        backend.clear();
+        fetch(vec![vec![1, 2, 4]]).await;
+        assert_eq!(inner.request_count(), 3);
+    }
+}
@@ -0,0 +1,481 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! A suite of checks which can be run against any [`Backend`] implementation in order to
+//! verify that it behaves in the same way as some reference implementation (typically the
+//! [`crate::backend::legacy::LegacyBackend`], pointed at a trusted node).
+//!
+//! # Example
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() {
+//! use subxt::backend::{conformance::ConformanceSuite, legacy::LegacyBackend, rpc::RpcClient};
+//! use subxt::PolkadotConfig;
+//!
+//! let rpc_client = RpcClient::from_url("ws://127.0.0.1:9944").await.unwrap();
+//! let reference = LegacyBackend::<PolkadotConfig>::new(rpc_client.clone());
+//!
+//! // This would be your own `Backend` implementation:
+//! let candidate = LegacyBackend::<PolkadotConfig>::new(rpc_client);
+//!
+//! let report = ConformanceSuite::<PolkadotConfig, _, _>::new(&reference, &candidate)
+//!     // The System.Account storage prefix:
+//!     .storage_prefix(hex::decode("26aa394eea5630e07c48ae0c9558cef7b99d880ec681799c0cf30e8886371da9").unwrap())
+//!     .run()
+//!     .await;
+//!
+//! report.assert_ok();
+//! # }
+//! ```
+
+use super::{Backend, StorageResponse};
+use crate::config::Header;
+use crate::error::Error;
+use crate::Config;
+use codec::Encode;
+use std::future::Future;
+
+/// Configure and run a set of conformance checks, comparing the results of a candidate
+/// [`Backend`] with those of a reference [`Backend`] at the latest finalized block.
+pub struct ConformanceSuite<'a, T: Config, R: ?Sized, C: ?Sized> {
+    reference: &'a R,
+    candidate: &'a C,
+    storage_keys: Vec<Vec<u8>>,
+    storage_prefixes: Vec<Vec<u8>>,
+    runtime_calls: Vec<(String, Option<Vec<u8>>)>,
+    check_block_streams: bool,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<'a, T, R, C> ConformanceSuite<'a, T, R, C>
+where
+    T: Config,
+    R: Backend<T> + ?Sized,
+    C: Backend<T> + ?Sized,
+{
+    /// Create a new conformance suite, which will check that `candidate` returns the
+    /// same results as `reference`.
+    ///
+    /// By default, the `Core_version` runtime API is called and block streams are checked.
+    /// Storage is only checked for the keys and prefixes that are explicitly provided.
+    pub fn new(reference: &'a R, candidate: &'a C) -> Self {
+        ConformanceSuite {
+            reference,
+            candidate,
+            storage_keys: Vec::new(),
+            storage_prefixes: Vec::new(),
+            runtime_calls: vec![("Core_version".to_owned(), None)],
+            check_block_streams: true,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Check that fetching the value at this storage key gives back the same result
+    /// from both backends.
+    pub fn storage_key(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.storage_keys.push(key.into());
+        self
+    }
+
+    /// Check that fetching the keys and values underneath this storage prefix gives back
+    /// the same results from both backends.
+    pub fn storage_prefix(mut self, prefix: impl Into<Vec<u8>>) -> Self {
+        self.storage_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Check that calling this runtime API gives back the same result from both backends.
+    pub fn runtime_call(
+        mut self,
+        method: impl Into<String>,
+        call_parameters: Option<Vec<u8>>,
+    ) -> Self {
+        self.runtime_calls.push((method.into(), call_parameters));
+        self
+    }
+
+    /// Should we check the block header streams? These checks wait for new blocks to be
+    /// produced, and so will not complete against a chain which isn't producing blocks.
+    /// Defaults to `true`.
+    pub fn check_block_streams(mut self, check: bool) -> Self {
+        self.check_block_streams = check;
+        self
+    }
+
+    /// Run each of the configured checks, returning a report detailing the outcome of each.
+    pub async fn run(self) -> ConformanceReport {
+        let mut report = ConformanceReport::default();
+        let (reference, candidate) = (self.reference, self.candidate);
+
+        // Everything is compared at the reference backend's latest finalized block.
+        let at = match reference.latest_finalized_block_ref().await {
+            Ok(block_ref) => block_ref.hash(),
+            Err(e) => {
+                report.push(
+                    "latest_finalized_block_ref",
+                    Err(format!("reference backend failed: {e}")),
+                );
+                return report;
+            }
+        };
+
+        report.push(
+            "genesis_hash",
+            compare(reference.genesis_hash(), candidate.genesis_hash()).await,
+        );
+
+        report.push(
+            "block_header",
+            compare(
+                async { Ok(reference.block_header(at).await?.map(|h| h.encode())) },
+                async { Ok(candidate.block_header(at).await?.map(|h| h.encode())) },
+            )
+            .await,
+        );
+
+        report.push(
+            "block_body",
+            compare(reference.block_body(at), candidate.block_body(at)).await,
+        );
+
+        report.push(
+            "current_runtime_version",
+            compare(
+                reference.current_runtime_version(),
+                candidate.current_runtime_version(),
+            )
+            .await,
+        );
+
+        report.push(
+            "stream_runtime_version",
+            check_runtime_version_stream(candidate).await,
+        );
+
+        report.push(
+            "latest_finalized_block_ref",
+            check_block_ref_has_header(candidate, candidate.latest_finalized_block_ref()).await,
+        );
+
+        report.push(
+            "latest_best_block_ref",
+            check_block_ref_has_header(candidate, candidate.latest_best_block_ref()).await,
+        );
+
+        if !self.storage_keys.is_empty() {
+            report.push(
+                "storage_fetch_values",
+                compare(
+                    collect_storage_values(
+                        reference.storage_fetch_values(self.storage_keys.clone(), at),
+                    ),
+                    collect_storage_values(
+                        candidate.storage_fetch_values(self.storage_keys.clone(), at),
+                    ),
+                )
+                .await,
+            );
+        }
+
+        for prefix in &self.storage_prefixes {
+            report.push(
+                "storage_fetch_descendant_keys",
+                compare(
+                    collect_storage_keys(
+                        reference.storage_fetch_descendant_keys(prefix.clone(), at),
+                    ),
+                    collect_storage_keys(
+                        candidate.storage_fetch_descendant_keys(prefix.clone(), at),
+                    ),
+                )
+                .await,
+            );
+            report.push(
+                "storage_fetch_descendant_values",
+                compare(
+                    collect_storage_values(
+                        reference.storage_fetch_descendant_values(prefix.clone(), at),
+                    ),
+                    collect_storage_values(
+                        candidate.storage_fetch_descendant_values(prefix.clone(), at),
+                    ),
+                )
+                .await,
+            );
+        }
+
+        for (method, params) in &self.runtime_calls {
+            report.push(
+                "call",
+                compare(
+                    reference.call(method, params.as_deref(), at),
+                    candidate.call(method, params.as_deref(), at),
+                )
+                .await
+                .map_err(|e| format!("{method}: {e}")),
+            );
+        }
+
+        if self.check_block_streams {
+            report.push(
+                "stream_all_block_headers",
+                check_header_stream(reference, candidate.stream_all_block_headers()).await,
+            );
+            report.push(
+                "stream_best_block_headers",
+                check_header_stream(reference, candidate.stream_best_block_headers()).await,
+            );
+            report.push(
+                "stream_finalized_block_headers",
+                check_header_stream(reference, candidate.stream_finalized_block_headers()).await,
+            );
+        }
+
+        report
+    }
+}
+
+/// The outcome of running a [`ConformanceSuite`].
+#[derive(Debug, Clone, Default)]
+pub struct ConformanceReport {
+    checks: Vec<ConformanceCheck>,
+}
+
+/// The outcome of a single check in a [`ConformanceReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConformanceCheck {
+    /// The name of the check. This is generally the name of the [`Backend`] method
+    /// that was checked.
+    pub name: &'static str,
+    /// `Ok` if the check passed, else a description of why it failed.
+    pub outcome: Result<(), String>,
+}
+
+impl ConformanceReport {
+    fn push(&mut self, name: &'static str, outcome: Result<(), String>) {
+        self.checks.push(ConformanceCheck { name, outcome });
+    }
+
+    /// Every check that was run, in the order that they were run in.
+    pub fn checks(&self) -> &[ConformanceCheck] {
+        &self.checks
+    }
+
+    /// The checks which did not pass.
+    pub fn failures(&self) -> impl Iterator<Item = &ConformanceCheck> {
+        self.checks.iter().filter(|c| c.outcome.is_err())
+    }
+
+    /// Did every check pass?
+    pub fn is_ok(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// Panic with a description of each failure if any check did not pass.
+    /// This is useful when running the suite as part of a test.
+    pub fn assert_ok(&self) {
+        assert!(self.is_ok(), "{self}");
+    }
+}
+
+impl std::fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for check in &self.checks {
+            match &check.outcome {
+                Ok(()) => writeln!(f, "[pass] {}", check.name)?,
+                Err(e) => writeln!(f, "[FAIL] {}: {e}", check.name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check that both futures succeed and return the same value.
+async fn compare<V, RF, CF>(reference: RF, candidate: CF) -> Result<(), String>
+where
+    V: PartialEq + std::fmt::Debug,
+    RF: Future<Output = Result<V, Error>>,
+    CF: Future<Output = Result<V, Error>>,
+{
+    let reference = reference
+        .await
+        .map_err(|e| format!("reference backend failed: {e}"))?;
+    let candidate = candidate
+        .await
+        .map_err(|e| format!("candidate backend failed: {e}"))?;
+
+    if reference != candidate {
+        return Err(format!(
+            "expected {reference:?} but candidate backend returned {candidate:?}"
+        ));
+    }
+    Ok(())
+}
+
+/// Collect storage keys into a sorted vec, so that the order they are returned in doesn't matter.
+async fn collect_storage_keys(
+    fut: impl Future<Output = Result<super::StreamOfResults<Vec<u8>>, Error>>,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let mut keys = Vec::new();
+    let mut stream = fut.await?;
+    while let Some(key) = stream.next().await {
+        keys.push(key?);
+    }
+    keys.sort();
+    Ok(keys)
+}
+
+/// Collect storage values into a sorted vec, so that the order they are returned in doesn't matter.
+async fn collect_storage_values(
+    fut: impl Future<Output = Result<super::StreamOfResults<StorageResponse>, Error>>,
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Error> {
+    let mut values = Vec::new();
+    let mut stream = fut.await?;
+    while let Some(res) = stream.next().await {
+        let StorageResponse { key, value } = res?;
+        values.push((key, value));
+    }
+    values.sort();
+    Ok(values)
+}
+
+/// Check that the first runtime version handed back matches the current one.
+async fn check_runtime_version_stream<T: Config, C: Backend<T> + ?Sized>(
+    candidate: &C,
+) -> Result<(), String> {
+    let current = candidate
+        .current_runtime_version()
+        .await
+        .map_err(|e| format!("candidate backend failed: {e}"))?;
+    let mut stream = candidate
+        .stream_runtime_version()
+        .await
+        .map_err(|e| format!("candidate backend failed: {e}"))?;
+
+    match stream.next().await {
+        None => Err("runtime version stream ended unexpectedly".to_owned()),
+        Some(Err(e)) => Err(format!("candidate backend failed: {e}")),
+        Some(Ok(first)) if first != current => Err(format!(
+            "first runtime version streamed was {first:?}, but current runtime version is {current:?}"
+        )),
+        Some(Ok(_)) => Ok(()),
+    }
+}
+
+/// Check that a header can be obtained for the block ref that's handed back.
+async fn check_block_ref_has_header<T: Config, C: Backend<T> + ?Sized>(
+    candidate: &C,
+    fut: impl Future<Output = Result<super::BlockRef<T::Hash>, Error>>,
+) -> Result<(), String> {
+    let block_ref = fut
+        .await
+        .map_err(|e| format!("candidate backend failed: {e}"))?;
+    match candidate.block_header(block_ref.hash()).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(format!(
+            "no header available for block {:?}",
+            block_ref.hash()
+        )),
+        Err(e) => Err(format!("candidate backend failed: {e}")),
+    }
+}
+
+/// Check that the first header from some stream has the expected hash, and
+/// matches the header that the reference backend has for it.
+async fn check_header_stream<T: Config, R: Backend<T> + ?Sized>(
+    reference: &R,
+    fut: impl Future<
+        Output = Result<super::StreamOfResults<(T::Header, super::BlockRef<T::Hash>)>, Error>,
+    >,
+) -> Result<(), String> {
+    let mut stream = fut
+        .await
+        .map_err(|e| format!("candidate backend failed: {e}"))?;
+    let (header, block_ref) = match stream.next().await {
+        None => return Err("block header stream ended unexpectedly".to_owned()),
+        Some(Err(e)) => return Err(format!("candidate backend failed: {e}")),
+        Some(Ok(next)) => next,
+    };
+
+    if header.hash() != block_ref.hash() {
+        return Err(format!(
+            "header hash {:?} does not match block hash {:?}",
+            header.hash(),
+            block_ref.hash()
+        ));
+    }
+
+    let reference_header = reference
+        .block_header(block_ref.hash())
+        .await
+        .map_err(|e| format!("reference backend failed: {e}"))?;
+    if reference_header.map(|h| h.encode()) != Some(header.encode()) {
+        return Err(format!(
+            "header for block {:?} does not match the reference backend",
+            block_ref.hash()
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::test_utils::InMemoryBackend;
+    use crate::SubstrateConfig;
+
+    #[tokio::test]
+    async fn identical_backends_conform() {
+        let reference = InMemoryBackend::new();
+        let candidate = InMemoryBackend::new();
+
+        let report = ConformanceSuite::<SubstrateConfig, _, _>::new(&reference, &candidate)
+            .storage_key(vec![1, 2, 3])
+            .storage_key(vec![9, 9, 9])
+            .storage_prefix(vec![1, 2])
+            .run()
+            .await;
+
+        report.assert_ok();
+        assert_eq!(report.checks().len(), 14);
+    }
+
+    #[tokio::test]
+    async fn differing_storage_is_reported() {
+        let reference = InMemoryBackend::new();
+        let mut candidate = InMemoryBackend::new();
+        candidate.storage.insert(vec![1, 2, 5], vec![4]);
+
+        let report = ConformanceSuite::<SubstrateConfig, _, _>::new(&reference, &candidate)
+            .storage_key(vec![1, 2, 3])
+            .storage_prefix(vec![1, 2])
+            .check_block_streams(false)
+            .run()
+            .await;
+
+        let failures: Vec<_> = report.failures().map(|c| c.name).collect();
+        assert_eq!(
+            failures,
+            vec![
+                "storage_fetch_descendant_keys",
+                "storage_fetch_descendant_values"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn trait_objects_can_be_checked() {
+        let reference = InMemoryBackend::new();
+        let candidate: Box<dyn Backend<SubstrateConfig>> = Box::new(InMemoryBackend::new());
+
+        let report = ConformanceSuite::new(&reference, &*candidate)
+            .check_block_streams(false)
+            .run()
+            .await;
+
+        report.assert_ok();
+    }
+}
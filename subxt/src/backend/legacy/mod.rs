@@ -12,6 +12,7 @@ use crate::backend::{
     rpc::RpcClient, Backend, BlockRef, RuntimeVersion, StorageResponse, StreamOf, StreamOfResults,
     TransactionStatus,
 };
+use crate::error::RpcError;
 use crate::{config::Header, Config, Error};
 use async_trait::async_trait;
 use futures::{future, future::Either, stream, Future, FutureExt, Stream, StreamExt};
@@ -21,6 +22,46 @@ use std::task::{Context, Poll};
 
 pub use rpc_methods::LegacyRpcMethods;
 
+/// Configure and build a [`LegacyBackend`].
+pub struct LegacyBackendBuilder<T> {
+    max_batch_size: usize,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Config> Default for LegacyBackendBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config> LegacyBackendBuilder<T> {
+    /// Create a new [`LegacyBackendBuilder`].
+    pub fn new() -> Self {
+        Self {
+            max_batch_size: rpc_methods::DEFAULT_MAX_BATCH_SIZE,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// When fetching many storage values or block headers at once, the requests are sent to
+    /// the node in JSON-RPC batches of at most this many requests each. Nodes may refuse batches
+    /// which are too large. A size of 0 or 1 disables batching, sending each request individually.
+    ///
+    /// Defaults to [`rpc_methods::DEFAULT_MAX_BATCH_SIZE`].
+    pub fn max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    /// Given an [`RpcClient`] to use to make requests, this returns a [`LegacyBackend`],
+    /// which implements the [`Backend`] trait.
+    pub fn build(self, client: RpcClient) -> LegacyBackend<T> {
+        LegacyBackend {
+            methods: LegacyRpcMethods::new(client).with_max_batch_size(self.max_batch_size),
+        }
+    }
+}
+
 /// The legacy backend.
 #[derive(Debug, Clone)]
 pub struct LegacyBackend<T> {
@@ -30,13 +71,14 @@ pub struct LegacyBackend<T> {
 impl<T: Config> LegacyBackend<T> {
     /// Instantiate a new backend which uses the legacy API methods.
     pub fn new(client: RpcClient) -> Self {
-        Self {
-            methods: LegacyRpcMethods::new(client),
-        }
+        Self::builder().build(client)
     }
-}
 
-impl<T: Config> super::sealed::Sealed for LegacyBackend<T> {}
+    /// Configure and construct a [`LegacyBackend`].
+    pub fn builder() -> LegacyBackendBuilder<T> {
+        LegacyBackendBuilder::new()
+    }
+}
 
 #[async_trait]
 impl<T: Config + Send + Sync + 'static> Backend<T> for LegacyBackend<T> {
@@ -46,22 +88,35 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for LegacyBackend<T> {
         at: T::Hash,
     ) -> Result<StreamOfResults<StorageResponse>, Error> {
         let methods = self.methods.clone();
+        let batch_size = methods.max_batch_size().max(1);
 
-        // For each key, return it + a future to get the result.
-        let iter = keys.into_iter().map(move |key| {
+        // Fetch the values for each batch of keys in turn.
+        let s = stream::unfold(keys.into_iter(), move |mut remaining_keys| {
             let methods = methods.clone();
             async move {
-                let res = methods.state_get_storage(&key, Some(at)).await?;
-                Ok(res.map(|value| StorageResponse { key, value }))
+                let keys: Vec<_> = remaining_keys.by_ref().take(batch_size).collect();
+                if keys.is_empty() {
+                    return None;
+                }
+                let res = methods
+                    .state_get_storage_batch(&keys, Some(at))
+                    .await
+                    .map(|values| (keys, values));
+                Some((res, remaining_keys))
+            }
+        })
+        .flat_map(|res| match res {
+            Ok((keys, values)) => {
+                // Filter any Options out (ie if we didn't find a value at some key we return nothing for it).
+                let responses = keys
+                    .into_iter()
+                    .zip(values)
+                    .filter_map(|(key, value)| Some(Ok(StorageResponse { key, value: value? })));
+                Either::Left(stream::iter(responses))
             }
+            Err(e) => Either::Right(stream::once(future::ready(Err(e)))),
         });
 
-        let s = stream::iter(iter)
-            // Resolve the future
-            .then(|fut| fut)
-            // Filter any Options out (ie if we didn't find a value at some key we return nothing for it).
-            .filter_map(|r| future::ready(r.transpose()));
-
         Ok(StreamOf(Box::pin(s)))
     }
 
@@ -143,7 +198,12 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for LegacyBackend<T> {
     }
 
     async fn stream_runtime_version(&self) -> Result<StreamOfResults<RuntimeVersion>, Error> {
+        let methods = self.methods.clone();
         let sub = self.methods.state_subscribe_runtime_version().await?;
+        let sub = resubscribe_on_reconnect(sub, move || {
+            let methods = methods.clone();
+            async move { methods.state_subscribe_runtime_version().await }
+        });
         let sub = sub.map(|r| {
             r.map(|v| RuntimeVersion {
                 spec_version: v.spec_version,
@@ -156,7 +216,12 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for LegacyBackend<T> {
     async fn stream_all_block_headers(
         &self,
     ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        let methods = self.methods.clone();
         let sub = self.methods.chain_subscribe_all_heads().await?;
+        let sub = resubscribe_on_reconnect(sub, move || {
+            let methods = methods.clone();
+            async move { methods.chain_subscribe_all_heads().await }
+        });
         let sub = sub.map(|r| {
             r.map(|h| {
                 let hash = h.hash();
@@ -169,7 +234,12 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for LegacyBackend<T> {
     async fn stream_best_block_headers(
         &self,
     ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        let methods = self.methods.clone();
         let sub = self.methods.chain_subscribe_new_heads().await?;
+        let sub = resubscribe_on_reconnect(sub, move || {
+            let methods = methods.clone();
+            async move { methods.chain_subscribe_new_heads().await }
+        });
         let sub = sub.map(|r| {
             r.map(|h| {
                 let hash = h.hash();
@@ -192,6 +262,14 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for LegacyBackend<T> {
             .await?
             .map(|h| h.number().into());
 
+        // If the connection is lost and re-established, subscribe again. Any blocks finalized
+        // in the meantime will be filled in below.
+        let methods = self.methods.clone();
+        let sub = resubscribe_on_reconnect(sub, move || {
+            let methods = methods.clone();
+            async move { methods.chain_subscribe_finalized_heads().await }
+        });
+
         // Fill in any missing blocks, because the backend may not emit every finalized block; just the latest ones which
         // are finalized each time.
         let sub = subscribe_to_block_headers_filling_in_gaps(
@@ -212,10 +290,22 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for LegacyBackend<T> {
         &self,
         extrinsic: &[u8],
     ) -> Result<StreamOfResults<TransactionStatus<T::Hash>>, Error> {
-        let sub = self
+        let sub = match self
             .methods
             .author_submit_and_watch_extrinsic(extrinsic)
-            .await?;
+            .await
+        {
+            Ok(sub) => sub,
+            // If we can't subscribe (eg we're talking to the node over HTTP), we can still
+            // submit the transaction; we just won't hear about its progress after it's
+            // been validated and added to the pool.
+            Err(Error::Rpc(RpcError::SubscriptionsNotSupported(_))) => {
+                self.methods.author_submit_extrinsic(extrinsic).await?;
+                let sub = stream::once(future::ready(Ok(TransactionStatus::Validated)));
+                return Ok(StreamOf(Box::pin(sub)));
+            }
+            Err(e) => return Err(e),
+        };
         let sub = sub.filter_map(|r| {
             let mapped = r
                 .map(|tx| {
@@ -231,7 +321,9 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for LegacyBackend<T> {
                             })
                         }
                         RpcTransactionStatus::InBlock(hash) => {
-                            Some(TransactionStatus::InBestBlock { hash })
+                            Some(TransactionStatus::InBestBlock {
+                                hash: BlockRef::from_hash(hash),
+                            })
                         }
                         // These 5 mean that the stream will very likely end:
                         RpcTransactionStatus::FinalityTimeout(_) => {
@@ -240,7 +332,9 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for LegacyBackend<T> {
                             })
                         }
                         RpcTransactionStatus::Finalized(hash) => {
-                            Some(TransactionStatus::InFinalizedBlock { hash })
+                            Some(TransactionStatus::InFinalizedBlock {
+                                hash: BlockRef::from_hash(hash),
+                            })
                         }
                         RpcTransactionStatus::Usurped(_) => Some(TransactionStatus::Invalid {
                             message: "Transaction was usurped by another with the same nonce"
@@ -303,17 +397,29 @@ where
 
         // Iterate over all of the previous blocks we need headers for, ignoring the current block
         // (which we already have the header info for):
+        // These are fetched in batches, to avoid a round trip per block.
         let methods = methods.clone();
-        let previous_headers = stream::iter(start_block_num..end_block_num)
-            .then(move |n| {
-                let methods = methods.clone();
-                async move {
-                    let hash = methods.chain_get_block_hash(Some(n.into())).await?;
-                    let header = methods.chain_get_header(hash).await?;
-                    Ok::<_, Error>(header)
+        let batch_size = methods.max_batch_size().max(1) as u64;
+        let previous_headers = stream::unfold(start_block_num, move |from| {
+            let methods = methods.clone();
+            async move {
+                if from >= end_block_num {
+                    return None;
                 }
-            })
-            .filter_map(|h| async { h.transpose() });
+                let to = end_block_num.min(from.saturating_add(batch_size));
+                let block_nums: Vec<_> = (from..to).map(Into::into).collect();
+                let headers = async {
+                    let hashes = methods.chain_get_block_hash_batch(&block_nums).await?;
+                    let hashes: Vec<_> = hashes.into_iter().flatten().collect();
+                    methods.chain_get_header_batch(&hashes).await
+                };
+                Some((headers.await, to))
+            }
+        })
+        .flat_map(|headers| match headers {
+            Ok(headers) => Either::Left(stream::iter(headers.into_iter().flatten().map(Ok))),
+            Err(e) => Either::Right(stream::once(future::ready(Err(e)))),
+        });
 
         // On the next iteration, we'll get details starting just after this end block.
         last_block_num = Some(end_block_num);
@@ -323,6 +429,55 @@ where
     })
 }
 
+/// Hand back the items from a subscription, subscribing again using the function provided
+/// if it ends with [`RpcError::DisconnectedWillReconnect`], which happens when the connection
+/// is being re-established by something like [`crate::backend::rpc::ReconnectingRpcClient`].
+fn resubscribe_on_reconnect<Item, S, F, Fut>(
+    sub: S,
+    mut resubscribe: F,
+) -> impl Stream<Item = Result<Item, Error>> + Send
+where
+    Item: Send + 'static,
+    S: Stream<Item = Result<Item, Error>> + Send + 'static,
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<S, Error>> + Send,
+{
+    enum State<S> {
+        Subscribed(Pin<Box<S>>),
+        Disconnected,
+    }
+
+    stream::unfold(Some(State::Subscribed(Box::pin(sub))), move |state| {
+        // Start subscribing again if the last subscription was lost.
+        let next = state.map(|state| match state {
+            State::Subscribed(sub) => Either::Left(sub),
+            State::Disconnected => Either::Right(resubscribe()),
+        });
+
+        async move {
+            let mut sub = match next? {
+                Either::Left(sub) => sub,
+                Either::Right(fut) => match fut.await {
+                    Ok(sub) => Box::pin(sub),
+                    // We couldn't subscribe again, so hand back the error and end.
+                    Err(e) => return Some((Some(Err(e)), None)),
+                },
+            };
+
+            match sub.next().await {
+                Some(Err(Error::Rpc(RpcError::DisconnectedWillReconnect(e)))) => {
+                    tracing::debug!("Subscription ended ({e}); subscribing again");
+                    // Emit nothing for now; we'll subscribe again on the next poll.
+                    Some((None, Some(State::Disconnected)))
+                }
+                Some(item) => Some((Some(item), Some(State::Subscribed(sub)))),
+                None => None,
+            }
+        }
+    })
+    .filter_map(future::ready)
+}
+
 /// This provides a stream of values given some prefix `key`. It
 /// internally manages pagination and such.
 pub struct StorageFetchDescendantKeysStream<T: Config> {
@@ -466,3 +621,164 @@ impl<T: Config> Stream for StorageFetchDescendantValuesStream<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::rpc::{RawRpcFuture, RawRpcSubscription, RawValue, RpcClientT};
+    use crate::PolkadotConfig;
+    use std::sync::{Arc, Mutex};
+
+    fn disconnected<T>() -> Result<T, Error> {
+        Err(RpcError::DisconnectedWillReconnect("test".to_owned()).into())
+    }
+
+    #[tokio::test]
+    async fn resubscribes_when_disconnected() {
+        let first = stream::iter(vec![Ok(1), Ok(2), disconnected()]);
+        let mut subs = vec![
+            stream::iter(vec![
+                Ok(5),
+                Err(Error::Other("not a disconnect".to_owned())),
+            ]),
+            stream::iter(vec![Ok(3), Ok(4), disconnected()]),
+        ];
+
+        let items: Vec<_> = resubscribe_on_reconnect(first, move || {
+            let next = subs.pop().expect("too many resubscriptions");
+            async move { Ok(next) }
+        })
+        .map(|r| r.map_err(|e| e.to_string()))
+        .collect()
+        .await;
+
+        assert_eq!(
+            items,
+            vec![
+                Ok(1),
+                Ok(2),
+                Ok(3),
+                Ok(4),
+                Ok(5),
+                Err("Other error: not a disconnect".to_owned())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn ends_if_resubscribing_fails() {
+        let first = stream::iter(vec![Ok(1), disconnected()]);
+        let items: Vec<_> = resubscribe_on_reconnect(first, move || async {
+            Err::<stream::Iter<std::vec::IntoIter<Result<u32, Error>>>, _>(Error::Other(
+                "failed".to_owned(),
+            ))
+        })
+        .map(|r| r.map_err(|e| e.to_string()))
+        .collect()
+        .await;
+
+        assert_eq!(items, vec![Ok(1), Err("Other error: failed".to_owned())]);
+    }
+
+    /// A fake node which answers a couple of requests, can't subscribe to anything,
+    /// and records the size of each batch request made to it.
+    #[derive(Clone, Default)]
+    struct FakeNode {
+        batch_sizes: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl RpcClientT for FakeNode {
+        fn request_raw<'a>(
+            &'a self,
+            method: &'a str,
+            params: Option<Box<RawValue>>,
+        ) -> RawRpcFuture<'a, Box<RawValue>> {
+            Box::pin(async move {
+                let json = match method {
+                    "author_submitExtrinsic" => format!("\"0x{}\"", "00".repeat(32)),
+                    // Hand back the key as the value, unless it's empty.
+                    "state_getStorage" => {
+                        let params: (String, Option<String>) =
+                            serde_json::from_str(params.unwrap().get()).unwrap();
+                        match params.0.as_str() {
+                            "0x" => "null".to_owned(),
+                            key => format!("\"{key}\""),
+                        }
+                    }
+                    _ => panic!("unexpected request: {method}"),
+                };
+                Ok(RawValue::from_string(json).unwrap())
+            })
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            sub: &'a str,
+            _params: Option<Box<RawValue>>,
+            _unsub: &'a str,
+        ) -> RawRpcFuture<'a, RawRpcSubscription> {
+            Box::pin(async move { Err(RpcError::SubscriptionsNotSupported(sub.to_owned())) })
+        }
+
+        fn batch_request_raw<'a>(
+            &'a self,
+            requests: Vec<(&'a str, Option<Box<RawValue>>)>,
+        ) -> RawRpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+            self.batch_sizes.lock().unwrap().push(requests.len());
+            Box::pin(async move {
+                let mut results = Vec::new();
+                for (method, params) in requests {
+                    results.push(self.request_raw(method, params).await);
+                }
+                Ok(results)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn submits_without_watching_if_subscriptions_not_supported() {
+        let backend = LegacyBackend::<PolkadotConfig>::new(RpcClient::new(FakeNode::default()));
+
+        let statuses: Vec<_> = backend
+            .submit_transaction(&[1, 2, 3])
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        assert!(matches!(&statuses[..], [Ok(TransactionStatus::Validated)]));
+    }
+
+    async fn fetch_values_with_batch_size(max_batch_size: usize) -> (Vec<Vec<u8>>, Vec<usize>) {
+        let node = FakeNode::default();
+        let backend = LegacyBackend::<PolkadotConfig>::builder()
+            .max_batch_size(max_batch_size)
+            .build(RpcClient::new(node.clone()));
+
+        let keys = vec![vec![1], vec![2], vec![], vec![4], vec![5]];
+        let values = backend
+            .storage_fetch_values(keys, Default::default())
+            .await
+            .unwrap()
+            .map(|r| r.unwrap().value)
+            .collect()
+            .await;
+
+        let batch_sizes = node.batch_sizes.lock().unwrap().clone();
+        (values, batch_sizes)
+    }
+
+    #[tokio::test]
+    async fn storage_values_are_fetched_in_batches() {
+        let (values, batch_sizes) = fetch_values_with_batch_size(2).await;
+        assert_eq!(values, vec![vec![1], vec![2], vec![4], vec![5]]);
+        assert_eq!(batch_sizes, vec![2, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn storage_values_are_fetched_individually_if_batching_disabled() {
+        let (values, batch_sizes) = fetch_values_with_batch_size(1).await;
+        assert_eq!(values, vec![vec![1], vec![2], vec![4], vec![5]]);
+        assert!(batch_sizes.is_empty());
+    }
+}
@@ -4,18 +4,20 @@
 
 //! An interface to call the raw legacy RPC methods.
 
-use crate::backend::rpc::{rpc_params, RpcClient, RpcSubscription};
+use crate::backend::rpc::{rpc_params, RpcClient, RpcParams, RpcSubscription};
 use crate::metadata::Metadata;
+use crate::tx::DryRunOutcome;
 use crate::{Config, Error};
 use codec::Decode;
 use primitive_types::U256;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 /// An interface to call the legacy RPC methods. This interface is instantiated with
 /// some `T: Config` trait which determines some of the types that the RPC methods will
 /// take or hand back.
 pub struct LegacyRpcMethods<T> {
     client: RpcClient,
+    max_batch_size: usize,
     _marker: std::marker::PhantomData<T>,
 }
 
@@ -23,6 +25,7 @@ impl<T> Clone for LegacyRpcMethods<T> {
     fn clone(&self) -> Self {
         Self {
             client: self.client.clone(),
+            max_batch_size: self.max_batch_size,
             _marker: self._marker,
         }
     }
@@ -32,20 +35,69 @@ impl<T> std::fmt::Debug for LegacyRpcMethods<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("LegacyRpcMethods")
             .field("client", &self.client)
+            .field("max_batch_size", &self.max_batch_size)
             .field("_marker", &self._marker)
             .finish()
     }
 }
 
+/// The default maximum number of requests sent in a single JSON-RPC batch.
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 64;
+
 impl<T: Config> LegacyRpcMethods<T> {
     /// Instantiate the legacy RPC method interface.
     pub fn new(client: RpcClient) -> Self {
         LegacyRpcMethods {
             client,
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
             _marker: std::marker::PhantomData,
         }
     }
 
+    /// Set the maximum number of requests that the `*_batch` methods will send in a single
+    /// JSON-RPC batch request, splitting larger numbers of requests across several batches.
+    /// A size of 0 or 1 disables batching, and requests are then sent one at a time.
+    ///
+    /// Defaults to [`DEFAULT_MAX_BATCH_SIZE`].
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    /// The maximum number of requests that will be sent in a single JSON-RPC batch request.
+    pub fn max_batch_size(&self) -> usize {
+        self.max_batch_size
+    }
+
+    /// Make a request to the given method for each set of params provided, using as few
+    /// batch requests as we're allowed to. Fails if any one of the requests fails.
+    async fn batch_request<Res: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Vec<RpcParams>,
+    ) -> Result<Vec<Res>, Error> {
+        let mut results = Vec::with_capacity(params.len());
+
+        if self.max_batch_size <= 1 {
+            for params in params {
+                results.push(self.client.request(method, params).await?);
+            }
+            return Ok(results);
+        }
+
+        let mut params = params.into_iter().peekable();
+        while params.peek().is_some() {
+            let batch = params
+                .by_ref()
+                .take(self.max_batch_size)
+                .map(|params| (method, params));
+            for res in self.client.batch_request(batch).await? {
+                results.push(res?);
+            }
+        }
+        Ok(results)
+    }
+
     /// Fetch the raw bytes for a given storage key
     pub async fn state_get_storage(
         &self,
@@ -57,6 +109,21 @@ impl<T: Config> LegacyRpcMethods<T> {
         Ok(data.map(|b| b.0))
     }
 
+    /// Fetch the raw bytes for several storage keys at once, sending them in as few
+    /// batch requests as possible. Values are returned in the same order as the keys.
+    pub async fn state_get_storage_batch(
+        &self,
+        keys: &[Vec<u8>],
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<Option<StorageData>>, Error> {
+        let params = keys
+            .iter()
+            .map(|key| rpc_params![to_hex(key), hash])
+            .collect();
+        let data: Vec<Option<Bytes>> = self.batch_request("state_getStorage", params).await?;
+        Ok(data.into_iter().map(|b| b.map(|b| b.0)).collect())
+    }
+
     /// Returns the keys with prefix with pagination support.
     /// Up to `count` keys will be returned.
     /// If `start_key` is passed, return next keys in storage in lexicographic order.
@@ -148,6 +215,18 @@ impl<T: Config> LegacyRpcMethods<T> {
             .await
     }
 
+    /// Fetch the next index (nonce) of some account. Unlike the `AccountNonceApi` runtime
+    /// API, this takes into account any transactions from the account which are waiting
+    /// in the node's transaction pool.
+    pub async fn system_account_next_index(&self, account_id: &T::AccountId) -> Result<u64, Error>
+    where
+        T::AccountId: Serialize,
+    {
+        self.client
+            .request("system_accountNextIndex", rpc_params![account_id])
+            .await
+    }
+
     /// Get a header
     pub async fn chain_get_header(
         &self,
@@ -168,6 +247,26 @@ impl<T: Config> LegacyRpcMethods<T> {
         Ok(block_hash)
     }
 
+    /// Get the block hashes for several block numbers at once, sending them in as few
+    /// batch requests as possible. Hashes are returned in the same order as the numbers.
+    pub async fn chain_get_block_hash_batch(
+        &self,
+        block_numbers: &[BlockNumber],
+    ) -> Result<Vec<Option<T::Hash>>, Error> {
+        let params = block_numbers.iter().map(|n| rpc_params![n]).collect();
+        self.batch_request("chain_getBlockHash", params).await
+    }
+
+    /// Get the headers for several blocks at once, sending them in as few batch requests
+    /// as possible. Headers are returned in the same order as the hashes.
+    pub async fn chain_get_header_batch(
+        &self,
+        hashes: &[T::Hash],
+    ) -> Result<Vec<Option<T::Header>>, Error> {
+        let params = hashes.iter().map(|hash| rpc_params![hash]).collect();
+        self.batch_request("chain_getHeader", params).await
+    }
+
     /// Get a block hash of the latest finalized block
     pub async fn chain_get_finalized_head(&self) -> Result<T::Hash, Error> {
         let hash = self
@@ -387,6 +486,8 @@ impl<T: Config> LegacyRpcMethods<T> {
     /// Submits the extrinsic to the dry_run RPC, to test if it would succeed.
     ///
     /// Returns a [`DryRunResult`], which is the result of performing the dry run.
+    /// See also [`crate::tx::SubmittableExtrinsic::dry_run()`], which works with any
+    /// backend and decodes more of the result.
     pub async fn dry_run(
         &self,
         encoded_signed: &[u8],
@@ -530,24 +631,17 @@ impl DryRunResultBytes {
         self,
         metadata: &crate::metadata::Metadata,
     ) -> Result<DryRunResult, crate::Error> {
-        // dryRun returns an ApplyExtrinsicResult, which is basically a
-        // `Result<Result<(), DispatchError>, TransactionValidityError>`.
-        let bytes = self.0;
-        if bytes[0] == 0 && bytes[1] == 0 {
-            // Ok(Ok(())); transaction is valid and executed ok
-            Ok(DryRunResult::Success)
-        } else if bytes[0] == 0 && bytes[1] == 1 {
-            // Ok(Err(dispatch_error)); transaction is valid but execution failed
-            let dispatch_error =
-                crate::error::DispatchError::decode_from(&bytes[2..], metadata.clone())?;
-            Ok(DryRunResult::DispatchError(dispatch_error))
-        } else if bytes[0] == 1 {
-            // Err(transaction_error); some transaction validity error (we ignore the details at the moment)
-            Ok(DryRunResult::TransactionValidityError)
-        } else {
-            // unable to decode the bytes; they aren't what we expect.
-            Err(crate::Error::Unknown(bytes))
-        }
+        // dryRun returns an ApplyExtrinsicResult, which is decoded in the same way as the
+        // result of calling `BlockBuilder_apply_extrinsic`.
+        let outcome = DryRunOutcome::decode_from(&self.0, metadata.clone())?;
+        Ok(match outcome {
+            DryRunOutcome::Success => DryRunResult::Success,
+            DryRunOutcome::DispatchError(e) => DryRunResult::DispatchError(e),
+            // We ignore the details of transaction validity errors here.
+            DryRunOutcome::Invalid(_)
+            | DryRunOutcome::Unknown(_)
+            | DryRunOutcome::UndecodedValidityError(_) => DryRunResult::TransactionValidityError,
+        })
     }
 }
 
@@ -0,0 +1,646 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! This module exposes a [`MockBackend`], which serves blocks, storage, runtime API call
+//! responses and transaction statuses that have been handed to it, rather than obtaining
+//! them from a node. This allows code built on top of [`crate::OnlineClient`] to be tested
+//! without needing a node to be running. This module is only available when the `mock`
+//! feature is enabled.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() {
+//! # #[cfg(feature = "mock")]
+//! # {
+//! use std::sync::Arc;
+//! use subxt::backend::mock::MockBackend;
+//! use subxt::backend::{RuntimeVersion, TransactionStatus};
+//! use subxt::config::substrate::{Digest, SubstrateHeader};
+//! use subxt::config::Header;
+//! use subxt::{Metadata, OnlineClient, PolkadotConfig};
+//! use subxt::ext::codec::Decode;
+//!
+//! let metadata_bytes = std::fs::read("polkadot_metadata.scale").unwrap();
+//! let metadata = Metadata::decode(&mut &*metadata_bytes).unwrap();
+//!
+//! let backend = MockBackend::<PolkadotConfig>::new();
+//! backend.set_runtime_version(RuntimeVersion { spec_version: 1, transaction_version: 1 });
+//! backend.set_metadata(metadata);
+//!
+//! // Add a block, and some storage at that block:
+//! let block_hash = backend.add_block(
+//!     SubstrateHeader {
+//!         parent_hash: Default::default(),
+//!         number: 1,
+//!         state_root: Default::default(),
+//!         extrinsics_root: Default::default(),
+//!         digest: Digest::default(),
+//!     },
+//!     vec![],
+//! );
+//! backend.set_storage(block_hash, vec![1, 2, 3], vec![4, 5, 6]);
+//!
+//! // Decide how the next transaction that's submitted will progress:
+//! backend.push_transaction_statuses(vec![
+//!     TransactionStatus::Validated,
+//!     TransactionStatus::InBestBlock { hash: block_hash.into() },
+//! ]);
+//!
+//! // Hand the backend to an OnlineClient, and use that as normal:
+//! let api = OnlineClient::<PolkadotConfig>::from_backend(Arc::new(backend.clone()))
+//!     .await
+//!     .unwrap();
+//!
+//! // Later, check which transactions were submitted:
+//! let submitted = backend.submitted_extrinsics();
+//! # }
+//! # }
+//! ```
+
+use crate::backend::{
+    Backend, BlockRef, RuntimeVersion, StorageResponse, StreamOf, StreamOfResults,
+    TransactionStatus,
+};
+use crate::config::Header;
+use crate::error::{Error, RpcError};
+use crate::metadata::Metadata;
+use crate::Config;
+use async_trait::async_trait;
+use codec::{Decode, Encode};
+use futures::channel::mpsc;
+use futures::{stream, StreamExt};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// A [`Backend`] implementation which serves whatever it has been told to, for use in tests.
+///
+/// This is cheap to clone, and clones share the same state, so a clone can be kept hold of to
+/// change what is served or to inspect what was submitted after handing the backend to an
+/// [`crate::OnlineClient`].
+///
+/// - Blocks are added with [`MockBackend::add_block`]. Each new block becomes the current best
+///   and finalized block, and is handed to any block header subscriptions. Use
+///   [`MockBackend::set_best_block`] and [`MockBackend::set_finalized_block`] to change this.
+/// - Storage is set per block with [`MockBackend::set_storage`]. New blocks start out with
+///   a copy of the storage of the best block at the time they were added.
+/// - Runtime API calls are answered with the responses given to
+///   [`MockBackend::set_call_response`]. Calls to fetch the metadata are answered using the
+///   metadata given to [`MockBackend::set_metadata`].
+/// - Each submitted transaction is recorded (see [`MockBackend::submitted_extrinsics`]) and
+///   progresses through the next sequence of statuses given to
+///   [`MockBackend::push_transaction_statuses`], or is just [`TransactionStatus::Validated`]
+///   if none were given. As with a real node, the status stream only ends after a status
+///   like [`TransactionStatus::InFinalizedBlock`] or [`TransactionStatus::Dropped`].
+pub struct MockBackend<T: Config> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+impl<T: Config> Clone for MockBackend<T> {
+    fn clone(&self) -> Self {
+        MockBackend {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T: Config> std::fmt::Debug for MockBackend<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("MockBackend")
+            .field("genesis_hash", &state.genesis_hash)
+            .field("runtime_version", &state.runtime_version)
+            .field("num_blocks", &state.blocks.len())
+            .field("best_block", &state.best_block)
+            .field("finalized_block", &state.finalized_block)
+            .finish()
+    }
+}
+
+impl<T: Config> Default for MockBackend<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct State<T: Config> {
+    genesis_hash: Option<T::Hash>,
+    runtime_version: RuntimeVersion,
+    metadata: Option<Vec<u8>>,
+    // Headers are stored encoded, since they aren't necessarily `Clone`.
+    blocks: HashMap<T::Hash, MockBlock>,
+    best_block: Option<T::Hash>,
+    finalized_block: Option<T::Hash>,
+    storage: HashMap<T::Hash, BTreeMap<Vec<u8>, Vec<u8>>>,
+    call_responses: HashMap<(String, Option<Vec<u8>>), Vec<u8>>,
+    queued_call_responses: HashMap<String, VecDeque<Vec<u8>>>,
+    // Each transaction's statuses, and whether the subscription is lost after them.
+    transaction_statuses: VecDeque<(Vec<TransactionStatus<T::Hash>>, bool)>,
+    submitted_extrinsics: Vec<Vec<u8>>,
+    all_block_subscribers: Vec<mpsc::UnboundedSender<T::Hash>>,
+    best_block_subscribers: Vec<mpsc::UnboundedSender<T::Hash>>,
+    finalized_block_subscribers: Vec<mpsc::UnboundedSender<T::Hash>>,
+    runtime_version_subscribers: Vec<mpsc::UnboundedSender<RuntimeVersion>>,
+}
+
+struct MockBlock {
+    header: Vec<u8>,
+    extrinsics: Vec<Vec<u8>>,
+}
+
+impl<T: Config> MockBackend<T> {
+    /// Create a new [`MockBackend`] which has no blocks, storage or anything else yet.
+    pub fn new() -> Self {
+        MockBackend {
+            state: Arc::new(Mutex::new(State {
+                genesis_hash: None,
+                runtime_version: RuntimeVersion {
+                    spec_version: 0,
+                    transaction_version: 0,
+                },
+                metadata: None,
+                blocks: HashMap::new(),
+                best_block: None,
+                finalized_block: None,
+                storage: HashMap::new(),
+                call_responses: HashMap::new(),
+                queued_call_responses: HashMap::new(),
+                transaction_statuses: VecDeque::new(),
+                submitted_extrinsics: Vec::new(),
+                all_block_subscribers: Vec::new(),
+                best_block_subscribers: Vec::new(),
+                finalized_block_subscribers: Vec::new(),
+                runtime_version_subscribers: Vec::new(),
+            })),
+        }
+    }
+
+    /// Set the genesis hash. If this isn't set, the hash of the first block added
+    /// is used instead.
+    pub fn set_genesis_hash(&self, hash: T::Hash) {
+        self.state.lock().unwrap().genesis_hash = Some(hash);
+    }
+
+    /// Set the current runtime version, handing it to any runtime version subscriptions.
+    pub fn set_runtime_version(&self, runtime_version: RuntimeVersion) {
+        let mut state = self.state.lock().unwrap();
+        state.runtime_version = runtime_version.clone();
+        notify(&mut state.runtime_version_subscribers, runtime_version);
+    }
+
+    /// Set the metadata to hand back from the runtime API calls which fetch it.
+    pub fn set_metadata(&self, metadata: impl Into<Metadata>) {
+        let metadata: Metadata = metadata.into();
+        self.state.lock().unwrap().metadata = Some((*metadata).encode());
+    }
+
+    /// Add a block with the given header and extrinsics, returning its hash. Each extrinsic
+    /// is expected to be SCALE encoded, ie to begin with its compact encoded length. The new
+    /// block becomes the best and finalized block.
+    pub fn add_block(&self, header: T::Header, extrinsics: Vec<Vec<u8>>) -> T::Hash {
+        let hash = header.hash();
+        let mut state = self.state.lock().unwrap();
+
+        let storage = state
+            .best_block
+            .and_then(|best| state.storage.get(&best).cloned())
+            .unwrap_or_default();
+        state.storage.entry(hash).or_insert(storage);
+        state.genesis_hash.get_or_insert(hash);
+        state.blocks.insert(
+            hash,
+            MockBlock {
+                header: header.encode(),
+                extrinsics,
+            },
+        );
+
+        notify(&mut state.all_block_subscribers, hash);
+        state.set_best_block(hash);
+        state.set_finalized_block(hash);
+        hash
+    }
+
+    /// Set the best block, handing it to any best block subscriptions.
+    pub fn set_best_block(&self, hash: T::Hash) {
+        self.state.lock().unwrap().set_best_block(hash);
+    }
+
+    /// Set the finalized block, handing it to any finalized block subscriptions.
+    pub fn set_finalized_block(&self, hash: T::Hash) {
+        self.state.lock().unwrap().set_finalized_block(hash);
+    }
+
+    /// Set the value of some storage key at the given block.
+    pub fn set_storage(&self, at: T::Hash, key: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) {
+        self.state
+            .lock()
+            .unwrap()
+            .storage
+            .entry(at)
+            .or_default()
+            .insert(key.into(), value.into());
+    }
+
+    /// Remove the value at some storage key at the given block.
+    pub fn remove_storage(&self, at: T::Hash, key: &[u8]) {
+        if let Some(storage) = self.state.lock().unwrap().storage.get_mut(&at) {
+            storage.remove(key);
+        }
+    }
+
+    /// Set the SCALE encoded response to hand back from a runtime API call to the given
+    /// method (eg `"AccountNonceApi_account_nonce"`), whatever the parameters given to it.
+    pub fn set_call_response(&self, method: impl Into<String>, response: impl Into<Vec<u8>>) {
+        self.state
+            .lock()
+            .unwrap()
+            .call_responses
+            .insert((method.into(), None), response.into());
+    }
+
+    /// Queue up a SCALE encoded response to hand back from the next runtime API call to the
+    /// given method. Each call queues up the response to one more call, and queued responses
+    /// take precedence over any others given.
+    pub fn push_call_response(&self, method: impl Into<String>, response: impl Into<Vec<u8>>) {
+        self.state
+            .lock()
+            .unwrap()
+            .queued_call_responses
+            .entry(method.into())
+            .or_default()
+            .push_back(response.into());
+    }
+
+    /// Set the SCALE encoded response to hand back from a runtime API call to the given
+    /// method with exactly the given SCALE encoded parameters. This takes precedence over a
+    /// response given to [`MockBackend::set_call_response`].
+    pub fn set_call_response_for_params(
+        &self,
+        method: impl Into<String>,
+        params: impl Into<Vec<u8>>,
+        response: impl Into<Vec<u8>>,
+    ) {
+        self.state
+            .lock()
+            .unwrap()
+            .call_responses
+            .insert((method.into(), Some(params.into())), response.into());
+    }
+
+    /// Queue up the sequence of statuses that the next submitted transaction will progress
+    /// through. Each call queues up the statuses for one more transaction.
+    pub fn push_transaction_statuses(&self, statuses: Vec<TransactionStatus<T::Hash>>) {
+        self.state
+            .lock()
+            .unwrap()
+            .transaction_statuses
+            .push_back((statuses, false));
+    }
+
+    /// Like [`MockBackend::push_transaction_statuses`], except that after the given statuses,
+    /// the subscription to them fails with [`RpcError::SubscriptionDropped`], as it might if
+    /// the connection to a node was lost.
+    pub fn push_transaction_statuses_then_drop(&self, statuses: Vec<TransactionStatus<T::Hash>>) {
+        self.state
+            .lock()
+            .unwrap()
+            .transaction_statuses
+            .push_back((statuses, true));
+    }
+
+    /// The bytes of each transaction that has been submitted so far, in the order that
+    /// they were submitted in.
+    pub fn submitted_extrinsics(&self) -> Vec<Vec<u8>> {
+        self.state.lock().unwrap().submitted_extrinsics.clone()
+    }
+
+    /// A stream of the headers of blocks as they're handed to the given subscribers,
+    /// starting with the given block if there is one.
+    fn header_stream(
+        &self,
+        current: Option<T::Hash>,
+        subscribers: impl FnOnce(&mut State<T>) -> &mut Vec<mpsc::UnboundedSender<T::Hash>>,
+    ) -> StreamOfResults<(T::Header, BlockRef<T::Hash>)> {
+        let (tx, rx) = mpsc::unbounded();
+        if let Some(current) = current {
+            let _ = tx.unbounded_send(current);
+        }
+        subscribers(&mut self.state.lock().unwrap()).push(tx);
+
+        let backend = self.clone();
+        let headers = rx.filter_map(move |hash| {
+            let header = backend.header(hash).transpose();
+            async move { Some(header?.map(|h| (h, BlockRef::from_hash(hash)))) }
+        });
+        StreamOf::new(Box::pin(headers))
+    }
+
+    fn header(&self, hash: T::Hash) -> Result<Option<T::Header>, Error> {
+        let state = self.state.lock().unwrap();
+        let Some(block) = state.blocks.get(&hash) else {
+            return Ok(None);
+        };
+        Ok(Some(T::Header::decode(&mut &*block.header)?))
+    }
+}
+
+impl<T: Config> State<T> {
+    fn set_best_block(&mut self, hash: T::Hash) {
+        self.best_block = Some(hash);
+        notify(&mut self.best_block_subscribers, hash);
+    }
+
+    fn set_finalized_block(&mut self, hash: T::Hash) {
+        self.finalized_block = Some(hash);
+        notify(&mut self.finalized_block_subscribers, hash);
+    }
+
+    fn storage_at(&self, at: &T::Hash) -> impl Iterator<Item = (&Vec<u8>, &Vec<u8>)> {
+        self.storage.get(at).into_iter().flatten()
+    }
+}
+
+/// Hand an item to each subscriber, forgetting about any that have gone away.
+fn notify<Item: Clone>(subscribers: &mut Vec<mpsc::UnboundedSender<Item>>, item: Item) {
+    subscribers.retain(|tx| tx.unbounded_send(item.clone()).is_ok());
+}
+
+fn no_block_error(kind: &str) -> Error {
+    Error::Other(format!("MockBackend: no {kind} block has been added"))
+}
+
+#[async_trait]
+impl<T: Config> Backend<T> for MockBackend<T> {
+    async fn storage_fetch_values(
+        &self,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        let state = self.state.lock().unwrap();
+        let storage = state.storage.get(&at);
+        let values: Vec<_> = keys
+            .into_iter()
+            .filter_map(|key| {
+                let value = storage?.get(&key)?.clone();
+                Some(Ok(StorageResponse { key, value }))
+            })
+            .collect();
+        Ok(StreamOf::new(Box::pin(stream::iter(values))))
+    }
+
+    async fn storage_fetch_descendant_keys(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<Vec<u8>>, Error> {
+        let state = self.state.lock().unwrap();
+        let keys: Vec<_> = state
+            .storage_at(&at)
+            .filter(|(k, _)| k.starts_with(&key))
+            .map(|(k, _)| Ok(k.clone()))
+            .collect();
+        Ok(StreamOf::new(Box::pin(stream::iter(keys))))
+    }
+
+    async fn storage_fetch_descendant_values(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        let state = self.state.lock().unwrap();
+        let values: Vec<_> = state
+            .storage_at(&at)
+            .filter(|(k, _)| k.starts_with(&key))
+            .map(|(k, v)| {
+                Ok(StorageResponse {
+                    key: k.clone(),
+                    value: v.clone(),
+                })
+            })
+            .collect();
+        Ok(StreamOf::new(Box::pin(stream::iter(values))))
+    }
+
+    async fn genesis_hash(&self) -> Result<T::Hash, Error> {
+        self.state
+            .lock()
+            .unwrap()
+            .genesis_hash
+            .ok_or_else(|| Error::Other("MockBackend: no genesis hash has been set".into()))
+    }
+
+    async fn block_header(&self, at: T::Hash) -> Result<Option<T::Header>, Error> {
+        self.header(at)
+    }
+
+    async fn block_body(&self, at: T::Hash) -> Result<Option<Vec<Vec<u8>>>, Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.blocks.get(&at).map(|b| b.extrinsics.clone()))
+    }
+
+    async fn latest_finalized_block_ref(&self) -> Result<BlockRef<T::Hash>, Error> {
+        let state = self.state.lock().unwrap();
+        let hash = state
+            .finalized_block
+            .ok_or_else(|| no_block_error("finalized"))?;
+        Ok(BlockRef::from_hash(hash))
+    }
+
+    async fn latest_best_block_ref(&self) -> Result<BlockRef<T::Hash>, Error> {
+        let state = self.state.lock().unwrap();
+        let hash = state.best_block.ok_or_else(|| no_block_error("best"))?;
+        Ok(BlockRef::from_hash(hash))
+    }
+
+    async fn current_runtime_version(&self) -> Result<RuntimeVersion, Error> {
+        Ok(self.state.lock().unwrap().runtime_version.clone())
+    }
+
+    async fn stream_runtime_version(&self) -> Result<StreamOfResults<RuntimeVersion>, Error> {
+        let (tx, rx) = mpsc::unbounded();
+        let mut state = self.state.lock().unwrap();
+        let _ = tx.unbounded_send(state.runtime_version.clone());
+        state.runtime_version_subscribers.push(tx);
+        Ok(StreamOf::new(Box::pin(rx.map(Ok))))
+    }
+
+    async fn stream_all_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        Ok(self.header_stream(None, |s| &mut s.all_block_subscribers))
+    }
+
+    async fn stream_best_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        let best = self.state.lock().unwrap().best_block;
+        Ok(self.header_stream(best, |s| &mut s.best_block_subscribers))
+    }
+
+    async fn stream_finalized_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        let finalized = self.state.lock().unwrap().finalized_block;
+        Ok(self.header_stream(finalized, |s| &mut s.finalized_block_subscribers))
+    }
+
+    async fn submit_transaction(
+        &self,
+        bytes: &[u8],
+    ) -> Result<StreamOfResults<TransactionStatus<T::Hash>>, Error> {
+        let mut state = self.state.lock().unwrap();
+        state.submitted_extrinsics.push(bytes.to_vec());
+        let (statuses, then_drop) = state
+            .transaction_statuses
+            .pop_front()
+            .unwrap_or_else(|| (vec![TransactionStatus::Validated], false));
+
+        // Like a real node, only end the stream after a status that nothing follows.
+        let is_final = matches!(
+            statuses.last(),
+            Some(
+                TransactionStatus::InFinalizedBlock { .. }
+                    | TransactionStatus::Error { .. }
+                    | TransactionStatus::Invalid { .. }
+                    | TransactionStatus::Dropped { .. }
+            )
+        );
+        let statuses = stream::iter(statuses.into_iter().map(Ok));
+        if then_drop {
+            let dropped = stream::once(async { Err(RpcError::SubscriptionDropped.into()) });
+            Ok(StreamOf::new(Box::pin(statuses.chain(dropped))))
+        } else if is_final {
+            Ok(StreamOf::new(Box::pin(statuses)))
+        } else {
+            Ok(StreamOf::new(Box::pin(statuses.chain(stream::pending()))))
+        }
+    }
+
+    async fn call(
+        &self,
+        method: &str,
+        call_parameters: Option<&[u8]>,
+        _at: T::Hash,
+    ) -> Result<Vec<u8>, Error> {
+        let mut state = self.state.lock().unwrap();
+
+        let queued = state
+            .queued_call_responses
+            .get_mut(method)
+            .and_then(|responses| responses.pop_front());
+        if let Some(response) = queued {
+            return Ok(response);
+        }
+
+        let params = call_parameters.map(|p| p.to_vec());
+        let response = state
+            .call_responses
+            .get(&(method.to_owned(), params))
+            .or_else(|| state.call_responses.get(&(method.to_owned(), None)));
+        if let Some(response) = response {
+            return Ok(response.clone());
+        }
+
+        // Answer requests for metadata if we have some.
+        if let Some(metadata) = &state.metadata {
+            let opaque = frame_metadata::OpaqueMetadata(metadata.clone());
+            match method {
+                "Metadata_metadata" => return Ok(opaque.encode()),
+                "Metadata_metadata_at_version" => {
+                    // Our metadata is always encoded as V15.
+                    let version = call_parameters.and_then(|mut p| u32::decode(&mut p).ok());
+                    return Ok((version == Some(15)).then_some(opaque).encode());
+                }
+                _ => {}
+            }
+        }
+
+        Err(Error::Other(format!(
+            "MockBackend: no response has been set for the runtime API call {method}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::test_utils::{header, Hash};
+    use crate::backend::BackendExt;
+    use crate::{OnlineClient, PolkadotConfig};
+
+    fn metadata() -> Metadata {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_small.scale").unwrap();
+        Metadata::decode(&mut &*bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_storage_per_block() {
+        let backend = MockBackend::<PolkadotConfig>::new();
+        let first = backend.add_block(header(1, Hash::zero()), vec![]);
+        backend.set_storage(first, vec![1], vec![1]);
+        backend.set_storage(first, vec![2], vec![2]);
+
+        // New blocks inherit the storage of the best block.
+        let second = backend.add_block(header(2, Hash::zero()), vec![]);
+        backend.set_storage(second, vec![1], vec![3]);
+        backend.remove_storage(second, &[2]);
+
+        let value = |key: u8, at| backend.storage_fetch_value(vec![key], at);
+        assert_eq!(value(1, first).await.unwrap(), Some(vec![1]));
+        assert_eq!(value(2, first).await.unwrap(), Some(vec![2]));
+        assert_eq!(value(1, second).await.unwrap(), Some(vec![3]));
+        assert_eq!(value(2, second).await.unwrap(), None);
+        assert_eq!(
+            backend.latest_best_block_ref().await.unwrap().hash(),
+            second
+        );
+        assert_eq!(backend.genesis_hash().await.unwrap(), first);
+    }
+
+    #[tokio::test]
+    async fn streams_new_blocks() {
+        let backend = MockBackend::<PolkadotConfig>::new();
+        let first = backend.add_block(header(1, Hash::zero()), vec![]);
+
+        let mut finalized = backend.stream_finalized_block_headers().await.unwrap();
+        let mut all = backend.stream_all_block_headers().await.unwrap();
+        let second = backend.add_block(header(2, Hash::zero()), vec![]);
+
+        let hash =
+            |item: Option<Result<(_, BlockRef<Hash>), Error>>| item.unwrap().unwrap().1.hash();
+        assert_eq!(hash(finalized.next().await), first);
+        assert_eq!(hash(finalized.next().await), second);
+        assert_eq!(hash(all.next().await), second);
+    }
+
+    #[tokio::test]
+    async fn works_with_online_client() {
+        let backend = MockBackend::<PolkadotConfig>::new();
+        backend.set_metadata(metadata());
+        let block_hash = backend.add_block(header(1, Hash::zero()), vec![vec![8, 1, 2]]);
+        backend.push_transaction_statuses(vec![
+            TransactionStatus::Validated,
+            TransactionStatus::InBestBlock {
+                hash: block_hash.into(),
+            },
+        ]);
+
+        let api = OnlineClient::<PolkadotConfig>::from_backend(Arc::new(backend.clone()))
+            .await
+            .unwrap();
+
+        let block = api.blocks().at_latest().await.unwrap();
+        assert_eq!(block.hash(), block_hash);
+        assert_eq!(block.number(), 1);
+
+        let tx = crate::dynamic::tx("System", "remark", vec![vec![1u8, 2, 3]]);
+        let tx = api.tx().create_unsigned(&tx).unwrap();
+        let mut progress = tx.submit_and_watch().await.unwrap();
+        assert!(progress.next().await.is_some());
+
+        assert_eq!(backend.submitted_extrinsics(), vec![tx.encoded().to_vec()]);
+    }
+}
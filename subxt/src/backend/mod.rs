@@ -6,10 +6,17 @@
 //! the necessary information (probably from a JSON-RPC API, but that's up to the
 //! implementation).
 
+pub mod caching;
+pub mod conformance;
 pub mod legacy;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 pub mod rpc;
 pub mod unstable;
 
+#[cfg(test)]
+pub(crate) mod test_utils;
+
 use crate::error::Error;
 use crate::metadata::Metadata;
 use crate::Config;
@@ -19,16 +26,15 @@ use futures::{Stream, StreamExt};
 use std::pin::Pin;
 use std::sync::Arc;
 
-/// Prevent the backend trait being implemented externally.
-#[doc(hidden)]
-pub(crate) mod sealed {
-    pub trait Sealed {}
-}
-
 /// This trait exposes the interface that Subxt will use to communicate with
 /// a backend. Its goal is to be as minimal as possible.
+///
+/// Subxt provides [`legacy::LegacyBackend`] and [`unstable::UnstableBackend`], but this
+/// trait can also be implemented externally in order to obtain data from some other source,
+/// and then handed to [`crate::OnlineClient::from_backend()`]. The [`conformance`] module
+/// can be used to check that such an implementation behaves like the ones provided here.
 #[async_trait]
-pub trait Backend<T: Config>: sealed::Sealed + Send + Sync + 'static {
+pub trait Backend<T: Config>: Send + Sync + 'static {
     /// Fetch values from storage.
     async fn storage_fetch_values(
         &self,
@@ -317,12 +323,12 @@ pub enum TransactionStatus<Hash> {
     /// Transaction has been included in block with given hash.
     InBestBlock {
         /// Block hash the transaction is in.
-        hash: Hash,
+        hash: BlockRef<Hash>,
     },
     /// Transaction has been finalized by a finality-gadget, e.g GRANDPA
     InFinalizedBlock {
         /// Block hash the transaction is in.
-        hash: Hash,
+        hash: BlockRef<Hash>,
     },
     /// Something went wrong in the node.
     Error {
@@ -0,0 +1,85 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use std::time::Duration;
+
+/// The delays to wait between attempts to do something (eg reconnect or retry a request),
+/// which grow exponentially from some initial delay up to some maximum delay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExponentialBackoff {
+    initial_delay: Duration,
+    max_delay: Duration,
+    factor: u32,
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        ExponentialBackoff::from_millis(100)
+    }
+}
+
+impl ExponentialBackoff {
+    /// Start by waiting the given number of milliseconds, doubling the delay after
+    /// each failed attempt up to a maximum of 10 seconds.
+    pub fn from_millis(millis: u64) -> Self {
+        ExponentialBackoff {
+            initial_delay: Duration::from_millis(millis),
+            max_delay: Duration::from_secs(10),
+            factor: 2,
+        }
+    }
+
+    /// The maximum delay to wait between attempts.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// What to multiply the delay by after each failed attempt.
+    pub fn factor(mut self, factor: u32) -> Self {
+        self.factor = factor;
+        self
+    }
+
+    /// An endless iterator of the delays to wait between attempts.
+    pub fn delays(&self) -> impl Iterator<Item = Duration> {
+        let ExponentialBackoff {
+            max_delay, factor, ..
+        } = *self;
+        std::iter::successors(Some(self.initial_delay.min(max_delay)), move |delay| {
+            Some(delay.saturating_mul(factor).min(max_delay))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_delays_grow_up_to_max() {
+        let backoff = ExponentialBackoff::from_millis(100)
+            .factor(3)
+            .max_delay(Duration::from_secs(2));
+
+        let delays: Vec<_> = backoff.delays().take(6).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Duration::from_millis(100),
+                Duration::from_millis(300),
+                Duration::from_millis(900),
+                Duration::from_secs(2),
+                Duration::from_secs(2),
+                Duration::from_secs(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn backoff_initial_delay_respects_max() {
+        let backoff = ExponentialBackoff::from_millis(5_000).max_delay(Duration::from_secs(1));
+        assert_eq!(backoff.delays().next(), Some(Duration::from_secs(1)));
+    }
+}
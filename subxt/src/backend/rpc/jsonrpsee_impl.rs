@@ -8,6 +8,7 @@ use futures::stream::{StreamExt, TryStreamExt};
 use jsonrpsee::{
     core::{
         client::{Client, ClientT, SubscriptionClientT, SubscriptionKind},
+        params::BatchRequestBuilder,
         traits::ToRpcParams,
         Error as JsonRpseeError,
     },
@@ -15,6 +16,9 @@ use jsonrpsee::{
 };
 use serde_json::value::RawValue;
 
+#[cfg(feature = "native")]
+use jsonrpsee::http_client::HttpClient;
+
 struct Params(Option<Box<RawValue>>);
 
 impl ToRpcParams for Params {
@@ -23,6 +27,35 @@ impl ToRpcParams for Params {
     }
 }
 
+/// Send a batch of requests using some jsonrpsee client.
+async fn batch_request<C: ClientT + Sync>(
+    client: &C,
+    requests: Vec<(&str, Option<Box<RawValue>>)>,
+) -> Result<Vec<Result<Box<RawValue>, RpcError>>, RpcError> {
+    // jsonrpsee refuses to send empty batches.
+    if requests.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut batch = BatchRequestBuilder::new();
+    for (method, params) in requests {
+        batch
+            .insert(method, Params(params))
+            .map_err(|e| RpcError::ClientError(Box::new(e)))?;
+    }
+
+    let res = ClientT::batch_request::<Box<RawValue>>(client, batch)
+        .await
+        .map_err(|e| RpcError::ClientError(Box::new(e)))?;
+
+    Ok(res
+        .into_iter()
+        .map(|r| {
+            r.map_err(|e| RpcError::ClientError(Box::new(JsonRpseeError::Call(e.into_owned()))))
+        })
+        .collect())
+}
+
 impl RpcClientT for Client {
     fn request_raw<'a>(
         &'a self,
@@ -37,6 +70,13 @@ impl RpcClientT for Client {
         })
     }
 
+    fn batch_request_raw<'a>(
+        &'a self,
+        requests: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RawRpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        Box::pin(batch_request(self, requests))
+    }
+
     fn subscribe_raw<'a>(
         &'a self,
         sub: &'a str,
@@ -67,3 +107,37 @@ impl RpcClientT for Client {
         })
     }
 }
+
+/// HTTP clients can make requests, but the node has no way to push subscription
+/// notifications to them, so subscribing always fails.
+#[cfg(feature = "native")]
+impl RpcClientT for HttpClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            let res = ClientT::request(self, method, Params(params))
+                .await
+                .map_err(|e| RpcError::ClientError(Box::new(e)))?;
+            Ok(res)
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        _params: Option<Box<RawValue>>,
+        _unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(async move { Err(RpcError::SubscriptionsNotSupported(sub.to_owned())) })
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        requests: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RawRpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        Box::pin(batch_request(self, requests))
+    }
+}
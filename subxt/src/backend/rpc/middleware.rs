@@ -0,0 +1,867 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Middleware which can be wrapped around an [`RpcClient`] to observe or alter the
+//! requests and subscriptions that are made through it.
+//!
+//! Anything implementing [`RpcMiddleware`] can be added using [`RpcClient::with_middleware`].
+//! Middleware can be stacked; each call to [`RpcClient::with_middleware`] wraps the client
+//! returned from the previous one, so the last middleware added sees each call first.
+//!
+//! The following middleware is provided:
+//!
+//! - [`RateLimit`] limits the number of requests that are made per some period of time.
+//! - [`Retry`] retries requests which fail with some transient error.
+//! - [`Metrics`] keeps count of the calls made to each RPC method, their latency, errors
+//!   and sizes, and can hand details of each call to a hook of your choosing.
+//! - [`Logging`] logs each call made, and its outcome, using `tracing`.
+//!
+//! Things like HTTP headers are a property of the underlying connection, and so should be
+//! configured when building the underlying client (for instance a `jsonrpsee` client)
+//! instead.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() {
+//! use std::time::Duration;
+//! use subxt::backend::rpc::middleware::{Metrics, RateLimit, Retry};
+//! use subxt::backend::rpc::RpcClient;
+//! use subxt::{OnlineClient, PolkadotConfig};
+//!
+//! let metrics = Metrics::new();
+//! let rpc_client = RpcClient::from_url("ws://127.0.0.1:9944")
+//!     .await
+//!     .unwrap()
+//!     .with_middleware(RateLimit::new(100, Duration::from_secs(1)))
+//!     .with_middleware(Retry::new().max_retries(5))
+//!     .with_middleware(metrics.clone());
+//!
+//! let api = OnlineClient::<PolkadotConfig>::from_rpc_client(rpc_client)
+//!     .await
+//!     .unwrap();
+//!
+//! for (method, m) in metrics.method_metrics() {
+//!     let average_latency = m.average_latency();
+//!     println!("{method}: {} calls, {} errors, {average_latency:?}", m.calls, m.errors);
+//! }
+//! # }
+//! ```
+
+use super::{
+    is_idempotent, ExponentialBackoff, RawRpcFuture, RawRpcSubscription, RpcClient, RpcClientT,
+};
+use crate::error::RpcError;
+use futures::StreamExt;
+use instant::Instant;
+use serde_json::value::RawValue;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Something which can be wrapped around an [`RpcClient`] to observe or alter the calls
+/// made through it. See [`RpcClient::with_middleware`].
+pub trait RpcMiddleware {
+    /// The client that results from wrapping an [`RpcClient`] in this middleware.
+    type Client: RpcClientT;
+
+    /// Wrap the given client.
+    fn wrap(self, client: RpcClient) -> Self::Client;
+}
+
+/// Is the given error likely to be temporary, such that retrying the call may succeed?
+///
+/// This is the case for errors caused by the connection or the node being busy, but not
+/// for errors returned by the node in response to a call (which would likely be returned
+/// again).
+pub fn is_transient_error(err: &RpcError) -> bool {
+    match err {
+        RpcError::LimitReached | RpcError::DisconnectedWillReconnect(_) => true,
+        #[cfg(feature = "jsonrpsee")]
+        RpcError::ClientError(e) => {
+            use jsonrpsee::core::Error as JsonRpseeError;
+            matches!(
+                e.downcast_ref::<JsonRpseeError>(),
+                Some(
+                    JsonRpseeError::Transport(_)
+                        | JsonRpseeError::RestartNeeded(_)
+                        | JsonRpseeError::RequestTimeout
+                        | JsonRpseeError::MaxSlotsExceeded
+                )
+            )
+        }
+        _ => false,
+    }
+}
+
+/// Middleware which limits the rate at which RPC calls are made. Calls beyond this limit wait
+/// until they are allowed to proceed. Each request in a batch counts as one call, as does each
+/// subscription.
+///
+/// Calls can be made in bursts of up to the maximum number allowed per period, and are
+/// otherwise spread evenly across it.
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    max_calls: u32,
+    period: Duration,
+}
+
+impl RateLimit {
+    /// Allow calls to be made at an average rate of `max_calls` per `period`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_calls` is 0.
+    pub fn new(max_calls: u32, period: Duration) -> Self {
+        assert!(max_calls > 0, "RateLimit must allow at least one call");
+        RateLimit { max_calls, period }
+    }
+
+    /// Allow calls to be made at an average rate of `max_calls` per second.
+    pub fn per_second(max_calls: u32) -> Self {
+        Self::new(max_calls, Duration::from_secs(1))
+    }
+}
+
+impl RpcMiddleware for RateLimit {
+    type Client = RateLimitClient;
+
+    fn wrap(self, client: RpcClient) -> Self::Client {
+        RateLimitClient {
+            client,
+            interval: self.period / self.max_calls,
+            period: self.period,
+            next_call_at: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+/// An [`RpcClientT`] which has had [`RateLimit`] middleware applied.
+#[derive(Debug, Clone)]
+pub struct RateLimitClient {
+    client: RpcClient,
+    // Calls are spaced this far apart on average.
+    interval: Duration,
+    // Calls are allowed up to this far ahead of time, to allow for bursts.
+    period: Duration,
+    // The time at which the next call would be made if calls were evenly spaced.
+    next_call_at: Arc<Mutex<Option<Instant>>>,
+}
+
+impl RateLimitClient {
+    /// Wait until we're allowed to make the given number of calls.
+    async fn wait_for(&self, num_calls: u32) {
+        let delay = {
+            let now = Instant::now();
+            let mut next_call_at = self.next_call_at.lock().unwrap();
+            let start = next_call_at.map_or(now, |t| t.max(now));
+            let end = start + self.interval * num_calls;
+            *next_call_at = Some(end);
+            end.saturating_duration_since(now + self.period)
+        };
+
+        if !delay.is_zero() {
+            futures_timer::Delay::new(delay).await;
+        }
+    }
+}
+
+impl RpcClientT for RateLimitClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            self.wait_for(1).await;
+            self.client.request_raw(method, params).await
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(async move {
+            self.wait_for(1).await;
+            self.client.subscribe_raw(sub, params, unsub).await
+        })
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        requests: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RawRpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        Box::pin(async move {
+            self.wait_for(requests.len() as u32).await;
+            self.client.batch_request_raw(requests).await
+        })
+    }
+}
+
+/// Middleware which retries requests and subscription attempts that fail with some transient
+/// error, waiting between attempts according to some [`ExponentialBackoff`]. Batch requests
+/// are retried as a whole if the batch fails, but not if individual requests in it do.
+///
+/// Calls to methods which ask the node to do something, like `author_submitExtrinsic`, are
+/// never retried (nor are batches containing them), since the node may have acted on the
+/// first call even though it failed. The error is handed back as-is for these instead.
+///
+/// By default, calls are retried up to 3 times if they fail with an error that
+/// [`is_transient_error`] deems to be transient.
+#[derive(Clone)]
+pub struct Retry {
+    max_retries: usize,
+    backoff: ExponentialBackoff,
+    retry_if: Arc<dyn Fn(&RpcError) -> bool + Send + Sync>,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for Retry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Retry")
+            .field("max_retries", &self.max_retries)
+            .field("backoff", &self.backoff)
+            .finish()
+    }
+}
+
+impl Retry {
+    /// Create a new [`Retry`] middleware with the default settings.
+    pub fn new() -> Self {
+        Retry {
+            max_retries: 3,
+            backoff: ExponentialBackoff::default(),
+            retry_if: Arc::new(is_transient_error),
+        }
+    }
+
+    /// The maximum number of times to retry a call before handing back the error.
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// How long to wait between each attempt.
+    pub fn backoff(mut self, backoff: ExponentialBackoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Decide which errors are worth retrying a call for. Defaults to [`is_transient_error`].
+    pub fn retry_if<F>(mut self, retry_if: F) -> Self
+    where
+        F: Fn(&RpcError) -> bool + Send + Sync + 'static,
+    {
+        self.retry_if = Arc::new(retry_if);
+        self
+    }
+}
+
+impl RpcMiddleware for Retry {
+    type Client = RetryClient;
+
+    fn wrap(self, client: RpcClient) -> Self::Client {
+        RetryClient {
+            client,
+            policy: self,
+        }
+    }
+}
+
+/// An [`RpcClientT`] which has had [`Retry`] middleware applied.
+#[derive(Debug, Clone)]
+pub struct RetryClient {
+    client: RpcClient,
+    policy: Retry,
+}
+
+impl RetryClient {
+    /// Make some call, retrying it according to our policy. Calls which aren't `idempotent`
+    /// are never retried.
+    async fn with_retries<'a, T, F>(
+        &'a self,
+        name: &str,
+        idempotent: bool,
+        mut call: F,
+    ) -> Result<T, RpcError>
+    where
+        F: FnMut() -> RawRpcFuture<'a, T>,
+    {
+        let mut delays = self.policy.backoff.delays();
+        let mut retries = 0;
+        loop {
+            match call().await {
+                Err(e)
+                    if idempotent
+                        && retries < self.policy.max_retries
+                        && (self.policy.retry_if)(&e) =>
+                {
+                    retries += 1;
+                    let delay = delays.next().expect("delays never end; qed");
+                    tracing::debug!("{name} failed (retry {retries}); retrying in {delay:?}: {e}");
+                    futures_timer::Delay::new(delay).await;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+impl RpcClientT for RetryClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(self.with_retries(method, is_idempotent(method), move || {
+            self.client.request_raw(method, params.clone())
+        }))
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(self.with_retries(sub, is_idempotent(sub), move || {
+            self.client.subscribe_raw(sub, params.clone(), unsub)
+        }))
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        requests: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RawRpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        let idempotent = requests.iter().all(|(method, _)| is_idempotent(method));
+        Box::pin(self.with_retries("batch request", idempotent, move || {
+            self.client.batch_request_raw(requests.clone())
+        }))
+    }
+}
+
+/// Details about a single RPC call, handed to the hook given to [`Metrics::on_call`].
+#[derive(Debug)]
+pub struct CallInfo<'a> {
+    /// The RPC method that was called.
+    pub method: &'a str,
+    /// What sort of call this was.
+    pub kind: CallKind,
+    /// How long it took for the call to complete. For requests in a batch, this is how long
+    /// the whole batch took, and for subscriptions, how long it took to subscribe.
+    pub latency: Duration,
+    /// The size in bytes of the JSON-encoded parameters that were sent.
+    pub request_bytes: usize,
+    /// The size in bytes of the JSON-encoded response, if there was one.
+    pub response_bytes: usize,
+    /// The error that the call failed with, if it did.
+    pub error: Option<&'a RpcError>,
+}
+
+/// The sort of RPC call described by some [`CallInfo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind {
+    /// A single request.
+    Request,
+    /// A request made as part of a batch.
+    BatchRequest,
+    /// An attempt to subscribe.
+    Subscription,
+}
+
+/// Counters describing the calls that have been made to some RPC method.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MethodMetrics {
+    /// The number of calls made to the method, including any that failed.
+    pub calls: u64,
+    /// The number of calls which failed.
+    pub errors: u64,
+    /// The total time spent waiting for calls to complete.
+    pub total_latency: Duration,
+    /// The longest time spent waiting for a call to complete.
+    pub max_latency: Duration,
+    /// The total size in bytes of the parameters sent.
+    pub request_bytes: u64,
+    /// The total size in bytes of the responses received.
+    pub response_bytes: u64,
+    /// For subscriptions, the number of notifications received.
+    pub notifications: u64,
+}
+
+impl MethodMetrics {
+    /// The average time spent waiting for a call to complete.
+    pub fn average_latency(&self) -> Duration {
+        if self.calls == 0 {
+            Duration::ZERO
+        } else {
+            let nanos = self.total_latency.as_nanos() / self.calls as u128;
+            Duration::from_nanos(nanos as u64)
+        }
+    }
+}
+
+type CallHook = dyn Fn(&CallInfo<'_>) + Send + Sync;
+
+/// Middleware which records [`MethodMetrics`] for each RPC method that is called. This is
+/// cheap to clone, and clones share the same metrics, so a clone can be kept hold of to
+/// read the metrics from after handing this to [`RpcClient::with_middleware`].
+///
+/// Use [`Metrics::on_call`] to be handed the details of each call as it completes, for
+/// instance to export them somewhere.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    methods: Arc<Mutex<BTreeMap<String, MethodMetrics>>>,
+    hook: Option<Arc<CallHook>>,
+}
+
+impl std::fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Metrics")
+            .field("methods", &self.methods)
+            .finish()
+    }
+}
+
+impl Metrics {
+    /// Create a new, empty set of metrics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call the function provided with the details of each call once it completes.
+    pub fn on_call<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&CallInfo<'_>) + Send + Sync + 'static,
+    {
+        self.hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// The metrics recorded so far for each RPC method, keyed by method name.
+    pub fn method_metrics(&self) -> BTreeMap<String, MethodMetrics> {
+        self.methods.lock().unwrap().clone()
+    }
+
+    /// Forget all of the metrics recorded so far.
+    pub fn reset(&self) {
+        self.methods.lock().unwrap().clear();
+    }
+
+    fn record(&self, info: CallInfo<'_>) {
+        {
+            let mut methods = self.methods.lock().unwrap();
+            let m = methods.entry(info.method.to_owned()).or_default();
+            m.calls += 1;
+            m.errors += info.error.is_some() as u64;
+            m.total_latency += info.latency;
+            m.max_latency = m.max_latency.max(info.latency);
+            m.request_bytes += info.request_bytes as u64;
+            m.response_bytes += info.response_bytes as u64;
+        }
+        if let Some(hook) = &self.hook {
+            hook(&info);
+        }
+    }
+
+    fn record_notification(&self, method: &str, response_bytes: usize) {
+        let mut methods = self.methods.lock().unwrap();
+        let m = methods.entry(method.to_owned()).or_default();
+        m.notifications += 1;
+        m.response_bytes += response_bytes as u64;
+    }
+}
+
+impl RpcMiddleware for Metrics {
+    type Client = MetricsClient;
+
+    fn wrap(self, client: RpcClient) -> Self::Client {
+        MetricsClient {
+            client,
+            metrics: self,
+        }
+    }
+}
+
+/// An [`RpcClientT`] which has had [`Metrics`] middleware applied.
+#[derive(Debug, Clone)]
+pub struct MetricsClient {
+    client: RpcClient,
+    metrics: Metrics,
+}
+
+impl RpcClientT for MetricsClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            let request_bytes = params_len(&params);
+            let start = Instant::now();
+            let res = self.client.request_raw(method, params).await;
+            self.metrics.record(CallInfo {
+                method,
+                kind: CallKind::Request,
+                latency: start.elapsed(),
+                request_bytes,
+                response_bytes: res.as_ref().map_or(0, |r| r.get().len()),
+                error: res.as_ref().err(),
+            });
+            res
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(async move {
+            let request_bytes = params_len(&params);
+            let start = Instant::now();
+            let res = self.client.subscribe_raw(sub, params, unsub).await;
+            self.metrics.record(CallInfo {
+                method: sub,
+                kind: CallKind::Subscription,
+                latency: start.elapsed(),
+                request_bytes,
+                response_bytes: 0,
+                error: res.as_ref().err(),
+            });
+
+            let RawRpcSubscription { stream, id } = res?;
+            let metrics = self.metrics.clone();
+            let sub = sub.to_owned();
+            let stream = stream
+                .inspect(move |item| {
+                    if let Ok(item) = item {
+                        metrics.record_notification(&sub, item.get().len());
+                    }
+                })
+                .boxed();
+            Ok(RawRpcSubscription { stream, id })
+        })
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        requests: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RawRpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        Box::pin(async move {
+            let calls: Vec<_> = requests
+                .iter()
+                .map(|(method, params)| (*method, params_len(params)))
+                .collect();
+            let start = Instant::now();
+            let res = self.client.batch_request_raw(requests).await;
+            let latency = start.elapsed();
+
+            for (idx, (method, request_bytes)) in calls.into_iter().enumerate() {
+                let (response_bytes, error) = match &res {
+                    Err(e) => (0, Some(e)),
+                    Ok(results) => match results.get(idx) {
+                        Some(Ok(r)) => (r.get().len(), None),
+                        Some(Err(e)) => (0, Some(e)),
+                        None => (0, None),
+                    },
+                };
+                self.metrics.record(CallInfo {
+                    method,
+                    kind: CallKind::BatchRequest,
+                    latency,
+                    request_bytes,
+                    response_bytes,
+                    error,
+                });
+            }
+            res
+        })
+    }
+}
+
+fn params_len(params: &Option<Box<RawValue>>) -> usize {
+    params.as_ref().map_or(0, |p| p.get().len())
+}
+
+/// Middleware which logs each RPC call and its outcome at the `debug` level (or `trace`
+/// level for subscription notifications), using `tracing`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Logging;
+
+impl RpcMiddleware for Logging {
+    type Client = LoggingClient;
+
+    fn wrap(self, client: RpcClient) -> Self::Client {
+        LoggingClient { client }
+    }
+}
+
+/// An [`RpcClientT`] which has had [`Logging`] middleware applied.
+#[derive(Debug, Clone)]
+pub struct LoggingClient {
+    client: RpcClient,
+}
+
+impl RpcClientT for LoggingClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            tracing::debug!("RPC request {method} ({} bytes)", params_len(&params));
+            let start = Instant::now();
+            let res = self.client.request_raw(method, params).await;
+            match &res {
+                Ok(r) => tracing::debug!(
+                    "RPC request {method} succeeded in {:?} ({} bytes)",
+                    start.elapsed(),
+                    r.get().len()
+                ),
+                Err(e) => {
+                    tracing::debug!("RPC request {method} failed in {:?}: {e}", start.elapsed())
+                }
+            }
+            res
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(async move {
+            tracing::debug!("RPC subscription {sub} ({} bytes)", params_len(&params));
+            let res = self.client.subscribe_raw(sub, params, unsub).await;
+            let RawRpcSubscription { stream, id } = match res {
+                Ok(s) => s,
+                Err(e) => {
+                    tracing::debug!("RPC subscription {sub} failed: {e}");
+                    return Err(e);
+                }
+            };
+
+            let sub = sub.to_owned();
+            let stream = stream
+                .inspect(move |item| match item {
+                    Ok(item) => {
+                        tracing::trace!("RPC subscription {sub} notification: {}", item.get())
+                    }
+                    Err(e) => tracing::debug!("RPC subscription {sub} error: {e}"),
+                })
+                .boxed();
+            Ok(RawRpcSubscription { stream, id })
+        })
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        requests: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RawRpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        Box::pin(async move {
+            let num_requests = requests.len();
+            tracing::debug!("RPC batch of {num_requests} requests");
+            let start = Instant::now();
+            let res = self.client.batch_request_raw(requests).await;
+            match &res {
+                Ok(results) => tracing::debug!(
+                    "RPC batch of {num_requests} requests completed in {:?} ({} failed)",
+                    start.elapsed(),
+                    results.iter().filter(|r| r.is_err()).count()
+                ),
+                Err(e) => tracing::debug!(
+                    "RPC batch of {num_requests} requests failed in {:?}: {e}",
+                    start.elapsed()
+                ),
+            }
+            res
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::rpc::rpc_params;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A fake client which fails the first few requests it is given.
+    #[derive(Clone)]
+    struct FlakyClient {
+        failures_left: Arc<AtomicUsize>,
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl FlakyClient {
+        fn new(failures: usize) -> Self {
+            FlakyClient {
+                failures_left: Arc::new(AtomicUsize::new(failures)),
+                requests: Arc::new(AtomicUsize::new(0)),
+            }
+        }
+    }
+
+    impl RpcClientT for FlakyClient {
+        fn request_raw<'a>(
+            &'a self,
+            method: &'a str,
+            _params: Option<Box<RawValue>>,
+        ) -> RawRpcFuture<'a, Box<RawValue>> {
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            let fail = self
+                .failures_left
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                .is_ok();
+            Box::pin(async move {
+                if fail {
+                    return Err(RpcError::LimitReached);
+                }
+                match method {
+                    "bad_method" => Err(RpcError::ClientError("bad method".into())),
+                    _ => Ok(RawValue::from_string(format!("\"{method}\"")).unwrap()),
+                }
+            })
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            sub: &'a str,
+            _params: Option<Box<RawValue>>,
+            _unsub: &'a str,
+        ) -> RawRpcFuture<'a, RawRpcSubscription> {
+            Box::pin(async move { Err(RpcError::SubscriptionsNotSupported(sub.to_owned())) })
+        }
+    }
+
+    fn retry_immediately() -> Retry {
+        Retry::new().backoff(ExponentialBackoff::from_millis(0))
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors() {
+        let flaky = FlakyClient::new(2);
+        let client = RpcClient::new(flaky.clone()).with_middleware(retry_immediately());
+
+        let res: String = client.request("foo", rpc_params![]).await.unwrap();
+        assert_eq!(res, "foo");
+        assert_eq!(flaky.requests.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let flaky = FlakyClient::new(10);
+        let client =
+            RpcClient::new(flaky.clone()).with_middleware(retry_immediately().max_retries(2));
+
+        let res = client.request::<String>("foo", rpc_params![]).await;
+        assert!(res.is_err());
+        assert_eq!(flaky.requests.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_other_errors() {
+        let flaky = FlakyClient::new(0);
+        let client = RpcClient::new(flaky.clone()).with_middleware(retry_immediately());
+
+        let res = client.request::<String>("bad_method", rpc_params![]).await;
+        assert!(res.is_err());
+        assert_eq!(flaky.requests.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_transaction_submissions() {
+        let flaky = FlakyClient::new(1);
+        let client = RpcClient::new(flaky.clone()).with_middleware(retry_immediately());
+
+        let res = client
+            .request::<String>("author_submitExtrinsic", rpc_params!["0x00"])
+            .await;
+        assert!(matches!(
+            res,
+            Err(crate::Error::Rpc(RpcError::LimitReached))
+        ));
+        assert_eq!(flaky.requests.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn metrics_are_recorded_per_method() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let metrics = Metrics::new().on_call({
+            let calls = calls.clone();
+            move |_| {
+                calls.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        let client = RpcClient::new(FlakyClient::new(0)).with_middleware(metrics.clone());
+
+        let _ = client.request::<String>("foo", rpc_params![1]).await;
+        let _ = client.request::<String>("foo", rpc_params![]).await;
+        let _ = client.request::<String>("bad_method", rpc_params![]).await;
+        let _ = client
+            .batch_request::<String>([("foo", rpc_params![]), ("bar", rpc_params![])])
+            .await;
+        let _ = client
+            .subscribe::<String>("sub", rpc_params![], "unsub")
+            .await;
+
+        let m = metrics.method_metrics();
+        assert_eq!(m.len(), 4);
+        assert_eq!(m["foo"].calls, 3);
+        assert_eq!(m["foo"].errors, 0);
+        assert_eq!(m["foo"].request_bytes, "[1]".len() as u64);
+        assert_eq!(m["foo"].response_bytes, 3 * "\"foo\"".len() as u64);
+        assert_eq!(m["bar"].calls, 1);
+        assert_eq!(m["bad_method"].errors, 1);
+        assert_eq!(m["sub"].errors, 1);
+        assert_eq!(calls.load(Ordering::Relaxed), 6);
+
+        metrics.reset();
+        assert!(metrics.method_metrics().is_empty());
+    }
+
+    #[test]
+    fn average_latency_handles_large_call_counts() {
+        let metrics = MethodMetrics {
+            calls: 1 << 32,
+            total_latency: Duration::from_secs(1 << 32),
+            ..Default::default()
+        };
+        assert_eq!(metrics.average_latency(), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn rate_limit_allows_bursts_then_spaces_calls_out() {
+        let period = Duration::from_millis(200);
+        let client = RpcClient::new(FlakyClient::new(0)).with_middleware(RateLimit::new(2, period));
+
+        let start = Instant::now();
+        for _ in 0..2 {
+            client
+                .request::<String>("foo", rpc_params![])
+                .await
+                .unwrap();
+        }
+        assert!(start.elapsed() < period);
+
+        for _ in 0..2 {
+            client
+                .request::<String>("foo", rpc_params![])
+                .await
+                .unwrap();
+        }
+        assert!(start.elapsed() >= period);
+    }
+}
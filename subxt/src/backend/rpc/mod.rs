@@ -59,9 +59,47 @@
 #[cfg(feature = "jsonrpsee")]
 mod jsonrpsee_impl;
 
+#[cfg(feature = "jsonrpsee")]
+mod reconnecting_rpc_client;
+
+pub mod middleware;
+
+mod backoff;
+mod multi_endpoint_rpc_client;
+mod recording_rpc_client;
+mod replay_rpc_client;
 mod rpc_client;
 mod rpc_client_t;
 
+pub use backoff::ExponentialBackoff;
+
 pub use rpc_client_t::{RawRpcFuture, RawRpcSubscription, RawValue, RpcClientT};
 
+pub use multi_endpoint_rpc_client::{
+    EndpointStats, LoadBalancingStrategy, MultiEndpointRpcClient, MultiEndpointRpcClientBuilder,
+};
+
+pub use recording_rpc_client::RecordingRpcClient;
+pub use replay_rpc_client::ReplayRpcClient;
+
 pub use rpc_client::{rpc_params, RpcClient, RpcParams, RpcSubscription};
+
+#[cfg(feature = "jsonrpsee")]
+pub use reconnecting_rpc_client::{ReconnectingRpcClient, ReconnectingRpcClientBuilder};
+
+/// RPC methods which ask the node to do something (like submit a transaction) rather than
+/// just to return some data, and so which must not be sent again if we don't know whether
+/// the node received them the first time.
+const NON_IDEMPOTENT_METHODS: &[&str] = &[
+    "author_submitExtrinsic",
+    "author_submitAndWatchExtrinsic",
+    "author_insertKey",
+    "author_rotateKeys",
+    "transaction_unstable_submitAndWatch",
+];
+
+/// Is it safe to automatically send a call to this RPC method again if the connection
+/// is lost before we get a response?
+pub(crate) fn is_idempotent(method: &str) -> bool {
+    !NON_IDEMPOTENT_METHODS.contains(&method)
+}
@@ -0,0 +1,706 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use super::{is_idempotent, RawRpcFuture, RawRpcSubscription, RpcClient, RpcClientT};
+use crate::backend::legacy::rpc_methods::SystemHealth;
+use crate::error::{Error, RpcError};
+use futures::{stream, StreamExt};
+use instant::Instant;
+use serde_json::value::RawValue;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How should requests be distributed across the healthy endpoints of a
+/// [`MultiEndpointRpcClient`]?
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoadBalancingStrategy {
+    /// Send each request to the next healthy endpoint in turn.
+    #[default]
+    RoundRobin,
+    /// Send each request to the healthy endpoint which has been responding the fastest.
+    LeastLatency,
+}
+
+/// Configure and build a [`MultiEndpointRpcClient`].
+#[derive(Debug, Clone, Default)]
+pub struct MultiEndpointRpcClientBuilder {
+    endpoints: Vec<(String, RpcClient)>,
+    strategy: LoadBalancingStrategy,
+}
+
+impl MultiEndpointRpcClientBuilder {
+    /// Create a new [`MultiEndpointRpcClientBuilder`] with no endpoints.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an endpoint to send requests to. The name is used in logs and in
+    /// [`MultiEndpointRpcClient::endpoint_stats()`] to identify it.
+    pub fn endpoint(mut self, name: impl Into<String>, client: RpcClient) -> Self {
+        self.endpoints.push((name.into(), client));
+        self
+    }
+
+    /// How to distribute requests across the healthy endpoints. Defaults to
+    /// [`LoadBalancingStrategy::RoundRobin`].
+    pub fn strategy(mut self, strategy: LoadBalancingStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Connect to each of the given URLs, adding them as endpoints. URLs which can't be
+    /// connected to are skipped, but an error is returned if none of them can be connected to.
+    #[cfg(feature = "jsonrpsee")]
+    pub async fn connect_to_urls<U: AsRef<str>>(
+        mut self,
+        urls: impl IntoIterator<Item = U>,
+    ) -> Result<Self, Error> {
+        let mut last_err = None;
+        let mut connected = false;
+        for url in urls {
+            let url = url.as_ref();
+            match RpcClient::from_url(url).await {
+                Ok(client) => {
+                    connected = true;
+                    self.endpoints.push((url.to_owned(), client));
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to connect to {url}; skipping it: {e}");
+                    last_err = Some(e);
+                }
+            }
+        }
+
+        match (connected, last_err) {
+            (false, Some(e)) => Err(e),
+            _ => Ok(self),
+        }
+    }
+
+    /// Build a [`MultiEndpointRpcClient`]. An error is returned if no endpoints were given.
+    pub fn build(self) -> Result<MultiEndpointRpcClient, Error> {
+        if self.endpoints.is_empty() {
+            return Err(Error::Other(
+                "At least one endpoint must be given to MultiEndpointRpcClient".into(),
+            ));
+        }
+
+        let endpoints = self
+            .endpoints
+            .into_iter()
+            .map(|(name, client)| Endpoint {
+                name,
+                client,
+                state: Mutex::new(EndpointState::default()),
+            })
+            .collect();
+
+        Ok(MultiEndpointRpcClient {
+            inner: Arc::new(Inner {
+                endpoints,
+                strategy: self.strategy,
+                next: AtomicUsize::new(0),
+            }),
+        })
+    }
+}
+
+/// An [`RpcClientT`] implementation which distributes requests across several endpoints
+/// (for instance, a handful of archive nodes), failing over to other endpoints when one of
+/// them misbehaves.
+///
+/// - Requests are sent to a healthy endpoint chosen according to the [`LoadBalancingStrategy`].
+///   If that endpoint fails to respond, it is marked as unhealthy and the request is sent to
+///   the next endpoint instead. Errors returned by the node itself in response to a request
+///   (as opposed to transport errors) are handed straight back. Requests which submit something
+///   to the node, like `author_submitExtrinsic`, are not sent to another endpoint, since the
+///   failed endpoint may have received them anyway; [`RpcError::DisconnectedWillReconnect`]
+///   is returned instead.
+/// - Subscriptions are started on a healthy endpoint in the same way. If the endpoint fails
+///   while a subscription is running, the subscription emits
+///   [`RpcError::DisconnectedWillReconnect`] and ends, and subscribing again will pick another
+///   endpoint. [`crate::backend::legacy::LegacyBackend`] does this automatically.
+/// - Unhealthy endpoints are only used if no healthy ones are left. They are checked via the
+///   `system_health` RPC method by the future returned from
+///   [`MultiEndpointRpcClient::health_check_task()`], which should be spawned, and become
+///   healthy again once they respond successfully and aren't syncing.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::time::Duration;
+/// use subxt::backend::rpc::{LoadBalancingStrategy, MultiEndpointRpcClient, RpcClient};
+/// use subxt::{OnlineClient, PolkadotConfig};
+///
+/// let client = MultiEndpointRpcClient::builder()
+///     .strategy(LoadBalancingStrategy::LeastLatency)
+///     .connect_to_urls(["ws://10.0.0.1:9944", "ws://10.0.0.2:9944"])
+///     .await
+///     .unwrap()
+///     .build()
+///     .unwrap();
+///
+/// // Periodically check the health of each endpoint in the background:
+/// tokio::spawn(client.health_check_task(Duration::from_secs(10)));
+///
+/// let api = OnlineClient::<PolkadotConfig>::from_rpc_client(RpcClient::new(client))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct MultiEndpointRpcClient {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    endpoints: Vec<Endpoint>,
+    strategy: LoadBalancingStrategy,
+    // Used to pick the next endpoint for round robin.
+    next: AtomicUsize,
+}
+
+struct Endpoint {
+    name: String,
+    client: RpcClient,
+    state: Mutex<EndpointState>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct EndpointState {
+    unhealthy: bool,
+    consecutive_failures: u32,
+    latency: Option<Duration>,
+}
+
+/// Statistics about one of the endpoints of a [`MultiEndpointRpcClient`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointStats {
+    /// The name given to the endpoint.
+    pub name: String,
+    /// Is the endpoint currently considered healthy?
+    pub healthy: bool,
+    /// How many requests to this endpoint have failed in a row.
+    pub consecutive_failures: u32,
+    /// A moving average of how long requests to the endpoint take, if any have succeeded.
+    pub latency: Option<Duration>,
+}
+
+impl std::fmt::Debug for MultiEndpointRpcClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultiEndpointRpcClient")
+            .field("strategy", &self.inner.strategy)
+            .field("endpoints", &self.endpoint_stats())
+            .finish()
+    }
+}
+
+impl MultiEndpointRpcClient {
+    /// Configure and construct a [`MultiEndpointRpcClient`].
+    pub fn builder() -> MultiEndpointRpcClientBuilder {
+        MultiEndpointRpcClientBuilder::new()
+    }
+
+    /// Return statistics about each of the endpoints, in the order that they were added.
+    pub fn endpoint_stats(&self) -> Vec<EndpointStats> {
+        self.inner
+            .endpoints
+            .iter()
+            .map(|endpoint| {
+                let state = endpoint.state.lock().unwrap();
+                EndpointStats {
+                    name: endpoint.name.clone(),
+                    healthy: !state.unhealthy,
+                    consecutive_failures: state.consecutive_failures,
+                    latency: state.latency,
+                }
+            })
+            .collect()
+    }
+
+    /// Check the health of every endpoint once, using the `system_health` RPC method.
+    pub async fn check_health(&self) {
+        let checks = self
+            .inner
+            .endpoints
+            .iter()
+            .map(|endpoint| endpoint.check_health());
+        futures::future::join_all(checks).await;
+    }
+
+    /// Return a future which checks the health of every endpoint at the given interval. This
+    /// never completes, and should be spawned onto whichever async runtime is in use.
+    pub fn health_check_task(&self, interval: Duration) -> impl Future<Output = ()> + Send {
+        let this = self.clone();
+        async move {
+            loop {
+                this.check_health().await;
+                futures_timer::Delay::new(interval).await;
+            }
+        }
+    }
+
+    /// The indexes of the endpoints to try, in the order that they should be tried.
+    fn candidates(&self) -> Vec<usize> {
+        let endpoints = &self.inner.endpoints;
+        let mut candidates: Vec<(usize, EndpointState)> = endpoints
+            .iter()
+            .map(|e| e.state.lock().unwrap().clone())
+            .enumerate()
+            .collect();
+
+        match self.inner.strategy {
+            LoadBalancingStrategy::RoundRobin => {
+                let start = self.inner.next.fetch_add(1, Ordering::Relaxed) % endpoints.len();
+                candidates.rotate_left(start);
+            }
+            LoadBalancingStrategy::LeastLatency => {
+                // Endpoints we've not measured yet go first, so that they get measured.
+                candidates.sort_by_key(|(_, state)| state.latency);
+            }
+        }
+
+        // Unhealthy endpoints are only used as a last resort.
+        candidates.sort_by_key(|(_, state)| state.unhealthy);
+        candidates.into_iter().map(|(idx, _)| idx).collect()
+    }
+
+    /// Watch a subscription to some endpoint, ending it with [`RpcError::DisconnectedWillReconnect`]
+    /// if the endpoint fails, so that the subscription can be started again on another endpoint.
+    fn watch_subscription(
+        &self,
+        idx: usize,
+        subscription: RawRpcSubscription,
+    ) -> RawRpcSubscription {
+        let RawRpcSubscription { stream, id } = subscription;
+        let inner = self.inner.clone();
+
+        let stream = stream::unfold(Some(stream), move |stream| {
+            let inner = inner.clone();
+            async move {
+                let mut stream = stream?;
+                let endpoint = &inner.endpoints[idx];
+                let failed = match stream.next().await {
+                    Some(Err(e)) if is_endpoint_failure(&e) => {
+                        endpoint.record_failure(&e);
+                        true
+                    }
+                    Some(item) => return Some((item, Some(stream))),
+                    // The subscription may have ended because the endpoint is unhealthy.
+                    None => !endpoint.check_health().await,
+                };
+
+                if !failed {
+                    return None;
+                }
+                let err = RpcError::DisconnectedWillReconnect(format!(
+                    "RPC endpoint {} failed; subscribe again to use another endpoint",
+                    endpoint.name
+                ));
+                Some((Err(err), None))
+            }
+        })
+        .boxed();
+
+        RawRpcSubscription { stream, id }
+    }
+}
+
+impl Endpoint {
+    fn record_success(&self, latency: Duration) {
+        let mut state = self.state.lock().unwrap();
+        if state.unhealthy {
+            tracing::info!("RPC endpoint {} is healthy again", self.name);
+        }
+        state.unhealthy = false;
+        state.consecutive_failures = 0;
+        // An exponential moving average, so that one slow response doesn't skew things.
+        state.latency = Some(match state.latency {
+            Some(avg) => (avg * 4 + latency) / 5,
+            None => latency,
+        });
+    }
+
+    fn record_failure(&self, reason: &dyn std::fmt::Display) {
+        let mut state = self.state.lock().unwrap();
+        if !state.unhealthy {
+            tracing::warn!("RPC endpoint {} is unhealthy: {reason}", self.name);
+        }
+        state.unhealthy = true;
+        state.consecutive_failures += 1;
+    }
+
+    /// Check the health of this endpoint, returning whether it is healthy or not.
+    async fn check_health(&self) -> bool {
+        let start = Instant::now();
+        let res = self
+            .client
+            .request::<SystemHealth>("system_health", super::rpc_params![])
+            .await;
+
+        match res {
+            Ok(health) if health.is_syncing => {
+                self.record_failure(&"node is syncing");
+                false
+            }
+            Ok(health) if health.should_have_peers && health.peers == 0 => {
+                self.record_failure(&"node has no peers");
+                false
+            }
+            Ok(_) => {
+                self.record_success(start.elapsed());
+                true
+            }
+            Err(e) => {
+                self.record_failure(&e);
+                false
+            }
+        }
+    }
+}
+
+impl RpcClientT for MultiEndpointRpcClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            let mut last_err = None;
+            for idx in self.candidates() {
+                let endpoint = &self.inner.endpoints[idx];
+                let start = Instant::now();
+                match endpoint.client.request_raw(method, params.clone()).await {
+                    Err(e) if is_endpoint_failure(&e) => {
+                        endpoint.record_failure(&e);
+                        if !is_idempotent(method) {
+                            return Err(not_resent_error(endpoint, method));
+                        }
+                        last_err = Some(e);
+                    }
+                    res => {
+                        endpoint.record_success(start.elapsed());
+                        return res;
+                    }
+                }
+            }
+            Err(last_err.expect("at least one endpoint exists; qed"))
+        })
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        requests: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RawRpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        Box::pin(async move {
+            let mut last_err = None;
+            for idx in self.candidates() {
+                let endpoint = &self.inner.endpoints[idx];
+                let start = Instant::now();
+                match endpoint.client.batch_request_raw(requests.clone()).await {
+                    Err(e) if is_endpoint_failure(&e) => {
+                        endpoint.record_failure(&e);
+                        if let Some((method, _)) =
+                            requests.iter().find(|(method, _)| !is_idempotent(method))
+                        {
+                            return Err(not_resent_error(endpoint, method));
+                        }
+                        last_err = Some(e);
+                    }
+                    res => {
+                        endpoint.record_success(start.elapsed());
+                        return res;
+                    }
+                }
+            }
+            Err(last_err.expect("at least one endpoint exists; qed"))
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(async move {
+            let mut last_err = None;
+            for idx in self.candidates() {
+                let endpoint = &self.inner.endpoints[idx];
+                let start = Instant::now();
+                match endpoint
+                    .client
+                    .subscribe_raw(sub, params.clone(), unsub)
+                    .await
+                {
+                    Err(e) if is_endpoint_failure(&e) => {
+                        endpoint.record_failure(&e);
+                        if !is_idempotent(sub) {
+                            return Err(not_resent_error(endpoint, sub));
+                        }
+                        last_err = Some(e);
+                    }
+                    Err(e) => return Err(e),
+                    Ok(subscription) => {
+                        endpoint.record_success(start.elapsed());
+                        return Ok(self.watch_subscription(idx, subscription));
+                    }
+                }
+            }
+            Err(last_err.expect("at least one endpoint exists; qed"))
+        })
+    }
+}
+
+/// The error returned when an endpoint fails during a call that isn't safe to send again.
+fn not_resent_error(endpoint: &Endpoint, method: &str) -> RpcError {
+    RpcError::DisconnectedWillReconnect(format!(
+        "RPC endpoint {} failed during a call to {method}, which may or may not have been received",
+        endpoint.name
+    ))
+}
+
+/// Does this error indicate that the endpoint is misbehaving, rather than the
+/// node rejecting the request itself (which any other node would also do)?
+///
+/// Errors saying that the client can't subscribe to anything (eg because it talks HTTP)
+/// or can't handle the call right now aren't failures of the endpoint; they are handed
+/// back as they are so that the caller can decide what to do instead.
+fn is_endpoint_failure(err: &RpcError) -> bool {
+    match err {
+        RpcError::SubscriptionsNotSupported(_) | RpcError::LimitReached => false,
+        #[cfg(feature = "jsonrpsee")]
+        RpcError::ClientError(e) => !matches!(
+            e.downcast_ref::<jsonrpsee::core::Error>(),
+            Some(jsonrpsee::core::Error::Call(_))
+        ),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::legacy::LegacyBackend;
+    use crate::backend::{Backend, TransactionStatus};
+    use crate::PolkadotConfig;
+    use std::sync::atomic::AtomicBool;
+
+    /// A fake endpoint which responds with its name, or fails if told to.
+    #[derive(Clone)]
+    struct FakeEndpoint {
+        name: &'static str,
+        failing: Arc<AtomicBool>,
+        requests: Arc<AtomicUsize>,
+        // Like an HTTP client, this can't subscribe to anything.
+        http: bool,
+    }
+
+    impl FakeEndpoint {
+        fn new(name: &'static str) -> Self {
+            FakeEndpoint {
+                name,
+                failing: Arc::new(AtomicBool::new(false)),
+                requests: Arc::new(AtomicUsize::new(0)),
+                http: false,
+            }
+        }
+
+        fn http(name: &'static str) -> Self {
+            FakeEndpoint {
+                http: true,
+                ..Self::new(name)
+            }
+        }
+
+        fn set_failing(&self, failing: bool) {
+            self.failing.store(failing, Ordering::Relaxed);
+        }
+
+        fn requests(&self) -> usize {
+            self.requests.load(Ordering::Relaxed)
+        }
+
+        fn response(&self, method: &str) -> Result<Box<RawValue>, RpcError> {
+            if self.failing.load(Ordering::Relaxed) {
+                return Err(RpcError::ClientError(
+                    format!("{} is down", self.name).into(),
+                ));
+            }
+            let json = match method {
+                "system_health" => {
+                    r#"{"peers":1,"isSyncing":false,"shouldHavePeers":true}"#.to_owned()
+                }
+                "author_submitExtrinsic" => format!("\"0x{}\"", "00".repeat(32)),
+                _ => format!("\"{}\"", self.name),
+            };
+            Ok(RawValue::from_string(json).unwrap())
+        }
+    }
+
+    impl RpcClientT for FakeEndpoint {
+        fn request_raw<'a>(
+            &'a self,
+            method: &'a str,
+            _params: Option<Box<RawValue>>,
+        ) -> RawRpcFuture<'a, Box<RawValue>> {
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            let res = self.response(method);
+            Box::pin(async move { res })
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            sub: &'a str,
+            _params: Option<Box<RawValue>>,
+            _unsub: &'a str,
+        ) -> RawRpcFuture<'a, RawRpcSubscription> {
+            if self.http {
+                return Box::pin(async move {
+                    Err(RpcError::SubscriptionsNotSupported(sub.to_owned()))
+                });
+            }
+            let this = self.clone();
+            let res = self.response(sub).map(|first| {
+                // Hand back one item, and then an error if the endpoint is failing by then.
+                let next = stream::once(async move { this.response("sub") });
+                let stream = stream::once(async { Ok(first) }).chain(next).boxed();
+                RawRpcSubscription { stream, id: None }
+            });
+            Box::pin(async move { res })
+        }
+    }
+
+    fn client_with(
+        endpoints: &[FakeEndpoint],
+        strategy: LoadBalancingStrategy,
+    ) -> MultiEndpointRpcClient {
+        let mut builder = MultiEndpointRpcClient::builder().strategy(strategy);
+        for endpoint in endpoints {
+            builder = builder.endpoint(endpoint.name, RpcClient::new(endpoint.clone()));
+        }
+        builder.build().unwrap()
+    }
+
+    async fn request(client: &MultiEndpointRpcClient) -> Result<String, RpcError> {
+        let res = client.request_raw("foo", None).await?;
+        Ok(serde_json::from_str(res.get()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn round_robin_distributes_requests() {
+        let endpoints = [FakeEndpoint::new("a"), FakeEndpoint::new("b")];
+        let client = client_with(&endpoints, LoadBalancingStrategy::RoundRobin);
+
+        let mut names = Vec::new();
+        for _ in 0..4 {
+            names.push(request(&client).await.unwrap());
+        }
+
+        assert_eq!(names, vec!["a", "b", "a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn requests_fail_over_to_healthy_endpoints() {
+        let endpoints = [FakeEndpoint::new("a"), FakeEndpoint::new("b")];
+        let client = client_with(&endpoints, LoadBalancingStrategy::RoundRobin);
+        endpoints[0].set_failing(true);
+
+        for _ in 0..4 {
+            assert_eq!(request(&client).await.unwrap(), "b");
+        }
+
+        // "a" was tried once, and then avoided since it's unhealthy.
+        assert_eq!(endpoints[0].requests(), 1);
+        let stats = client.endpoint_stats();
+        assert!(!stats[0].healthy);
+        assert_eq!(stats[0].consecutive_failures, 1);
+        assert!(stats[1].healthy);
+
+        // Once "a" passes a health check, it'll be used again.
+        endpoints[0].set_failing(false);
+        client.check_health().await;
+        assert!(client.endpoint_stats()[0].healthy);
+
+        let mut names = Vec::new();
+        for _ in 0..2 {
+            names.push(request(&client).await.unwrap());
+        }
+        names.sort();
+        assert_eq!(names, vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn submissions_are_not_sent_to_another_endpoint() {
+        let endpoints = [FakeEndpoint::new("a"), FakeEndpoint::new("b")];
+        let client = client_with(&endpoints, LoadBalancingStrategy::RoundRobin);
+        endpoints[0].set_failing(true);
+
+        let res = client.request_raw("author_submitExtrinsic", None).await;
+        assert!(matches!(res, Err(RpcError::DisconnectedWillReconnect(_))));
+        assert_eq!(endpoints[1].requests(), 0);
+
+        // Now that "a" is unhealthy, "b" will be used first:
+        let res = client.request_raw("author_submitExtrinsic", None).await;
+        assert!(res.is_ok());
+        assert_eq!(endpoints[1].requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn error_returned_if_all_endpoints_fail() {
+        let endpoints = [FakeEndpoint::new("a"), FakeEndpoint::new("b")];
+        let client = client_with(&endpoints, LoadBalancingStrategy::LeastLatency);
+        endpoints[0].set_failing(true);
+        endpoints[1].set_failing(true);
+
+        assert!(request(&client).await.is_err());
+        assert!(client.endpoint_stats().iter().all(|s| !s.healthy));
+    }
+
+    #[tokio::test]
+    async fn subscriptions_end_with_reconnect_error_if_endpoint_fails() {
+        let endpoints = [FakeEndpoint::new("a")];
+        let client = client_with(&endpoints, LoadBalancingStrategy::RoundRobin);
+
+        let sub = client.subscribe_raw("sub", None, "unsub").await.unwrap();
+        endpoints[0].set_failing(true);
+
+        let items: Vec<_> = sub
+            .stream
+            .map(|r| r.map(|v| v.get().to_owned()))
+            .collect()
+            .await;
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].as_ref().unwrap(), "\"a\"");
+        assert!(matches!(
+            items[1],
+            Err(RpcError::DisconnectedWillReconnect(_))
+        ));
+        assert!(!client.endpoint_stats()[0].healthy);
+    }
+
+    #[tokio::test]
+    async fn transactions_can_be_submitted_to_http_endpoints() {
+        let endpoints = [FakeEndpoint::http("a"), FakeEndpoint::http("b")];
+        let client = client_with(&endpoints, LoadBalancingStrategy::RoundRobin);
+        let backend = LegacyBackend::<PolkadotConfig>::new(RpcClient::new(client.clone()));
+
+        // Not being able to watch the transaction, the backend falls back to submitting it.
+        let statuses: Vec<_> = backend
+            .submit_transaction(&[1, 2, 3])
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        assert!(matches!(&statuses[..], [Ok(TransactionStatus::Validated)]));
+        assert_eq!(endpoints[0].requests() + endpoints[1].requests(), 1);
+        assert!(client.endpoint_stats().iter().all(|s| s.healthy));
+    }
+}
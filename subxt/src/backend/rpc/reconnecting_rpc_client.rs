@@ -0,0 +1,308 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use super::rpc_client::jsonrpsee_helpers::{Client, Error as JsonRpseeError};
+use super::{is_idempotent, ExponentialBackoff, RawRpcFuture, RawRpcSubscription, RpcClientT};
+use crate::error::{Error, RpcError};
+use futures::future::{self, Either};
+use futures::lock::Mutex as AsyncMutex;
+use futures::{stream, StreamExt};
+use serde_json::value::RawValue;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long to wait for the connection to be reported as closed when a
+/// subscription ends, before assuming that it ended for some other reason.
+const DISCONNECT_GRACE_PERIOD: Duration = Duration::from_millis(500);
+
+/// Configure and build a [`ReconnectingRpcClient`].
+#[derive(Debug, Clone)]
+pub struct ReconnectingRpcClientBuilder {
+    backoff: ExponentialBackoff,
+    max_attempts: Option<usize>,
+}
+
+impl Default for ReconnectingRpcClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReconnectingRpcClientBuilder {
+    /// Create a new [`ReconnectingRpcClientBuilder`].
+    pub fn new() -> Self {
+        ReconnectingRpcClientBuilder {
+            backoff: ExponentialBackoff::default(),
+            max_attempts: None,
+        }
+    }
+
+    /// How long to wait between each attempt to reconnect.
+    pub fn backoff(mut self, backoff: ExponentialBackoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Give up after this many consecutive failed attempts to reconnect, handing back
+    /// the last error to any requests waiting on the connection. By default, we will
+    /// keep trying to reconnect forever.
+    pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Connect to the given URL, returning an error if the first connection attempt fails.
+    pub async fn build<U: Into<String>>(self, url: U) -> Result<ReconnectingRpcClient, Error> {
+        let url = url.into();
+        let client = super::rpc_client::jsonrpsee_helpers::client(&url)
+            .await
+            .map_err(|e| RpcError::ClientError(Box::new(e)))?;
+
+        Ok(ReconnectingRpcClient {
+            inner: Arc::new(Inner {
+                url,
+                backoff: self.backoff,
+                max_attempts: self.max_attempts,
+                client: AsyncMutex::new(Arc::new(client)),
+            }),
+        })
+    }
+}
+
+/// An [`RpcClientT`] implementation which re-establishes the connection to the node if it
+/// is lost, waiting between attempts according to some [`ExponentialBackoff`].
+///
+/// - Requests which fail because the connection was lost are re-issued once it has been
+///   re-established, so callers won't notice the disconnection beyond a delay. The exception
+///   is requests which submit something to the node, like `author_submitExtrinsic`; the node
+///   may have received these before the connection was lost, so instead of sending them again,
+///   a [`RpcError::DisconnectedWillReconnect`] error is returned.
+/// - Subscriptions can't be resumed transparently, because any notifications sent while
+///   disconnected are lost. Instead, they will emit a [`RpcError::DisconnectedWillReconnect`]
+///   error and then end, and may be subscribed to again. The
+///   [`crate::backend::legacy::LegacyBackend`] does this automatically for the block header
+///   and runtime version subscriptions that it exposes.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::time::Duration;
+/// use subxt::backend::rpc::{ExponentialBackoff, ReconnectingRpcClient, RpcClient};
+/// use subxt::{OnlineClient, PolkadotConfig};
+///
+/// let reconnecting_client = ReconnectingRpcClient::builder()
+///     .backoff(ExponentialBackoff::from_millis(100).max_delay(Duration::from_secs(5)))
+///     .build("ws://127.0.0.1:9944")
+///     .await
+///     .unwrap();
+///
+/// let rpc_client = RpcClient::new(reconnecting_client);
+/// let api = OnlineClient::<PolkadotConfig>::from_rpc_client(rpc_client)
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ReconnectingRpcClient {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    url: String,
+    backoff: ExponentialBackoff,
+    max_attempts: Option<usize>,
+    // Locked while reconnecting, so that only one reconnection happens at a time
+    // and anything waiting for a client is handed the new one.
+    client: AsyncMutex<Arc<Client>>,
+}
+
+impl std::fmt::Debug for ReconnectingRpcClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReconnectingRpcClient")
+            .field("url", &self.inner.url)
+            .field("backoff", &self.inner.backoff)
+            .field("max_attempts", &self.inner.max_attempts)
+            .finish()
+    }
+}
+
+impl ReconnectingRpcClient {
+    /// Configure and construct a [`ReconnectingRpcClient`].
+    pub fn builder() -> ReconnectingRpcClientBuilder {
+        ReconnectingRpcClientBuilder::new()
+    }
+
+    /// Return a connected client, reconnecting first if necessary.
+    async fn current_client(&self) -> Result<Arc<Client>, RpcError> {
+        let client = self.inner.client.lock().await.clone();
+        if client.is_connected() {
+            Ok(client)
+        } else {
+            self.reconnect(&client).await
+        }
+    }
+
+    /// Replace the given (disconnected) client with a newly connected one. If it's
+    /// already been replaced, then the replacement is handed back.
+    async fn reconnect(&self, disconnected: &Arc<Client>) -> Result<Arc<Client>, RpcError> {
+        let mut current = self.inner.client.lock().await;
+        if !Arc::ptr_eq(&current, disconnected) && current.is_connected() {
+            return Ok(current.clone());
+        }
+
+        let mut delays = self.inner.backoff.delays();
+        let mut attempts = 0;
+        loop {
+            match super::rpc_client::jsonrpsee_helpers::client(&self.inner.url).await {
+                Ok(client) => {
+                    tracing::debug!("Reconnected to {}", self.inner.url);
+                    *current = Arc::new(client);
+                    return Ok(current.clone());
+                }
+                Err(e) => {
+                    attempts += 1;
+                    if self.inner.max_attempts.is_some_and(|max| attempts >= max) {
+                        return Err(RpcError::ClientError(Box::new(e)));
+                    }
+
+                    let delay = delays.next().expect("delays never end; qed");
+                    tracing::debug!(
+                        "Failed to reconnect to {} (attempt {attempts}); retrying in {delay:?}: {e}",
+                        self.inner.url
+                    );
+                    futures_timer::Delay::new(delay).await;
+                }
+            }
+        }
+    }
+}
+
+impl RpcClientT for ReconnectingRpcClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            loop {
+                let client = self.current_client().await?;
+                match RpcClientT::request_raw(&*client, method, params.clone()).await {
+                    Err(e) if is_disconnect_error(&e) || !client.is_connected() => {
+                        if !is_idempotent(method) {
+                            return Err(not_resent_error(method));
+                        }
+                        tracing::debug!("Connection lost during {method} request; reconnecting");
+                        self.reconnect(&client).await?;
+                    }
+                    res => return res,
+                }
+            }
+        })
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        requests: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RawRpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        Box::pin(async move {
+            loop {
+                let client = self.current_client().await?;
+                match RpcClientT::batch_request_raw(&*client, requests.clone()).await {
+                    Err(e) if is_disconnect_error(&e) || !client.is_connected() => {
+                        if let Some((method, _)) =
+                            requests.iter().find(|(method, _)| !is_idempotent(method))
+                        {
+                            return Err(not_resent_error(method));
+                        }
+                        tracing::debug!("Connection lost during batch request; reconnecting");
+                        self.reconnect(&client).await?;
+                    }
+                    res => return res,
+                }
+            }
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(async move {
+            let (client, subscription) = loop {
+                let client = self.current_client().await?;
+                match RpcClientT::subscribe_raw(&*client, sub, params.clone(), unsub).await {
+                    Err(e) if is_disconnect_error(&e) || !client.is_connected() => {
+                        if !is_idempotent(sub) {
+                            return Err(not_resent_error(sub));
+                        }
+                        tracing::debug!("Connection lost during {sub} subscription; reconnecting");
+                        self.reconnect(&client).await?;
+                    }
+                    res => break (client, res?),
+                }
+            };
+
+            let RawRpcSubscription { stream, id } = subscription;
+            let stream = stream::unfold(Some((stream, client)), |state| async move {
+                let (mut stream, client) = state?;
+                let item = stream.next().await;
+
+                let disconnected = match &item {
+                    Some(Ok(_)) => false,
+                    Some(Err(e)) => is_disconnect_error(e) || !client.is_connected(),
+                    None => has_disconnected(&client).await,
+                };
+                if disconnected {
+                    let err = RpcError::DisconnectedWillReconnect(
+                        "the subscription was ended because the connection was lost".to_owned(),
+                    );
+                    return Some((Err(err), None));
+                }
+
+                let item = item?;
+                Some((item, Some((stream, client))))
+            })
+            .boxed();
+
+            Ok(RawRpcSubscription { stream, id })
+        })
+    }
+}
+
+/// The error returned when the connection is lost during a call that isn't safe to send again.
+/// We'll reconnect on the next call.
+fn not_resent_error(method: &str) -> RpcError {
+    RpcError::DisconnectedWillReconnect(format!(
+        "the connection was lost during a call to {method}, which may or may not have been received"
+    ))
+}
+
+/// Has the connection been lost, given some error from a request to the client?
+fn is_disconnect_error(err: &RpcError) -> bool {
+    let RpcError::ClientError(err) = err else {
+        return false;
+    };
+    matches!(
+        err.downcast_ref::<JsonRpseeError>(),
+        Some(JsonRpseeError::RestartNeeded(_))
+    )
+}
+
+/// Has the client been disconnected? Subscriptions can end just before the client
+/// notices that the connection is closed, so we give it a moment to do so.
+async fn has_disconnected(client: &Client) -> bool {
+    if !client.is_connected() {
+        return true;
+    }
+    let on_disconnect = Box::pin(client.on_disconnect());
+    let timeout = futures_timer::Delay::new(DISCONNECT_GRACE_PERIOD);
+    matches!(
+        future::select(on_disconnect, timeout).await,
+        Either::Left(_)
+    )
+}
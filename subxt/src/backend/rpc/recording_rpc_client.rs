@@ -0,0 +1,254 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use super::{RawRpcFuture, RawRpcSubscription, RpcClient, RpcClientT};
+use crate::error::{Error, RpcError};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A single entry in a recording. Recordings are written as one JSON encoded
+/// entry per line.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum Entry {
+    /// A request and the response to it. Requests made in a batch are
+    /// recorded individually.
+    Request {
+        method: String,
+        params: Option<Box<RawValue>>,
+        result: RecordedResult,
+    },
+    /// An attempt to subscribe to something. Notifications for it refer to it
+    /// by the index given here.
+    Subscribe {
+        method: String,
+        params: Option<Box<RawValue>>,
+        index: u64,
+        result: Result<Option<String>, String>,
+    },
+    /// A notification received on some subscription.
+    Notification { index: u64, result: RecordedResult },
+}
+
+/// The outcome of some request or notification. Errors are recorded only
+/// as strings, since the original error types can't be serialized.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum RecordedResult {
+    Ok(Box<RawValue>),
+    Err(String),
+}
+
+impl From<&Result<Box<RawValue>, RpcError>> for RecordedResult {
+    fn from(res: &Result<Box<RawValue>, RpcError>) -> Self {
+        match res {
+            Ok(val) => RecordedResult::Ok(val.clone()),
+            Err(e) => RecordedResult::Err(error_to_string(e)),
+        }
+    }
+}
+
+impl RecordedResult {
+    /// Hand back the recorded value, or an error which displays like the one recorded.
+    pub(super) fn to_result(&self) -> Result<Box<RawValue>, RpcError> {
+        match self {
+            RecordedResult::Ok(val) => Ok(val.clone()),
+            RecordedResult::Err(e) => Err(RpcError::ClientError(e.clone().into())),
+        }
+    }
+}
+
+/// Errors are replayed as [`RpcError::ClientError`]s, so record what is inside those
+/// to avoid nesting them each time a recording is replayed.
+fn error_to_string(err: &RpcError) -> String {
+    match err {
+        RpcError::ClientError(e) => e.to_string(),
+        e => e.to_string(),
+    }
+}
+
+/// An [`RpcClientT`] implementation which wraps some other [`RpcClient`], and records every
+/// request, response and subscription notification that passes through it. The recording
+/// can be served back by a [`super::ReplayRpcClient`], to run code against a node that
+/// isn't there.
+///
+/// Each call is written out as it completes, one JSON encoded entry per line. Errors
+/// writing the recording are logged rather than being handed back.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use subxt::backend::rpc::{RecordingRpcClient, RpcClient};
+/// use subxt::{OnlineClient, PolkadotConfig};
+///
+/// let rpc_client = RpcClient::from_url("ws://127.0.0.1:9944").await.unwrap();
+/// let recording_client = RecordingRpcClient::to_file(rpc_client, "session.jsonl").unwrap();
+///
+/// let api = OnlineClient::<PolkadotConfig>::from_rpc_client(RpcClient::new(recording_client))
+///     .await
+///     .unwrap();
+///
+/// // Anything done with `api` from here will be recorded.
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RecordingRpcClient {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    client: RpcClient,
+    writer: Mutex<Box<dyn Write + Send>>,
+    next_subscription_index: AtomicU64,
+}
+
+impl std::fmt::Debug for RecordingRpcClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordingRpcClient")
+            .field("client", &self.inner.client)
+            .finish()
+    }
+}
+
+impl RecordingRpcClient {
+    /// Record the calls made through the given client to the given writer.
+    pub fn new<W: Write + Send + 'static>(client: RpcClient, writer: W) -> Self {
+        RecordingRpcClient {
+            inner: Arc::new(Inner {
+                client,
+                writer: Mutex::new(Box::new(writer)),
+                next_subscription_index: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Record the calls made through the given client to a file at the given path. The file
+    /// is created if it doesn't exist, and replaced if it does.
+    pub fn to_file<P: AsRef<Path>>(client: RpcClient, path: P) -> Result<Self, Error> {
+        let file = std::fs::File::create(path)?;
+        Ok(Self::new(client, std::io::LineWriter::new(file)))
+    }
+}
+
+fn record(inner: &Inner, entry: Entry) {
+    let mut line = serde_json::to_vec(&entry).expect("entries can always be serialized; qed");
+    line.push(b'\n');
+
+    let mut writer = inner.writer.lock().unwrap();
+    if let Err(e) = writer.write_all(&line).and_then(|_| writer.flush()) {
+        tracing::warn!("Failed to write RPC recording: {e}");
+    }
+}
+
+impl RpcClientT for RecordingRpcClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            let res = self.inner.client.request_raw(method, params.clone()).await;
+            record(
+                &self.inner,
+                Entry::Request {
+                    method: method.to_owned(),
+                    params,
+                    result: (&res).into(),
+                },
+            );
+            res
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(async move {
+            let res = self
+                .inner
+                .client
+                .subscribe_raw(sub, params.clone(), unsub)
+                .await;
+
+            let index = self
+                .inner
+                .next_subscription_index
+                .fetch_add(1, Ordering::Relaxed);
+            record(
+                &self.inner,
+                Entry::Subscribe {
+                    method: sub.to_owned(),
+                    params,
+                    index,
+                    result: match &res {
+                        Ok(s) => Ok(s.id.clone()),
+                        Err(e) => Err(error_to_string(e)),
+                    },
+                },
+            );
+
+            let RawRpcSubscription { stream, id } = res?;
+            let inner = self.inner.clone();
+            let stream = stream
+                .inspect(move |item| {
+                    record(
+                        &inner,
+                        Entry::Notification {
+                            index,
+                            result: item.into(),
+                        },
+                    )
+                })
+                .boxed();
+            Ok(RawRpcSubscription { stream, id })
+        })
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        requests: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RawRpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        Box::pin(async move {
+            let results = match self.inner.client.batch_request_raw(requests.clone()).await {
+                Ok(results) => results,
+                Err(e) => {
+                    // The batch failed as a whole, so record the failure against each request
+                    // in it, so that they fail in the same way when replayed.
+                    for (method, params) in requests {
+                        record(
+                            &self.inner,
+                            Entry::Request {
+                                method: method.to_owned(),
+                                params,
+                                result: RecordedResult::Err(error_to_string(&e)),
+                            },
+                        );
+                    }
+                    return Err(e);
+                }
+            };
+            for ((method, params), res) in requests.into_iter().zip(&results) {
+                record(
+                    &self.inner,
+                    Entry::Request {
+                        method: method.to_owned(),
+                        params,
+                        result: res.into(),
+                    },
+                );
+            }
+            Ok(results)
+        })
+    }
+}
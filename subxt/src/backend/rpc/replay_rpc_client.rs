@@ -0,0 +1,460 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use super::recording_rpc_client::{Entry, RecordedResult};
+use super::{RawRpcFuture, RawRpcSubscription, RpcClientT};
+use crate::error::{Error, RpcError};
+use futures::{stream, StreamExt};
+use serde_json::value::RawValue;
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// An [`RpcClientT`] implementation which serves back the responses and subscription
+/// notifications captured by a [`super::RecordingRpcClient`], without needing a node.
+///
+/// Each call is answered with the response that was recorded for a call to the same
+/// method with the same parameters. Calls which were made several times are answered
+/// in the order that they were recorded in, and once they have all been served, the last
+/// response is repeated. If no call with the same parameters was recorded, an error
+/// naming the method and parameters is handed back.
+///
+/// Transactions are signed differently each time, and so calls which are given a signed
+/// transaction (like `author_submitExtrinsic`) are instead answered with the next unused
+/// response recorded for the same method if the parameters don't match. Use
+/// [`ReplayRpcClient::allow_any_params`] to do the same for other methods.
+///
+/// Subscriptions hand back the notifications that were recorded for them, and then end.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use subxt::backend::rpc::{ReplayRpcClient, RpcClient};
+/// use subxt::{OnlineClient, PolkadotConfig};
+///
+/// let replay_client = ReplayRpcClient::from_file("session.jsonl").unwrap();
+///
+/// let api = OnlineClient::<PolkadotConfig>::from_rpc_client(RpcClient::new(replay_client))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ReplayRpcClient {
+    requests: Mutex<HashMap<String, Vec<Recorded<RecordedResult>>>>,
+    subscriptions: Mutex<HashMap<String, Vec<Recorded<RecordedSubscription>>>>,
+    any_params_methods: HashSet<String>,
+}
+
+/// Methods whose parameters contain a signed transaction, and so which can't be expected
+/// to be called with the same parameters each time.
+const SIGNED_TRANSACTION_METHODS: &[&str] = &[
+    "author_submitExtrinsic",
+    "author_submitAndWatchExtrinsic",
+    "transaction_unstable_submitAndWatch",
+    "system_dryRun",
+];
+
+#[derive(Debug)]
+struct Recorded<T> {
+    params: Option<String>,
+    value: T,
+    used: bool,
+}
+
+#[derive(Debug)]
+struct RecordedSubscription {
+    result: Result<Option<String>, String>,
+    notifications: Vec<RecordedResult>,
+}
+
+impl ReplayRpcClient {
+    /// Load a recording from a file written by [`super::RecordingRpcClient::to_file`].
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = std::fs::File::open(path)?;
+        Self::from_reader(std::io::BufReader::new(file))
+    }
+
+    /// Load a recording from anything that a [`super::RecordingRpcClient`] was writing to.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let mut requests: HashMap<String, Vec<Recorded<RecordedResult>>> = HashMap::new();
+        let mut subscriptions: HashMap<u64, (String, Recorded<RecordedSubscription>)> =
+            HashMap::new();
+        // Subscriptions in the order that they were made, so we can replay them in order.
+        let mut subscription_order = Vec::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line)? {
+                Entry::Request {
+                    method,
+                    params,
+                    result,
+                } => requests.entry(method).or_default().push(Recorded {
+                    params: params.map(|p| p.get().to_owned()),
+                    value: result,
+                    used: false,
+                }),
+                Entry::Subscribe {
+                    method,
+                    params,
+                    index,
+                    result,
+                } => {
+                    let sub = Recorded {
+                        params: params.map(|p| p.get().to_owned()),
+                        value: RecordedSubscription {
+                            result,
+                            notifications: Vec::new(),
+                        },
+                        used: false,
+                    };
+                    subscriptions.insert(index, (method, sub));
+                    subscription_order.push(index);
+                }
+                Entry::Notification { index, result } => {
+                    let (_, sub) = subscriptions.get_mut(&index).ok_or_else(|| {
+                        Error::Other(format!(
+                            "RPC recording contains a notification for unknown subscription {index}"
+                        ))
+                    })?;
+                    sub.value.notifications.push(result);
+                }
+            }
+        }
+
+        let mut subscriptions_by_method: HashMap<String, Vec<_>> = HashMap::new();
+        for index in subscription_order {
+            let (method, sub) = subscriptions
+                .remove(&index)
+                .expect("index was recorded; qed");
+            subscriptions_by_method.entry(method).or_default().push(sub);
+        }
+
+        Ok(ReplayRpcClient {
+            requests: Mutex::new(requests),
+            subscriptions: Mutex::new(subscriptions_by_method),
+            any_params_methods: SIGNED_TRANSACTION_METHODS
+                .iter()
+                .map(|&m| m.to_owned())
+                .collect(),
+        })
+    }
+
+    /// If a call to the given method (or subscription) is made with parameters that weren't
+    /// recorded, answer it with the next unused response recorded for that method rather
+    /// than returning an error.
+    pub fn allow_any_params(mut self, method: impl Into<String>) -> Self {
+        self.any_params_methods.insert(method.into());
+        self
+    }
+
+    fn find_recorded<'a, T>(
+        &self,
+        recorded: &'a mut [Recorded<T>],
+        method: &str,
+        params: Option<&RawValue>,
+    ) -> Option<&'a mut Recorded<T>> {
+        let any_params = self.any_params_methods.contains(method);
+        find_recorded(recorded, params, any_params)
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        params: Option<Box<RawValue>>,
+    ) -> Result<Box<RawValue>, RpcError> {
+        let mut requests = self.requests.lock().unwrap();
+        let recorded = requests
+            .get_mut(method)
+            .and_then(|recorded| self.find_recorded(recorded, method, params.as_deref()))
+            .ok_or_else(|| no_recording_error(method, params.as_deref()))?;
+
+        recorded.value.to_result()
+    }
+}
+
+/// Find the best recorded value to hand back given some params, marking it as used. If
+/// `any_params` is true, values recorded with different params can be handed back too.
+fn find_recorded<'a, T>(
+    recorded: &'a mut [Recorded<T>],
+    params: Option<&RawValue>,
+    any_params: bool,
+) -> Option<&'a mut Recorded<T>> {
+    let params = params.map(|p| p.get());
+    let same_params = |r: &Recorded<T>| r.params.as_deref() == params;
+
+    let idx = recorded
+        .iter()
+        .position(|r| !r.used && same_params(r))
+        .or_else(|| recorded.iter().rposition(same_params))
+        .or_else(|| recorded.iter().position(|r| any_params && !r.used))?;
+
+    let recorded = &mut recorded[idx];
+    recorded.used = true;
+    Some(recorded)
+}
+
+fn no_recording_error(method: &str, params: Option<&RawValue>) -> RpcError {
+    let params = params.map_or("", |p| p.get());
+    RpcError::ClientError(format!("no response to {method}({params}) was recorded").into())
+}
+
+impl RpcClientT for ReplayRpcClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move { self.request(method, params) })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        _unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(async move {
+            let mut subscriptions = self.subscriptions.lock().unwrap();
+            let recorded = subscriptions
+                .get_mut(sub)
+                .and_then(|recorded| self.find_recorded(recorded, sub, params.as_deref()))
+                .ok_or_else(|| no_recording_error(sub, params.as_deref()))?;
+
+            let id = match &recorded.value.result {
+                Ok(id) => id.clone(),
+                Err(e) => return Err(RpcError::ClientError(e.clone().into())),
+            };
+
+            let notifications: Vec<_> = recorded
+                .value
+                .notifications
+                .iter()
+                .map(RecordedResult::to_result)
+                .collect();
+
+            Ok(RawRpcSubscription {
+                stream: stream::iter(notifications).boxed(),
+                id,
+            })
+        })
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        requests: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RawRpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        Box::pin(async move {
+            Ok(requests
+                .into_iter()
+                .map(|(method, params)| self.request(method, params))
+                .collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::rpc::{rpc_params, RecordingRpcClient, RpcClient};
+    use std::io::Write;
+    use std::sync::Arc;
+
+    /// A fake node which answers requests with their params, and
+    /// subscriptions with a couple of notifications.
+    struct FakeNode;
+
+    impl RpcClientT for FakeNode {
+        fn request_raw<'a>(
+            &'a self,
+            method: &'a str,
+            params: Option<Box<RawValue>>,
+        ) -> RawRpcFuture<'a, Box<RawValue>> {
+            Box::pin(async move {
+                match method {
+                    "fail" => Err(RpcError::ClientError("failed".into())),
+                    _ => {
+                        Ok(params.unwrap_or_else(|| RawValue::from_string("null".into()).unwrap()))
+                    }
+                }
+            })
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            _sub: &'a str,
+            _params: Option<Box<RawValue>>,
+            _unsub: &'a str,
+        ) -> RawRpcFuture<'a, RawRpcSubscription> {
+            Box::pin(async move {
+                let notifications =
+                    ["1", "2"].map(|n| Ok(RawValue::from_string(n.into()).unwrap()));
+                Ok(RawRpcSubscription {
+                    stream: stream::iter(notifications).boxed(),
+                    id: Some("sub_id".to_owned()),
+                })
+            })
+        }
+    }
+
+    /// Somewhere to write a recording to that we can read back.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn make_calls(client: &RpcClient) -> (Vec<String>, String) {
+        let mut results = Vec::new();
+        for params in [rpc_params![1], rpc_params![2], rpc_params![1]] {
+            let res: Result<Vec<u8>, _> = client.request("echo", params).await;
+            results.push(format!("{res:?}"));
+        }
+        let res: Result<(), _> = client.request("fail", rpc_params![]).await;
+        results.push(format!("{:?}", res.map_err(|e| e.to_string())));
+
+        let batch = client
+            .batch_request::<Vec<u8>>([("echo", rpc_params![3]), ("echo", rpc_params![4])])
+            .await
+            .unwrap();
+        results.extend(batch.into_iter().map(|r| format!("{r:?}")));
+
+        let sub = client
+            .subscribe::<u8>("sub", rpc_params![], "unsub")
+            .await
+            .unwrap();
+        let notifications = sub.map(|n| n.unwrap()).collect::<Vec<_>>().await;
+        (results, format!("{notifications:?}"))
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_session() {
+        let buffer = SharedBuffer::default();
+        let recording_client = RpcClient::new(RecordingRpcClient::new(
+            RpcClient::new(FakeNode),
+            buffer.clone(),
+        ));
+        let recorded = make_calls(&recording_client).await;
+
+        let recording = buffer.0.lock().unwrap().clone();
+        let replay_client = RpcClient::new(ReplayRpcClient::from_reader(&recording[..]).unwrap());
+        let replayed = make_calls(&replay_client).await;
+
+        assert_eq!(recorded.1, "[1, 2]");
+        assert_eq!(recorded.0[0], "Ok([1])");
+        assert_eq!(recorded.0[1], "Ok([2])");
+        assert_eq!(recorded.0[4], "Ok([3])");
+        // Errors are only recorded as strings, so compare how they're displayed.
+        assert_eq!(replayed, recorded);
+    }
+
+    #[tokio::test]
+    async fn replays_batches_which_failed_as_a_whole() {
+        struct FailingBatches;
+        impl RpcClientT for FailingBatches {
+            fn request_raw<'a>(
+                &'a self,
+                _method: &'a str,
+                _params: Option<Box<RawValue>>,
+            ) -> RawRpcFuture<'a, Box<RawValue>> {
+                Box::pin(async { Err(RpcError::ClientError("unused".into())) })
+            }
+            fn subscribe_raw<'a>(
+                &'a self,
+                _sub: &'a str,
+                _params: Option<Box<RawValue>>,
+                _unsub: &'a str,
+            ) -> RawRpcFuture<'a, RawRpcSubscription> {
+                Box::pin(async { Err(RpcError::ClientError("unused".into())) })
+            }
+            fn batch_request_raw<'a>(
+                &'a self,
+                _requests: Vec<(&'a str, Option<Box<RawValue>>)>,
+            ) -> RawRpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+                Box::pin(async { Err(RpcError::ClientError("batch failed".into())) })
+            }
+        }
+
+        let buffer = SharedBuffer::default();
+        let recording_client =
+            RecordingRpcClient::new(RpcClient::new(FailingBatches), buffer.clone());
+        let requests = || vec![("a", None), ("b", None)];
+        let err = recording_client
+            .batch_request_raw(requests())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "RPC error: batch failed");
+
+        let recording = buffer.0.lock().unwrap().clone();
+        let replay_client = ReplayRpcClient::from_reader(&recording[..]).unwrap();
+        let results = replay_client.batch_request_raw(requests()).await.unwrap();
+        assert_eq!(results.len(), 2);
+        for res in results {
+            assert_eq!(res.unwrap_err().to_string(), err.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn errors_if_params_were_not_recorded() {
+        let recording =
+            br#"{"request":{"method":"state_getStorage","params":["0x01"],"result":{"ok":"0x02"}}}"#;
+        let replay_client = RpcClient::new(ReplayRpcClient::from_reader(&recording[..]).unwrap());
+
+        let err = replay_client
+            .request::<String>("state_getStorage", rpc_params!["0x03"])
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains(r#"no response to state_getStorage(["0x03"]) was recorded"#));
+    }
+
+    #[tokio::test]
+    async fn transaction_submissions_fall_back_to_other_params() {
+        let recording = br#"{"request":{"method":"author_submitExtrinsic","params":["0x01"],"result":{"ok":"0x01"}}}"#;
+        let replay_client = RpcClient::new(ReplayRpcClient::from_reader(&recording[..]).unwrap());
+
+        let res: String = replay_client
+            .request("author_submitExtrinsic", rpc_params!["0x02"])
+            .await
+            .unwrap();
+        assert_eq!(res, "0x01");
+    }
+
+    #[tokio::test]
+    async fn other_methods_can_fall_back_to_other_params() {
+        let recording = br#"{"request":{"method":"submit","params":[1],"result":{"ok":"0x01"}}}"#;
+        let replay_client = ReplayRpcClient::from_reader(&recording[..])
+            .unwrap()
+            .allow_any_params("submit");
+        let replay_client = RpcClient::new(replay_client);
+
+        let res: String = replay_client
+            .request("submit", rpc_params![2])
+            .await
+            .unwrap();
+        assert_eq!(res, "0x01");
+
+        let err = replay_client
+            .request::<String>("other", rpc_params![])
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("no response to other() was recorded"));
+    }
+}
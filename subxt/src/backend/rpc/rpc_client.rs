@@ -2,7 +2,7 @@
 // This file is dual-licensed as Apache-2.0 or GPL-3.0.
 // see LICENSE for license details.
 
-use super::{RawRpcSubscription, RpcClientT};
+use super::{middleware, RawRpcSubscription, RpcClientT};
 use crate::error::Error;
 use futures::{Stream, StreamExt};
 use serde::{de::DeserializeOwned, Serialize};
@@ -20,7 +20,20 @@ pub struct RpcClient {
 impl RpcClient {
     #[cfg(feature = "jsonrpsee")]
     /// Create a default RPC client pointed at some URL, currently based on [`jsonrpsee`].
+    ///
+    /// On native builds, `http://` and `https://` URLs are connected to over HTTP. Such a
+    /// client cannot subscribe to anything (attempts to do so will return
+    /// [`crate::error::RpcError::SubscriptionsNotSupported`]), and so is best suited to
+    /// one-shot requests like fetching storage or submitting transactions. Any other URL
+    /// is connected to over a websocket.
     pub async fn from_url<U: AsRef<str>>(url: U) -> Result<Self, Error> {
+        #[cfg(feature = "native")]
+        if jsonrpsee_helpers::is_http_url(url.as_ref()) {
+            let client = jsonrpsee_helpers::http_client(url.as_ref())
+                .map_err(|e| crate::error::RpcError::ClientError(Box::new(e)))?;
+            return Ok(Self::new(client));
+        }
+
         let client = jsonrpsee_helpers::client(url.as_ref())
             .await
             .map_err(|e| crate::error::RpcError::ClientError(Box::new(e)))?;
@@ -34,6 +47,15 @@ impl RpcClient {
         }
     }
 
+    /// Wrap this client in some [`middleware::RpcMiddleware`], which can observe or alter the
+    /// calls made through it. Middleware can be stacked by calling this several times, in which
+    /// case the middleware added last sees each call first.
+    ///
+    /// See the [`middleware`] module for the middleware that is provided.
+    pub fn with_middleware<M: middleware::RpcMiddleware>(self, middleware: M) -> Self {
+        Self::new(middleware.wrap(self))
+    }
+
     /// Make an RPC request, given a method name and some parameters.
     ///
     /// See [`RpcParams`] and the [`rpc_params!`] macro for an example of how to
@@ -48,6 +70,50 @@ impl RpcClient {
         Ok(val)
     }
 
+    /// Make a batch of RPC requests, given the method name and parameters of each one. Where
+    /// the underlying client supports it, these are sent to the node as a single JSON-RPC batch
+    /// request, saving a round trip per request.
+    ///
+    /// The results are handed back in the same order as the requests were given. The outer
+    /// error is returned if the batch as a whole could not be sent, and each inner error if
+    /// the corresponding request failed or its response could not be decoded.
+    ///
+    /// Nodes typically limit how many requests can be given in a single batch, so large
+    /// numbers of requests may need splitting into several batches.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use subxt::backend::rpc::{rpc_params, RpcClient};
+    ///
+    /// let rpc_client = RpcClient::from_url("ws://127.0.0.1:9944").await.unwrap();
+    ///
+    /// let block_hashes = rpc_client
+    ///     .batch_request::<Option<String>>(
+    ///         (0u32..10).map(|n| ("chain_getBlockHash", rpc_params![n])),
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn batch_request<'a, Res: DeserializeOwned>(
+        &self,
+        requests: impl IntoIterator<Item = (&'a str, RpcParams)>,
+    ) -> Result<Vec<Result<Res, Error>>, Error> {
+        let requests = requests
+            .into_iter()
+            .map(|(method, params)| (method, params.build()))
+            .collect();
+        let res = self.client.batch_request_raw(requests).await?;
+        let vals = res
+            .into_iter()
+            .map(|r| Ok(serde_json::from_str(r?.get())?))
+            .collect();
+        Ok(vals)
+    }
+
     /// Subscribe to an RPC endpoint, providing the parameters and the method to call to
     /// unsubscribe from it again.
     ///
@@ -227,15 +293,26 @@ impl<Res: DeserializeOwned> Stream for RpcSubscription<Res> {
 
 // helpers for a jsonrpsee specific RPC client.
 #[cfg(all(feature = "jsonrpsee", feature = "native"))]
-mod jsonrpsee_helpers {
+pub(super) mod jsonrpsee_helpers {
     pub use jsonrpsee::{
         client_transport::ws::{Receiver, Sender, Url, WsTransportClientBuilder},
         core::{
             client::{Client, ClientBuilder},
             Error,
         },
+        http_client::{HttpClient, HttpClientBuilder},
     };
 
+    /// Build HTTP RPC client from URL
+    pub fn http_client(url: &str) -> Result<HttpClient, Error> {
+        HttpClientBuilder::default().build(url)
+    }
+
+    /// Should the given URL be connected to over HTTP rather than a websocket?
+    pub fn is_http_url(url: &str) -> bool {
+        Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+    }
+
     /// Build WS RPC client from URL
     pub async fn client(url: &str) -> Result<Client, Error> {
         let (sender, receiver) = ws_transport(url).await?;
@@ -255,7 +332,7 @@ mod jsonrpsee_helpers {
 
 // helpers for a jsonrpsee specific RPC client.
 #[cfg(all(feature = "jsonrpsee", feature = "web", target_arch = "wasm32"))]
-mod jsonrpsee_helpers {
+pub(super) mod jsonrpsee_helpers {
     pub use jsonrpsee::{
         client_transport::web,
         core::{
@@ -274,3 +351,18 @@ mod jsonrpsee_helpers {
             .build_with_wasm(sender, receiver))
     }
 }
+
+#[cfg(all(test, feature = "jsonrpsee", feature = "native"))]
+mod test {
+    use super::jsonrpsee_helpers::is_http_url;
+
+    #[test]
+    fn http_urls_are_detected() {
+        assert!(is_http_url("http://127.0.0.1:9933"));
+        assert!(is_http_url("https://rpc.example.com/some/path"));
+        assert!(is_http_url("HTTPS://rpc.example.com"));
+        assert!(!is_http_url("ws://127.0.0.1:9944"));
+        assert!(!is_http_url("wss://rpc.example.com"));
+        assert!(!is_http_url("not a url"));
+    }
+}
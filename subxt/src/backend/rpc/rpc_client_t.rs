@@ -3,7 +3,7 @@
 // see LICENSE for license details.
 
 use crate::error::RpcError;
-use futures::Stream;
+use futures::{future, Stream};
 use std::{future::Future, pin::Pin};
 
 // Re-exporting for simplicity since it's used a bunch in the trait definition.
@@ -51,6 +51,30 @@ pub trait RpcClientT: Send + Sync + 'static {
         params: Option<Box<RawValue>>,
         unsub: &'a str,
     ) -> RawRpcFuture<'a, RawRpcSubscription>;
+
+    /// Make a batch of raw requests, returning the result of each one in the same order
+    /// that the requests were given in. Implementations should expect that the params of each
+    /// request will either be `None`, or be an already-serialized JSON array of parameters.
+    ///
+    /// The outer error is returned if the batch as a whole failed (for instance because the
+    /// connection was lost), and each inner error if that specific request failed.
+    ///
+    /// By default, this makes each request individually and concurrently via
+    /// [`RpcClientT::request_raw`]. Implementations which can send a JSON-RPC batch request
+    /// in a single round trip should override this.
+    ///
+    /// Prefer to use the interface provided on [`super::RpcClient`] where possible.
+    fn batch_request_raw<'a>(
+        &'a self,
+        requests: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RawRpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        Box::pin(async move {
+            let requests = requests
+                .into_iter()
+                .map(|(method, params)| self.request_raw(method, params));
+            Ok(future::join_all(requests).await)
+        })
+    }
 }
 
 /// A boxed future that is returned from the [`RpcClientT`] methods.
@@ -0,0 +1,265 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Helpers for testing things built on top of the [`Backend`] trait, and for testing
+//! things built on top of an [`OnlineClient`] using a [`MockBackend`].
+
+use super::mock::MockBackend;
+use super::{
+    Backend, BlockRef, RuntimeVersion, StorageResponse, StreamOf, StreamOfResults,
+    TransactionStatus,
+};
+use crate::config::substrate::{Digest, SubstrateHeader};
+use crate::config::Header;
+use crate::error::Error;
+use crate::tx::Signer;
+use crate::utils::{AccountId32, MultiSignature};
+use crate::{Config, Metadata, OnlineClient, PolkadotConfig, SubstrateConfig};
+use codec::{Decode, Encode};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+pub type Hash = <SubstrateConfig as Config>::Hash;
+pub type TestHeader = <SubstrateConfig as Config>::Header;
+
+/// A tiny backend which serves a single block and some storage from memory,
+/// standing in for a third party implementation.
+#[derive(Clone)]
+pub struct InMemoryBackend {
+    pub header: TestHeader,
+    pub storage: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// How many storage, block and runtime API requests have been made.
+    pub requests: Arc<AtomicUsize>,
+}
+
+impl InMemoryBackend {
+    pub fn new() -> Self {
+        let header = SubstrateHeader {
+            parent_hash: Hash::zero(),
+            number: 1,
+            state_root: Hash::repeat_byte(1),
+            extrinsics_root: Hash::repeat_byte(2),
+            digest: Digest::default(),
+        };
+        let storage = [
+            (vec![1, 2, 3], vec![1]),
+            (vec![1, 2, 4], vec![2]),
+            (vec![2, 2, 2], vec![3]),
+        ]
+        .into_iter()
+        .collect();
+
+        InMemoryBackend {
+            header,
+            storage,
+            requests: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn runtime_version(&self) -> RuntimeVersion {
+        RuntimeVersion {
+            spec_version: 1,
+            transaction_version: 1,
+        }
+    }
+
+    /// The number of requests made so far.
+    pub fn request_count(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    fn note_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn header_stream(&self) -> StreamOfResults<(TestHeader, BlockRef<Hash>)> {
+        let item = (self.header.clone(), BlockRef::from_hash(self.header.hash()));
+        StreamOf::new(Box::pin(futures::stream::iter([Ok(item)])))
+    }
+}
+
+#[async_trait::async_trait]
+impl Backend<SubstrateConfig> for InMemoryBackend {
+    async fn storage_fetch_values(
+        &self,
+        keys: Vec<Vec<u8>>,
+        _at: Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        self.note_request();
+        let values: Vec<_> = keys
+            .into_iter()
+            .filter_map(|key| {
+                let value = self.storage.get(&key)?.clone();
+                Some(Ok(StorageResponse { key, value }))
+            })
+            .collect();
+        Ok(StreamOf::new(Box::pin(futures::stream::iter(values))))
+    }
+
+    async fn storage_fetch_descendant_keys(
+        &self,
+        key: Vec<u8>,
+        _at: Hash,
+    ) -> Result<StreamOfResults<Vec<u8>>, Error> {
+        self.note_request();
+        let keys: Vec<_> = self
+            .storage
+            .keys()
+            .filter(|k| k.starts_with(&key))
+            .map(|k| Ok(k.clone()))
+            .collect();
+        Ok(StreamOf::new(Box::pin(futures::stream::iter(keys))))
+    }
+
+    async fn storage_fetch_descendant_values(
+        &self,
+        key: Vec<u8>,
+        _at: Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        self.note_request();
+        let values: Vec<_> = self
+            .storage
+            .iter()
+            .filter(|(k, _)| k.starts_with(&key))
+            .map(|(k, v)| {
+                Ok(StorageResponse {
+                    key: k.clone(),
+                    value: v.clone(),
+                })
+            })
+            .collect();
+        Ok(StreamOf::new(Box::pin(futures::stream::iter(values))))
+    }
+
+    async fn genesis_hash(&self) -> Result<Hash, Error> {
+        Ok(Hash::zero())
+    }
+
+    async fn block_header(&self, at: Hash) -> Result<Option<TestHeader>, Error> {
+        self.note_request();
+        Ok((at == self.header.hash()).then(|| self.header.clone()))
+    }
+
+    async fn block_body(&self, at: Hash) -> Result<Option<Vec<Vec<u8>>>, Error> {
+        self.note_request();
+        Ok((at == self.header.hash()).then(Vec::new))
+    }
+
+    async fn latest_finalized_block_ref(&self) -> Result<BlockRef<Hash>, Error> {
+        Ok(BlockRef::from_hash(self.header.hash()))
+    }
+
+    async fn latest_best_block_ref(&self) -> Result<BlockRef<Hash>, Error> {
+        Ok(BlockRef::from_hash(self.header.hash()))
+    }
+
+    async fn current_runtime_version(&self) -> Result<RuntimeVersion, Error> {
+        Ok(self.runtime_version())
+    }
+
+    async fn stream_runtime_version(&self) -> Result<StreamOfResults<RuntimeVersion>, Error> {
+        let version = self.runtime_version();
+        Ok(StreamOf::new(Box::pin(futures::stream::iter([Ok(
+            version,
+        )]))))
+    }
+
+    async fn stream_all_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(TestHeader, BlockRef<Hash>)>, Error> {
+        Ok(self.header_stream())
+    }
+
+    async fn stream_best_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(TestHeader, BlockRef<Hash>)>, Error> {
+        Ok(self.header_stream())
+    }
+
+    async fn stream_finalized_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(TestHeader, BlockRef<Hash>)>, Error> {
+        Ok(self.header_stream())
+    }
+
+    async fn submit_transaction(
+        &self,
+        _bytes: &[u8],
+    ) -> Result<StreamOfResults<TransactionStatus<Hash>>, Error> {
+        Err(Error::Other("not supported".into()))
+    }
+
+    async fn call(
+        &self,
+        method: &str,
+        _call_parameters: Option<&[u8]>,
+        _at: Hash,
+    ) -> Result<Vec<u8>, Error> {
+        self.note_request();
+        Ok(method.as_bytes().to_vec())
+    }
+}
+
+/// Signs everything as the account `[1; 32]` with the same signature, so that
+/// transactions are predictable.
+pub struct FixedSigner;
+
+impl Signer<PolkadotConfig> for FixedSigner {
+    fn account_id(&self) -> AccountId32 {
+        AccountId32([1; 32])
+    }
+    fn address(&self) -> <PolkadotConfig as Config>::Address {
+        AccountId32([1; 32]).into()
+    }
+    fn sign(&self, _signer_payload: &[u8]) -> MultiSignature {
+        MultiSignature::Sr25519([2; 64])
+    }
+}
+
+/// The header of a block with the given number and parent hash, and nothing else in it.
+pub fn header(number: u32, parent_hash: Hash) -> TestHeader {
+    SubstrateHeader {
+        parent_hash,
+        number,
+        state_root: Hash::zero(),
+        extrinsics_root: Hash::zero(),
+        digest: Digest::default(),
+    }
+}
+
+/// The hash of the block that [`mock_client()`] starts with.
+pub fn first_block_hash() -> Hash {
+    header(1, Hash::zero()).hash()
+}
+
+/// A [`MockBackend`] serving the metadata in the given file from the `artifacts` folder and a
+/// single block (number 1, whose parent hash is zero), and an [`OnlineClient`] built on it.
+pub async fn mock_client(
+    metadata_file: &str,
+) -> (MockBackend<PolkadotConfig>, OnlineClient<PolkadotConfig>) {
+    let bytes = std::fs::read(format!("../artifacts/{metadata_file}")).unwrap();
+    mock_client_with_metadata(Metadata::decode(&mut &*bytes).unwrap()).await
+}
+
+/// Like [`mock_client()`], but serving the given metadata.
+pub async fn mock_client_with_metadata(
+    metadata: Metadata,
+) -> (MockBackend<PolkadotConfig>, OnlineClient<PolkadotConfig>) {
+    let backend = MockBackend::new();
+    backend.set_metadata(metadata);
+    backend.add_block(header(1, Hash::zero()), vec![]);
+    let api = OnlineClient::from_backend(Arc::new(backend.clone()))
+        .await
+        .unwrap();
+    (backend, api)
+}
+
+/// Like [`mock_client()`], serving the small test metadata, and with a nonce of 7 for every
+/// account, ready for transactions to be created and submitted.
+pub async fn mock_tx_client() -> (MockBackend<PolkadotConfig>, OnlineClient<PolkadotConfig>) {
+    let (backend, api) = mock_client("polkadot_metadata_small.scale").await;
+    backend.set_call_response("AccountNonceApi_account_nonce", 7u32.encode());
+    (backend, api)
+}
@@ -11,6 +11,675 @@
 //! Everything in this module is **unstable**, meaning that it could change without
 //! warning at any time.
 
+mod follow_stream;
+mod follow_stream_driver;
+mod follow_stream_unpin;
+mod storage_items;
+
 pub mod rpc_methods;
 
+use self::rpc_methods::{
+    FollowEvent, MethodResponse, RuntimeEvent, StorageQuery, StorageQueryType, StorageResultType,
+};
+use crate::backend::{
+    rpc::RpcClient, Backend, BlockRef, RuntimeVersion, StorageResponse, StreamOf, StreamOfResults,
+    TransactionStatus,
+};
+use crate::config::BlockHash;
+use crate::error::{Error, RpcError};
+use crate::Config;
+use async_trait::async_trait;
+use follow_stream_driver::{FollowStreamDriver, FollowStreamDriverHandle};
+use futures::{Stream, StreamExt};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::task::Poll;
+use storage_items::StorageItems;
+
+// Expose the RPC methods.
 pub use rpc_methods::UnstableRpcMethods;
+
+/// Configure and build an [`UnstableBackend`].
+pub struct UnstableBackendBuilder<T> {
+    max_block_life: usize,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Config> Default for UnstableBackendBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config> UnstableBackendBuilder<T> {
+    /// Create a new [`UnstableBackendBuilder`].
+    pub fn new() -> Self {
+        Self {
+            max_block_life: usize::MAX,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// The age of a block is defined here as the difference between the current finalized block number
+    /// and the block number of a given block. Once the difference equals or exceeds the number given
+    /// here, the block is unpinned.
+    ///
+    /// By default, we will never automatically unpin blocks, but if the number of pinned blocks that we
+    /// keep hold of exceeds the number that the server can tolerate, then a `stop` event is generated and
+    /// we are forced to resubscribe, losing any pinned blocks.
+    pub fn max_block_life(mut self, max_block_life: usize) -> Self {
+        self.max_block_life = max_block_life;
+        self
+    }
+
+    /// Given an [`RpcClient`] to use to make requests, this returns a tuple of an [`UnstableBackend`],
+    /// which implements the [`Backend`] trait, and an [`UnstableBackendDriver`] which must be polled in
+    /// order for the backend to make progress.
+    pub fn build(self, client: RpcClient) -> (UnstableBackend<T>, UnstableBackendDriver<T>) {
+        // Construct the underlying follow_stream layers:
+        let rpc_methods = UnstableRpcMethods::new(client);
+        let follow_stream =
+            follow_stream::FollowStream::<T::Hash>::from_methods(rpc_methods.clone());
+        let follow_stream_unpin = follow_stream_unpin::FollowStreamUnpin::<T::Hash>::from_methods(
+            follow_stream,
+            rpc_methods.clone(),
+            self.max_block_life,
+        );
+        let follow_stream_driver = FollowStreamDriver::new(follow_stream_unpin);
+
+        // Wrap these into the backend and driver that we'll expose.
+        let backend = UnstableBackend {
+            methods: rpc_methods,
+            follow_handle: follow_stream_driver.handle(),
+        };
+        let driver = UnstableBackendDriver {
+            driver: follow_stream_driver,
+        };
+
+        (backend, driver)
+    }
+}
+
+/// Driver for the [`UnstableBackend`]. This must be polled in order for the
+/// backend to make progress.
+#[derive(Debug)]
+pub struct UnstableBackendDriver<T: Config> {
+    driver: FollowStreamDriver<T::Hash>,
+}
+
+impl<T: Config> Stream for UnstableBackendDriver<T> {
+    type Item = <FollowStreamDriver<T::Hash> as Stream>::Item;
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        self.driver.poll_next_unpin(cx)
+    }
+}
+
+/// The unstable backend, built on top of the new `chainHead_*` and `transaction_*` JSON-RPC
+/// methods. A single `chainHead_follow` subscription is shared by everything, and blocks are
+/// pinned and unpinned automatically as [`BlockRef`]s to them are created and dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::sync::Arc;
+/// use subxt::{
+///     backend::{rpc::RpcClient, unstable::UnstableBackend},
+///     OnlineClient, PolkadotConfig,
+/// };
+///
+/// let rpc_client = RpcClient::from_url("ws://127.0.0.1:9944").await.unwrap();
+/// let (backend, mut driver) = UnstableBackend::<PolkadotConfig>::builder().build(rpc_client);
+///
+/// // The driver must be polled for the backend to make progress.
+/// tokio::spawn(async move {
+///     while let Some(res) = futures::StreamExt::next(&mut driver).await {
+///         if let Err(e) = res {
+///             eprintln!("Error driving unstable backend: {e}");
+///         }
+///     }
+/// });
+///
+/// let api = OnlineClient::<PolkadotConfig>::from_backend(Arc::new(backend))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UnstableBackend<T: Config> {
+    // RPC methods we'll want to call:
+    methods: UnstableRpcMethods<T>,
+    // A handle to the chainHead_follow subscription:
+    follow_handle: FollowStreamDriverHandle<T::Hash>,
+}
+
+impl<T: Config> UnstableBackend<T> {
+    /// Configure and construct an [`UnstableBackend`] and the associated [`UnstableBackendDriver`].
+    pub fn builder() -> UnstableBackendBuilder<T> {
+        UnstableBackendBuilder::new()
+    }
+
+    /// Stream block headers based on the provided filter fn
+    async fn stream_headers<F, I>(
+        &self,
+        f: F,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error>
+    where
+        F: Fn(FollowEvent<follow_stream_unpin::BlockRef<T::Hash>>) -> I + Send + Sync + 'static,
+        I: IntoIterator<Item = follow_stream_unpin::BlockRef<T::Hash>> + Send + 'static,
+        <I as IntoIterator>::IntoIter: Send,
+    {
+        let sub_id = get_subscription_id(&self.follow_handle).await?;
+        let sub_id = Arc::new(sub_id);
+        let methods = self.methods.clone();
+
+        // Keep track of the latest subscription ID, since this will change
+        // if the underlying subscription is restarted.
+        let mut current_sub_id = sub_id;
+        let headers = self
+            .follow_handle
+            .subscribe()
+            .flat_map(move |msg| {
+                let block_refs: Vec<_> = match msg {
+                    follow_stream::FollowStreamMsg::Ready(sub_id) => {
+                        current_sub_id = Arc::new(sub_id);
+                        Vec::new()
+                    }
+                    follow_stream::FollowStreamMsg::Event(ev) => f(ev).into_iter().collect(),
+                };
+                let sub_id = current_sub_id.clone();
+                futures::stream::iter(block_refs.into_iter().map(move |r| (sub_id.clone(), r)))
+            })
+            .filter_map(move |(sub_id, block_ref)| {
+                let methods = methods.clone();
+                async move {
+                    // Blocks we can't obtain a header for are skipped.
+                    let res = methods
+                        .chainhead_unstable_header(&sub_id, block_ref.hash())
+                        .await
+                        .transpose()?;
+
+                    let header = match res {
+                        Ok(header) => header,
+                        Err(e) => return Some(Err(e)),
+                    };
+
+                    Some(Ok((header, block_ref.into())))
+                }
+            });
+
+        Ok(StreamOf(Box::pin(headers)))
+    }
+}
+
+#[async_trait]
+impl<T: Config + Send + Sync + 'static> Backend<T> for UnstableBackend<T> {
+    async fn storage_fetch_values(
+        &self,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        let queries = keys.into_iter().map(|key| StorageQuery {
+            key,
+            query_type: StorageQueryType::Value,
+        });
+
+        let storage_items =
+            StorageItems::from_methods(queries, at, &self.follow_handle, self.methods.clone())
+                .await?;
+
+        let storage_result_stream = storage_items.filter_map(|val| async move {
+            let val = match val {
+                Ok(val) => val,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let StorageResultType::Value(result) = val.result else {
+                return None;
+            };
+            Some(Ok(StorageResponse {
+                key: val.key.0,
+                value: result.0,
+            }))
+        });
+
+        Ok(StreamOf(Box::pin(storage_result_stream)))
+    }
+
+    async fn storage_fetch_descendant_keys(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<Vec<u8>>, Error> {
+        // Ask for hashes, and then just ignore them and return the keys that come back.
+        let query = StorageQuery {
+            key,
+            query_type: StorageQueryType::DescendantsHashes,
+        };
+
+        let storage_items = StorageItems::from_methods(
+            std::iter::once(query),
+            at,
+            &self.follow_handle,
+            self.methods.clone(),
+        )
+        .await?;
+
+        let storage_result_stream = storage_items.map(|val| val.map(|v| v.key.0));
+        Ok(StreamOf(Box::pin(storage_result_stream)))
+    }
+
+    async fn storage_fetch_descendant_values(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        let query = StorageQuery {
+            key,
+            query_type: StorageQueryType::DescendantsValues,
+        };
+
+        let storage_items = StorageItems::from_methods(
+            std::iter::once(query),
+            at,
+            &self.follow_handle,
+            self.methods.clone(),
+        )
+        .await?;
+
+        let storage_result_stream = storage_items.filter_map(|val| async move {
+            let val = match val {
+                Ok(val) => val,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let StorageResultType::Value(result) = val.result else {
+                return None;
+            };
+            Some(Ok(StorageResponse {
+                key: val.key.0,
+                value: result.0,
+            }))
+        });
+
+        Ok(StreamOf(Box::pin(storage_result_stream)))
+    }
+
+    async fn genesis_hash(&self) -> Result<T::Hash, Error> {
+        self.methods.chainspec_v1_genesis_hash().await
+    }
+
+    async fn block_header(&self, at: T::Hash) -> Result<Option<T::Header>, Error> {
+        let sub_id = get_subscription_id(&self.follow_handle).await?;
+        self.methods.chainhead_unstable_header(&sub_id, at).await
+    }
+
+    async fn block_body(&self, at: T::Hash) -> Result<Option<Vec<Vec<u8>>>, Error> {
+        let sub_id = get_subscription_id(&self.follow_handle).await?;
+
+        // Subscribe to the body response and get our operationId back.
+        let follow_events = self.follow_handle.subscribe().events();
+        let status = self.methods.chainhead_unstable_body(&sub_id, at).await?;
+        let operation_id = match status {
+            MethodResponse::LimitReached => return Err(RpcError::LimitReached.into()),
+            MethodResponse::Started(s) => s.operation_id,
+        };
+
+        // Wait for the response to come back with the correct operationId.
+        let mut exts_stream = follow_events.filter_map(|ev| {
+            let out = match ev {
+                FollowEvent::OperationBodyDone(body) if body.operation_id == operation_id => {
+                    let exts: Vec<_> = body.value.into_iter().map(|ext| ext.0).collect();
+                    Some(Ok(exts))
+                }
+                FollowEvent::OperationInaccessible(res) if res.operation_id == operation_id => {
+                    Some(Err(Error::Other(
+                        "chainHead_body operation is inaccessible; try again later".into(),
+                    )))
+                }
+                FollowEvent::OperationError(res) if res.operation_id == operation_id => {
+                    Some(Err(Error::Other(res.error)))
+                }
+                // The subscription that the operation was started on has gone away,
+                // so we won't hear about it again.
+                FollowEvent::Stop => Some(Err(RpcError::SubscriptionDropped.into())),
+                _ => None,
+            };
+            std::future::ready(out)
+        });
+
+        exts_stream.next().await.transpose()
+    }
+
+    async fn latest_finalized_block_ref(&self) -> Result<BlockRef<T::Hash>, Error> {
+        let next_ref: Option<BlockRef<T::Hash>> = self
+            .follow_handle
+            .subscribe()
+            .events()
+            .filter_map(|ev| {
+                let out = match ev {
+                    FollowEvent::Initialized(init) => Some(init.finalized_block_hash.into()),
+                    _ => None,
+                };
+                std::future::ready(out)
+            })
+            .next()
+            .await;
+
+        next_ref.ok_or_else(|| RpcError::SubscriptionDropped.into())
+    }
+
+    async fn latest_best_block_ref(&self) -> Result<BlockRef<T::Hash>, Error> {
+        let next_ref: Option<BlockRef<T::Hash>> = self
+            .follow_handle
+            .subscribe()
+            .events()
+            .filter_map(|ev| {
+                let out = match ev {
+                    FollowEvent::BestBlockChanged(best) => Some(best.best_block_hash.into()),
+                    _ => None,
+                };
+                std::future::ready(out)
+            })
+            .next()
+            .await;
+
+        next_ref.ok_or_else(|| RpcError::SubscriptionDropped.into())
+    }
+
+    async fn current_runtime_version(&self) -> Result<RuntimeVersion, Error> {
+        // Just start a stream of version infos, and return the first value we get from it.
+        let runtime_version = self.stream_runtime_version().await?.next().await;
+        match runtime_version {
+            None => Err(Error::Rpc(RpcError::SubscriptionDropped)),
+            Some(Err(e)) => Err(e),
+            Some(Ok(version)) => Ok(version),
+        }
+    }
+
+    async fn stream_runtime_version(&self) -> Result<StreamOfResults<RuntimeVersion>, Error> {
+        // Keep track of runtime details announced in new blocks, and then when blocks
+        // are finalized, find the latest of these that has been finalized.
+        let mut runtimes = HashMap::new();
+        let runtime_stream = self
+            .follow_handle
+            .subscribe()
+            .events()
+            .filter_map(move |ev| {
+                let output = match ev {
+                    FollowEvent::Initialized(ev) => {
+                        runtimes.clear();
+                        ev.finalized_block_runtime
+                    }
+                    FollowEvent::NewBlock(ev) => {
+                        if let Some(runtime) = ev.new_runtime {
+                            runtimes.insert(ev.block_hash.hash(), runtime);
+                        }
+                        None
+                    }
+                    FollowEvent::Finalized(ev) => {
+                        let next_runtime = ev
+                            .finalized_block_hashes
+                            .iter()
+                            .rev()
+                            .filter_map(|h| runtimes.get(&h.hash()).cloned())
+                            .next();
+
+                        // Keep only runtime details that relate to blocks which may still be finalized.
+                        for hash in ev
+                            .finalized_block_hashes
+                            .iter()
+                            .chain(ev.pruned_block_hashes.iter())
+                        {
+                            runtimes.remove(&hash.hash());
+                        }
+
+                        next_runtime
+                    }
+                    _ => None,
+                };
+
+                let runtime_event = match output {
+                    None => return std::future::ready(None),
+                    Some(ev) => ev,
+                };
+
+                let runtime_details = match runtime_event {
+                    RuntimeEvent::Invalid(err) => {
+                        return std::future::ready(Some(Err(Error::Other(err.error))))
+                    }
+                    RuntimeEvent::Valid(ev) => ev,
+                };
+
+                std::future::ready(Some(Ok(RuntimeVersion {
+                    spec_version: runtime_details.spec.spec_version,
+                    transaction_version: runtime_details.spec.transaction_version,
+                })))
+            });
+
+        Ok(StreamOf(Box::pin(runtime_stream)))
+    }
+
+    async fn stream_all_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        self.stream_headers(|ev| match ev {
+            FollowEvent::Initialized(ev) => Some(ev.finalized_block_hash),
+            FollowEvent::NewBlock(ev) => Some(ev.block_hash),
+            _ => None,
+        })
+        .await
+    }
+
+    async fn stream_best_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        self.stream_headers(|ev| match ev {
+            FollowEvent::BestBlockChanged(ev) => Some(ev.best_block_hash),
+            _ => None,
+        })
+        .await
+    }
+
+    async fn stream_finalized_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        self.stream_headers(|ev| match ev {
+            FollowEvent::Initialized(ev) => vec![ev.finalized_block_hash],
+            FollowEvent::Finalized(ev) => ev.finalized_block_hashes,
+            _ => vec![],
+        })
+        .await
+    }
+
+    async fn submit_transaction(
+        &self,
+        extrinsic: &[u8],
+    ) -> Result<StreamOfResults<TransactionStatus<T::Hash>>, Error> {
+        // We care about new and finalized block hashes, so that we can hand back
+        // references to blocks (which keep them pinned) along with the status of the
+        // transaction. Subscribe before submitting so we don't miss any blocks.
+        let mut follow_events = self.follow_handle.subscribe().events();
+        let mut tx_progress = self
+            .methods
+            .transaction_unstable_submit_and_watch(extrinsic)
+            .await?;
+
+        let mut seen_blocks = HashMap::new();
+        let mut follow_done = false;
+        let mut waiting_for_block: Option<(T::Hash, bool)> = None;
+
+        let tx_stream = futures::stream::poll_fn(move |cx| {
+            loop {
+                // Keep track of any blocks that the follow subscription tells us about.
+                if !follow_done {
+                    match follow_events.poll_next_unpin(cx) {
+                        Poll::Ready(Some(ev)) => {
+                            note_seen_blocks(&mut seen_blocks, ev);
+                            continue;
+                        }
+                        Poll::Ready(None) => {
+                            follow_done = true;
+                            continue;
+                        }
+                        Poll::Pending => {}
+                    }
+                }
+
+                // If the transaction was seen in a block that we've not yet seen via the
+                // follow subscription, then wait until it shows up there before reporting it.
+                if let Some((hash, is_finalized)) = waiting_for_block.take() {
+                    let block_ref = match seen_blocks.get(&hash) {
+                        Some(block_ref) => Some(block_ref.clone()),
+                        // Nothing more will show up, so we'll have to hand back an unpinned ref.
+                        None if follow_done => Some(BlockRef::from_hash(hash)),
+                        None => None,
+                    };
+
+                    let Some(hash) = block_ref else {
+                        // We'll be woken up when new follow events come in.
+                        waiting_for_block = Some((hash, is_finalized));
+                        return Poll::Pending;
+                    };
+
+                    let status = if is_finalized {
+                        TransactionStatus::InFinalizedBlock { hash }
+                    } else {
+                        TransactionStatus::InBestBlock { hash }
+                    };
+                    return Poll::Ready(Some(Ok(status)));
+                }
+
+                let status = match tx_progress.poll_next_unpin(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(None) => return Poll::Ready(None),
+                    Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                    Poll::Ready(Some(Ok(status))) => status,
+                };
+
+                let status = match status {
+                    rpc_methods::TransactionStatus::Validated => TransactionStatus::Validated,
+                    rpc_methods::TransactionStatus::Broadcasted { num_peers } => {
+                        TransactionStatus::Broadcasted { num_peers }
+                    }
+                    rpc_methods::TransactionStatus::BestChainBlockIncluded { block: None } => {
+                        // The transaction is no longer in a best block; there's no
+                        // equivalent status to report so we wait for the next one.
+                        continue;
+                    }
+                    rpc_methods::TransactionStatus::BestChainBlockIncluded {
+                        block: Some(block),
+                    } => {
+                        waiting_for_block = Some((block.hash, false));
+                        continue;
+                    }
+                    rpc_methods::TransactionStatus::Finalized { block } => {
+                        waiting_for_block = Some((block.hash, true));
+                        continue;
+                    }
+                    rpc_methods::TransactionStatus::Error { error } => {
+                        TransactionStatus::Error { message: error }
+                    }
+                    rpc_methods::TransactionStatus::Invalid { error } => {
+                        TransactionStatus::Invalid { message: error }
+                    }
+                    rpc_methods::TransactionStatus::Dropped { error, .. } => {
+                        TransactionStatus::Dropped { message: error }
+                    }
+                };
+                return Poll::Ready(Some(Ok(status)));
+            }
+        });
+
+        Ok(StreamOf(Box::pin(tx_stream)))
+    }
+
+    async fn call(
+        &self,
+        method: &str,
+        call_parameters: Option<&[u8]>,
+        at: T::Hash,
+    ) -> Result<Vec<u8>, Error> {
+        let sub_id = get_subscription_id(&self.follow_handle).await?;
+
+        // Subscribe to the call response and get our operationId back.
+        let follow_events = self.follow_handle.subscribe().events();
+        let call_parameters = call_parameters.unwrap_or(&[]);
+        let status = self
+            .methods
+            .chainhead_unstable_call(&sub_id, at, method, call_parameters)
+            .await?;
+        let operation_id = match status {
+            MethodResponse::LimitReached => return Err(RpcError::LimitReached.into()),
+            MethodResponse::Started(s) => s.operation_id,
+        };
+
+        // Wait for the response to come back with the correct operationId.
+        let mut call_data_stream = follow_events.filter_map(|ev| {
+            let out = match ev {
+                FollowEvent::OperationCallDone(res) if res.operation_id == operation_id => {
+                    Some(Ok(res.output.0))
+                }
+                FollowEvent::OperationInaccessible(res) if res.operation_id == operation_id => {
+                    Some(Err(Error::Other(
+                        "chainHead_call operation is inaccessible; try again later".into(),
+                    )))
+                }
+                FollowEvent::OperationError(res) if res.operation_id == operation_id => {
+                    Some(Err(Error::Other(res.error)))
+                }
+                FollowEvent::Stop => Some(Err(RpcError::SubscriptionDropped.into())),
+                _ => None,
+            };
+            std::future::ready(out)
+        });
+
+        match call_data_stream.next().await {
+            Some(res) => res,
+            None => Err(RpcError::SubscriptionDropped.into()),
+        }
+    }
+}
+
+/// Record any blocks that we're told about, so that transaction statuses can
+/// hand back references to them.
+fn note_seen_blocks<Hash: BlockHash>(
+    seen_blocks: &mut HashMap<Hash, BlockRef<Hash>>,
+    ev: FollowEvent<follow_stream_unpin::BlockRef<Hash>>,
+) {
+    match ev {
+        FollowEvent::Initialized(ev) => {
+            let block_ref = ev.finalized_block_hash;
+            seen_blocks.insert(block_ref.hash(), block_ref.into());
+        }
+        FollowEvent::NewBlock(ev) => {
+            let block_ref = ev.block_hash;
+            seen_blocks.insert(block_ref.hash(), block_ref.into());
+        }
+        FollowEvent::Finalized(ev) => {
+            for block_ref in ev.pruned_block_hashes {
+                seen_blocks.remove(&block_ref.hash());
+            }
+        }
+        FollowEvent::Stop => {
+            // Blocks from the old subscription are no longer pinned.
+            seen_blocks.clear();
+        }
+        _ => {}
+    }
+}
+
+/// A helper to obtain a subscription ID.
+async fn get_subscription_id<Hash: BlockHash>(
+    follow_handle: &FollowStreamDriverHandle<Hash>,
+) -> Result<String, Error> {
+    let Some(sub_id) = follow_handle.subscribe().subscription_id().await else {
+        return Err(RpcError::SubscriptionDropped.into());
+    };
+
+    Ok(sub_id)
+}
@@ -0,0 +1,185 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use super::follow_stream_driver::FollowStreamDriverHandle;
+use super::follow_stream_unpin::BlockRef;
+use super::rpc_methods::{
+    FollowEvent, MethodResponse, StorageQuery, StorageResult, UnstableRpcMethods,
+};
+use crate::config::Config;
+use crate::error::{Error, RpcError};
+use futures::{stream, Stream, StreamExt};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Obtain a stream of storage items given some query. this handles continuing
+/// and stopping under the hood, and returns a stream of `StorageResult`s.
+pub struct StorageItems {
+    inner: Pin<Box<dyn Stream<Item = Result<StorageResult, Error>> + Send + 'static>>,
+}
+
+impl StorageItems {
+    /// Start a `chainHead_storage` operation for the given queries at the given block, and
+    /// hand back a stream of the results. If the node discards some of the queries that we
+    /// ask for, we'll ask for those again once the rest of the results have been handed back.
+    pub async fn from_methods<T: Config>(
+        queries: impl Iterator<Item = StorageQuery<Vec<u8>>>,
+        at: T::Hash,
+        follow_handle: &FollowStreamDriverHandle<T::Hash>,
+        methods: UnstableRpcMethods<T>,
+    ) -> Result<Self, Error> {
+        let sub_id = follow_handle
+            .subscribe()
+            .subscription_id()
+            .await
+            .ok_or(RpcError::SubscriptionDropped)?;
+
+        let mut state = StorageItemsState {
+            methods,
+            follow_handle: follow_handle.clone(),
+            at,
+            sub_id,
+            pending_queries: queries.collect(),
+            operation: None,
+            buffered_items: VecDeque::new(),
+        };
+
+        // Start the first operation now, so that any immediate problems are reported
+        // back by this call rather than via the stream.
+        if !state.pending_queries.is_empty() {
+            state.start_operation().await?;
+        }
+
+        let inner = stream::unfold(Some(state), |state| async move {
+            let mut state = state?;
+            match state.next_item().await? {
+                Ok(item) => Some((Ok(item), Some(state))),
+                // Nothing more will be handed back after an error.
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+
+        Ok(StorageItems {
+            inner: Box::pin(inner),
+        })
+    }
+}
+
+impl Stream for StorageItems {
+    type Item = Result<StorageResult, Error>;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+type OperationEvents<Hash> = Pin<Box<dyn Stream<Item = FollowEvent<BlockRef<Hash>>> + Send>>;
+
+struct StorageItemsState<T: Config> {
+    methods: UnstableRpcMethods<T>,
+    follow_handle: FollowStreamDriverHandle<T::Hash>,
+    at: T::Hash,
+    sub_id: String,
+    // Queries that we have yet to make (or that were discarded and need making again).
+    pending_queries: Vec<StorageQuery<Vec<u8>>>,
+    // The ID and events for the operation currently in progress, if any.
+    operation: Option<(String, OperationEvents<T::Hash>)>,
+    // Items we've been given but not yet handed back.
+    buffered_items: VecDeque<StorageResult>,
+}
+
+impl<T: Config> StorageItemsState<T> {
+    /// Make a `chainHead_storage` call for any pending queries.
+    async fn start_operation(&mut self) -> Result<(), Error> {
+        // Subscribe to events before starting the operation so that we don't miss any.
+        let events = self.follow_handle.subscribe().events();
+
+        let mut queries = std::mem::take(&mut self.pending_queries);
+        let status = self
+            .methods
+            .chainhead_unstable_storage(
+                &self.sub_id,
+                self.at,
+                queries.iter().map(|q| StorageQuery {
+                    key: &*q.key,
+                    query_type: q.query_type.clone(),
+                }),
+                None,
+            )
+            .await?;
+
+        let started = match status {
+            MethodResponse::LimitReached => return Err(RpcError::LimitReached.into()),
+            MethodResponse::Started(s) => s,
+        };
+
+        // Any queries discarded from the back of our list will be made again
+        // once the current operation is done.
+        let discarded = started.discarded_items.unwrap_or(0);
+        self.pending_queries = queries.split_off(queries.len().saturating_sub(discarded));
+        self.operation = Some((started.operation_id, Box::pin(events)));
+        Ok(())
+    }
+
+    /// Return the next storage item, or `None` if there are no more.
+    async fn next_item(&mut self) -> Option<Result<StorageResult, Error>> {
+        loop {
+            if let Some(item) = self.buffered_items.pop_front() {
+                return Some(Ok(item));
+            }
+
+            // No operation in progress; start another if we have queries left to make.
+            let Some((operation_id, events)) = &mut self.operation else {
+                if self.pending_queries.is_empty() {
+                    return None;
+                }
+                if let Err(e) = self.start_operation().await {
+                    return Some(Err(e));
+                }
+                continue;
+            };
+
+            let Some(ev) = events.next().await else {
+                return Some(Err(RpcError::SubscriptionDropped.into()));
+            };
+
+            match ev {
+                FollowEvent::OperationStorageItems(items)
+                    if items.operation_id == *operation_id =>
+                {
+                    self.buffered_items.extend(items.items);
+                }
+                FollowEvent::OperationWaitingForContinue(id)
+                    if id.operation_id == *operation_id =>
+                {
+                    let operation_id = operation_id.clone();
+                    if let Err(e) = self
+                        .methods
+                        .chainhead_unstable_continue(&self.sub_id, &operation_id)
+                        .await
+                    {
+                        return Some(Err(e));
+                    }
+                }
+                FollowEvent::OperationStorageDone(id) if id.operation_id == *operation_id => {
+                    self.operation = None;
+                }
+                FollowEvent::OperationInaccessible(id) if id.operation_id == *operation_id => {
+                    return Some(Err(Error::Other(
+                        "chainHead_storage operation is inaccessible; try again later".into(),
+                    )));
+                }
+                FollowEvent::OperationError(err) if err.operation_id == *operation_id => {
+                    return Some(Err(Error::Other(err.error)));
+                }
+                FollowEvent::Stop => {
+                    return Some(Err(RpcError::SubscriptionDropped.into()));
+                }
+                _ => {
+                    // Ignore any events that don't relate to our operation.
+                }
+            }
+        }
+    }
+}
@@ -5,10 +5,17 @@
 use crate::{
     blocks::block_types::{get_events, CachedEvents},
     client::{OfflineClientT, OnlineClientT},
-    config::{Config, Hasher},
-    error::{BlockError, Error, MetadataError},
+    config::{
+        signed_extensions::{
+            ChargeAssetTxPayment, ChargeTransactionPayment, CheckMortality, CheckNonce,
+        },
+        Config, Hasher, SignedExtension,
+    },
+    dynamic::DecodedValue,
+    error::{BlockError, DispatchError, Error, MetadataError},
     events,
     metadata::types::PalletMetadata,
+    utils::Era,
     Metadata,
 };
 
@@ -161,14 +168,18 @@ pub struct ExtrinsicDetails<T: Config, C> {
     address_start_idx: usize,
     /// The end index of the address in the encoded `bytes`.
     address_end_idx: usize,
+    /// The end index of the signature in the encoded `bytes`. The signed
+    /// extensions follow this, up until the call.
+    signature_end_idx: usize,
     /// The start index in the `bytes` from which the call is encoded.
     call_start_idx: usize,
     /// The pallet index.
     pallet_index: u8,
     /// The variant index.
     variant_index: u8,
-    /// The block hash of this extrinsic (needed to fetch events).
-    block_hash: T::Hash,
+    /// The block hash of this extrinsic (needed to fetch events), if
+    /// it was decoded from a block.
+    block_hash: Option<T::Hash>,
     /// Subxt client.
     client: C,
     /// Cached events.
@@ -183,6 +194,23 @@ where
     T: Config,
     C: OfflineClientT<T>,
 {
+    /// Decode a single extrinsic from the given bytes, without needing the block that it's
+    /// in. The bytes are expected to be SCALE encoded, which means that they begin with the
+    /// compact encoded length of the extrinsic; this is how extrinsics are found in block bodies
+    /// and how pending extrinsics are returned from a node.
+    ///
+    /// This only needs an offline client, so it can be used to inspect extrinsics which have
+    /// not been included in a block yet, or which have come from elsewhere.
+    ///
+    /// # Note
+    ///
+    /// Since the extrinsic doesn't belong to a block, [`Self::index()`] will always be 0, and
+    /// [`Self::events()`] will return an error.
+    pub fn decode(extrinsic_bytes: &[u8], client: C) -> Result<ExtrinsicDetails<T, C>, Error> {
+        let ids = ExtrinsicPartTypeIds::new(&client.metadata())?;
+        Self::decode_with_block_hash(0, extrinsic_bytes, client, None, Default::default(), ids)
+    }
+
     // Attempt to dynamically decode a single extrinsic from the given input.
     pub(crate) fn decode_from(
         index: u32,
@@ -191,6 +219,24 @@ where
         block_hash: T::Hash,
         cached_events: CachedEvents<T>,
         ids: ExtrinsicPartTypeIds,
+    ) -> Result<ExtrinsicDetails<T, C>, Error> {
+        Self::decode_with_block_hash(
+            index,
+            extrinsic_bytes,
+            client,
+            Some(block_hash),
+            cached_events,
+            ids,
+        )
+    }
+
+    fn decode_with_block_hash(
+        index: u32,
+        extrinsic_bytes: &[u8],
+        client: C,
+        block_hash: Option<T::Hash>,
+        cached_events: CachedEvents<T>,
+        ids: ExtrinsicPartTypeIds,
     ) -> Result<ExtrinsicDetails<T, C>, Error> {
         const SIGNATURE_MASK: u8 = 0b1000_0000;
         const VERSION_MASK: u8 = 0b0111_1111;
@@ -219,6 +265,7 @@ where
 
         let mut address_start_idx = 0;
         let mut address_end_idx = 0;
+        let mut signature_end_idx = 0;
 
         if is_signed {
             address_start_idx = bytes.len() - cursor.len();
@@ -240,6 +287,7 @@ where
                 scale_decode::visitor::IgnoreVisitor,
             )
             .map_err(scale_decode::Error::from)?;
+            signature_end_idx = bytes.len() - cursor.len();
 
             scale_decode::visitor::decode_with_visitor(
                 cursor,
@@ -264,6 +312,7 @@ where
             is_signed,
             address_start_idx,
             address_end_idx,
+            signature_end_idx,
             call_start_idx,
             pallet_index,
             variant_index,
@@ -330,6 +379,37 @@ where
             .then(|| &self.bytes[self.address_start_idx..self.address_end_idx])
     }
 
+    /// Return only the bytes of the signature for this extrinsic.
+    ///
+    /// # Note
+    ///
+    /// Returns `None` if the extrinsic is not signed.
+    pub fn signature_bytes(&self) -> Option<&[u8]> {
+        self.is_signed
+            .then(|| &self.bytes[self.address_end_idx..self.signature_end_idx])
+    }
+
+    /// Return only the bytes of the signed extensions (the "extra" data) for this extrinsic.
+    ///
+    /// # Note
+    ///
+    /// Returns `None` if the extrinsic is not signed.
+    pub fn signed_extensions_bytes(&self) -> Option<&[u8]> {
+        self.is_signed
+            .then(|| &self.bytes[self.signature_end_idx..self.call_start_idx])
+    }
+
+    /// Return the signed extensions of this extrinsic, which can be used to look up things
+    /// like the nonce and tip that it was submitted with.
+    ///
+    /// # Note
+    ///
+    /// Returns `None` if the extrinsic is not signed.
+    pub fn signed_extensions(&self) -> Option<ExtrinsicSignedExtensions<'_, T>> {
+        self.signed_extensions_bytes()
+            .map(|bytes| ExtrinsicSignedExtensions::new(bytes, &self.metadata))
+    }
+
     /// The index of the pallet that the extrinsic originated from.
     pub fn pallet_index(&self) -> u8 {
         self.pallet_index
@@ -422,13 +502,177 @@ where
     C: OnlineClientT<T>,
 {
     /// The events associated with the extrinsic.
+    ///
+    /// # Note
+    ///
+    /// This returns an error if the extrinsic was not decoded from a block; see
+    /// [`ExtrinsicDetails::decode()`].
     pub async fn events(&self) -> Result<ExtrinsicEvents<T>, Error> {
-        let events = get_events(&self.client, self.block_hash, &self.cached_events).await?;
+        let block_hash = self.block_hash.ok_or(BlockError::NoBlockHash)?;
+        let events = get_events(&self.client, block_hash, &self.cached_events).await?;
         let ext_hash = T::Hasher::hash_of(&self.bytes);
         Ok(ExtrinsicEvents::new(ext_hash, self.index, events))
     }
 }
 
+/// The signed extensions of an extrinsic.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct ExtrinsicSignedExtensions<'a, T: Config> {
+    bytes: &'a [u8],
+    metadata: &'a Metadata,
+    _marker: std::marker::PhantomData<T>,
+}
+
+// Dev note: derived `Clone` impls would clone the `Metadata` rather than the reference to it.
+impl<'a, T: Config> Clone for ExtrinsicSignedExtensions<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: Config> Copy for ExtrinsicSignedExtensions<'a, T> {}
+
+impl<'a, T: Config> ExtrinsicSignedExtensions<'a, T> {
+    fn new(bytes: &'a [u8], metadata: &'a Metadata) -> Self {
+        Self {
+            bytes,
+            metadata,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Returns an iterator over each of the signed extensions, in the order that they appear
+    /// in the extrinsic. If an error occurs, all subsequent iterations return `None`.
+    pub fn iter(&self) -> impl Iterator<Item = Result<ExtrinsicSignedExtension<'a, T>, Error>> {
+        let bytes = self.bytes;
+        let metadata = self.metadata;
+        let mut signed_extensions = metadata.extrinsic().signed_extensions().iter();
+        let mut cursor = bytes;
+        let mut done = false;
+
+        std::iter::from_fn(move || {
+            if done {
+                return None;
+            }
+            let ext = signed_extensions.next()?;
+            let start = bytes.len() - cursor.len();
+            if let Err(e) = scale_decode::visitor::decode_with_visitor(
+                &mut cursor,
+                ext.extra_ty(),
+                metadata.types(),
+                scale_decode::visitor::IgnoreVisitor,
+            ) {
+                done = true;
+                return Some(Err(scale_decode::Error::from(e).into()));
+            }
+            let end = bytes.len() - cursor.len();
+
+            Some(Ok(ExtrinsicSignedExtension {
+                bytes: &bytes[start..end],
+                identifier: ext.identifier(),
+                ty_id: ext.extra_ty(),
+                metadata,
+                _marker: std::marker::PhantomData,
+            }))
+        })
+    }
+
+    /// Find the signed extension `S` and decode it, returning `None` if it
+    /// isn't one of the signed extensions of this extrinsic.
+    pub fn find<S: SignedExtension<T>>(&self) -> Result<Option<S::Decoded>, Error> {
+        for ext in self.iter() {
+            if let Some(decoded) = ext?.as_signed_extension::<S>()? {
+                return Ok(Some(decoded));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The tip paid to the author of the block that this extrinsic was included in. This
+    /// comes from the `ChargeTransactionPayment` or `ChargeAssetTxPayment` signed extension,
+    /// and is `None` if neither of these can be found and decoded.
+    pub fn tip(&self) -> Option<u128> {
+        let tip = self
+            .find::<ChargeTransactionPayment>()
+            .ok()
+            .flatten()
+            .map(|e| e.tip());
+        tip.or_else(|| {
+            self.find::<ChargeAssetTxPayment>()
+                .ok()
+                .flatten()
+                .map(|e| e.tip())
+        })
+    }
+
+    /// The nonce of the account that submitted this extrinsic. This comes from the `CheckNonce`
+    /// signed extension, and is `None` if it can't be found and decoded.
+    pub fn nonce(&self) -> Option<u64> {
+        self.find::<CheckNonce>().ok().flatten()
+    }
+
+    /// The era (ie the mortality) of this extrinsic. This comes from the `CheckMortality`
+    /// signed extension, and is `None` if it can't be found and decoded.
+    pub fn era(&self) -> Option<Era> {
+        self.find::<CheckMortality<T>>().ok().flatten()
+    }
+}
+
+/// A single signed extension of an extrinsic.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct ExtrinsicSignedExtension<'a, T: Config> {
+    bytes: &'a [u8],
+    identifier: &'a str,
+    ty_id: u32,
+    metadata: &'a Metadata,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<'a, T: Config> Clone for ExtrinsicSignedExtension<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: Config> Copy for ExtrinsicSignedExtension<'a, T> {}
+
+impl<'a, T: Config> ExtrinsicSignedExtension<'a, T> {
+    /// The bytes of the extra data of this signed extension, as found in the extrinsic.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The name of the signed extension.
+    pub fn name(&self) -> &'a str {
+        self.identifier
+    }
+
+    /// The type ID of the extra data of this signed extension.
+    pub fn type_id(&self) -> u32 {
+        self.ty_id
+    }
+
+    /// Decode the extra data of this signed extension into a dynamic [`DecodedValue`].
+    pub fn value(&self) -> Result<DecodedValue, Error> {
+        let value =
+            DecodedValue::decode_as_type(&mut &self.bytes[..], self.ty_id, self.metadata.types())?;
+        Ok(value)
+    }
+
+    /// Decode the extra data of this signed extension into the type given by
+    /// [`SignedExtension::Decoded`], returning `None` if this isn't the signed extension `S`.
+    pub fn as_signed_extension<S: SignedExtension<T>>(&self) -> Result<Option<S::Decoded>, Error> {
+        if self.identifier != S::NAME {
+            return Ok(None);
+        }
+        let decoded =
+            S::Decoded::decode_as_type(&mut &self.bytes[..], self.ty_id, self.metadata.types())?;
+        Ok(Some(decoded))
+    }
+}
+
 /// Details for the given extrinsic plucked from the metadata.
 pub struct ExtrinsicMetadataDetails<'a> {
     pub pallet: PalletMetadata<'a>,
@@ -556,14 +800,135 @@ impl<T: Config> ExtrinsicEvents<T> {
     pub fn has<Ev: events::StaticEvent>(&self) -> Result<bool, Error> {
         Ok(self.find::<Ev>().next().transpose()?.is_some())
     }
+
+    /// If this transaction dispatched a `Utility` batch (for instance one built using
+    /// [`crate::tx::BatchPayload`]), this splits the transaction events up by the call in the batch
+    /// that they were emitted from, using the `ItemCompleted`, `ItemFailed` and
+    /// `BatchInterrupted` events that the `Utility` pallet emits.
+    ///
+    /// The item at index `i` is the result of the `i`th call in the batch. Each successful
+    /// call hands back the events it emitted, and each failed call hands back the error it
+    /// failed with. Calls which were never dispatched (because an earlier call interrupted a
+    /// `Utility::batch`) are not included, and nor are any if a `Utility::batch_all` failed,
+    /// since in that case the whole transaction fails.
+    ///
+    /// **Note:** Events emitted before the batch was dispatched (for instance those relating
+    /// to fee payment) are included in the events for the first call, and events emitted after
+    /// the batch completed are not included at all. Batches nested inside other batches are
+    /// not distinguished from the outer batch, so this is only useful for a single batch.
+    pub fn batch_item_results(&self) -> Result<Vec<BatchItemResult<T>>, Error> {
+        let metadata = self.events.metadata();
+        let mut results = Vec::new();
+        let mut item_events = Vec::new();
+
+        for ev in self.iter() {
+            let ev = ev?;
+            if ev.pallet_name() == "Utility" {
+                match ev.variant_name() {
+                    "ItemCompleted" => {
+                        results.push(Ok(std::mem::take(&mut item_events)));
+                        continue;
+                    }
+                    "ItemFailed" => {
+                        // Events from the failed call are reverted, so there's nothing
+                        // more to hand back here.
+                        item_events.clear();
+                        results.push(Err(decode_error_field(&ev, metadata)?));
+                        continue;
+                    }
+                    "BatchInterrupted" => {
+                        results.push(Err(decode_error_field(&ev, metadata)?));
+                        break;
+                    }
+                    "BatchCompleted" | "BatchCompletedWithErrors" => break,
+                    _ => {}
+                }
+            }
+            item_events.push(ev);
+        }
+
+        Ok(results)
+    }
+
+    /// If this transaction dispatched a call via the `Proxy` pallet (for instance one built
+    /// using [`crate::tx::ProxyPayload`]), this returns the result of that call, as given by
+    /// the `Proxy::ProxyExecuted` event. Returns `None` if there is no such event.
+    ///
+    /// **Note:** If proxy calls are nested, each emits a `ProxyExecuted` event, and the result
+    /// of the outermost call (which is emitted last) is returned.
+    pub fn proxy_result(&self) -> Result<Option<Result<(), DispatchError>>, Error> {
+        let metadata = self.events.metadata();
+        let mut proxy_result = None;
+        for ev in self.iter() {
+            let ev = ev?;
+            if ev.pallet_name() != "Proxy" || ev.variant_name() != "ProxyExecuted" {
+                continue;
+            }
+            // The result is a `DispatchResult`; a `Result<(), DispatchError>`.
+            let bytes = named_field_bytes(&ev, metadata, "result")?;
+            let result = match bytes.first() {
+                Some(0) => Ok(()),
+                Some(1) => Err(DispatchError::decode_from(&bytes[1..], metadata.clone())?),
+                _ => {
+                    return Err(Error::Other(
+                        "Could not decode the result in a Proxy::ProxyExecuted event".into(),
+                    ))
+                }
+            };
+            proxy_result = Some(result);
+        }
+        Ok(proxy_result)
+    }
+}
+
+/// The result of dispatching a single call in a `Utility` batch; see
+/// [`ExtrinsicEvents::batch_item_results()`].
+pub type BatchItemResult<T> = Result<Vec<events::EventDetails<T>>, DispatchError>;
+
+/// Decode the field named "error" in some event into a [`DispatchError`].
+fn decode_error_field<T: Config>(
+    ev: &events::EventDetails<T>,
+    metadata: &Metadata,
+) -> Result<DispatchError, Error> {
+    let bytes = named_field_bytes(ev, metadata, "error")?;
+    DispatchError::decode_from(bytes, metadata.clone())
+}
+
+/// Return the bytes of the field with the given name (and any fields following it) in some event.
+fn named_field_bytes<'a, T: Config>(
+    ev: &'a events::EventDetails<T>,
+    metadata: &Metadata,
+    name: &str,
+) -> Result<&'a [u8], Error> {
+    let mut input = ev.field_bytes();
+    for field in &ev.event_metadata().variant.fields {
+        if field.name.as_deref() == Some(name) {
+            return Ok(input);
+        }
+        scale_decode::visitor::decode_with_visitor(
+            &mut input,
+            field.ty.id,
+            metadata.types(),
+            scale_decode::visitor::IgnoreVisitor,
+        )
+        .map_err(scale_decode::Error::from)?;
+    }
+    Err(Error::Other(format!(
+        "No '{name}' field found in the {}::{} event",
+        ev.pallet_name(),
+        ev.variant_name()
+    )))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::backend::test_utils::FixedSigner;
+    use crate::tx::Signer;
+    use crate::utils::MultiSignature;
     use crate::{backend::RuntimeVersion, OfflineClient, PolkadotConfig};
     use assert_matches::assert_matches;
-    use codec::{Decode, Encode};
+    use codec::{Compact, Decode, Encode};
     use frame_metadata::v15::{CustomMetadata, OuterEnums};
     use frame_metadata::{
         v15::{ExtrinsicMetadata, PalletCallMetadata, PalletMetadata, RuntimeMetadataV15},
@@ -832,4 +1197,224 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn batch_item_results_are_split_up_by_utility_events() {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_full.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*bytes).unwrap();
+
+        // Encode an event record for some event in the given extrinsic.
+        let event = |ext_idx: u32, pallet: &str, variant: &str, fields: &[u8]| {
+            let pallet = metadata.pallet_by_name(pallet).unwrap();
+            let variant = pallet
+                .event_variants()
+                .unwrap()
+                .iter()
+                .find(|v| v.name == variant)
+                .unwrap();
+            let mut bytes = events::Phase::ApplyExtrinsic(ext_idx).encode();
+            bytes.extend([pallet.index(), variant.index]);
+            bytes.extend(fields);
+            Vec::<H256>::new().encode_to(&mut bytes);
+            bytes
+        };
+        let remarked = [[1u8; 32], [2u8; 32]].concat();
+        let bad_origin = [2u8];
+        let batch_interrupted = [2u32.encode(), bad_origin.to_vec()].concat();
+
+        let extrinsic_events = |records: Vec<Vec<u8>>| {
+            let mut bytes = Compact(records.len() as u32).encode();
+            bytes.extend(records.concat());
+            let events =
+                events::Events::<PolkadotConfig>::new(metadata.clone(), H256::zero(), bytes);
+            ExtrinsicEvents::new(H256::zero(), 1, events)
+        };
+
+        // A batch which is interrupted by the third call:
+        let results = extrinsic_events(vec![
+            event(0, "System", "Remarked", &remarked),
+            event(1, "System", "Remarked", &remarked),
+            event(1, "Utility", "ItemCompleted", &[]),
+            event(1, "Utility", "ItemCompleted", &[]),
+            event(1, "Utility", "BatchInterrupted", &batch_interrupted),
+        ])
+        .batch_item_results()
+        .unwrap();
+
+        assert_eq!(results.len(), 3);
+        let first_events = results[0].as_ref().unwrap();
+        assert_eq!(first_events.len(), 1);
+        assert_eq!(first_events[0].variant_name(), "Remarked");
+        assert!(results[1].as_ref().unwrap().is_empty());
+        assert!(matches!(results[2], Err(DispatchError::BadOrigin)));
+
+        // A forced batch in which the first call fails:
+        let results = extrinsic_events(vec![
+            event(1, "Utility", "ItemFailed", &bad_origin),
+            event(1, "System", "Remarked", &remarked),
+            event(1, "Utility", "ItemCompleted", &[]),
+            event(1, "Utility", "BatchCompletedWithErrors", &[]),
+            event(1, "System", "Remarked", &remarked),
+        ])
+        .batch_item_results()
+        .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0], Err(DispatchError::BadOrigin)));
+        assert_eq!(results[1].as_ref().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn proxy_result_is_decoded_from_proxy_executed_events() {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_full.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*bytes).unwrap();
+        let pallet = metadata.pallet_by_name("Proxy").unwrap();
+        let proxy_executed = pallet
+            .event_variants()
+            .unwrap()
+            .iter()
+            .find(|v| v.name == "ProxyExecuted")
+            .unwrap();
+
+        let extrinsic_events = |results: &[&[u8]]| {
+            let mut bytes = Compact(results.len() as u32).encode();
+            for result in results {
+                events::Phase::ApplyExtrinsic(1).encode_to(&mut bytes);
+                bytes.extend([pallet.index(), proxy_executed.index]);
+                bytes.extend(*result);
+                Vec::<H256>::new().encode_to(&mut bytes);
+            }
+            let events =
+                events::Events::<PolkadotConfig>::new(metadata.clone(), H256::zero(), bytes);
+            ExtrinsicEvents::new(H256::zero(), 1, events)
+        };
+
+        // No events:
+        assert!(extrinsic_events(&[]).proxy_result().unwrap().is_none());
+        // A successful call:
+        assert_matches!(extrinsic_events(&[&[0]]).proxy_result(), Ok(Some(Ok(()))));
+        // A call which failed with `BadOrigin`:
+        assert_matches!(
+            extrinsic_events(&[&[1, 2]]).proxy_result(),
+            Ok(Some(Err(DispatchError::BadOrigin)))
+        );
+        // The last (outermost) result is returned:
+        assert_matches!(
+            extrinsic_events(&[&[1, 2], &[0]]).proxy_result(),
+            Ok(Some(Ok(())))
+        );
+    }
+
+    #[test]
+    fn signed_extensions_are_decoded() {
+        use crate::config::{polkadot::PolkadotExtrinsicParamsBuilder, signed_extensions};
+
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_full.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*bytes).unwrap();
+        let client = client(metadata.clone());
+        let ids = ExtrinsicPartTypeIds::new(&metadata).unwrap();
+
+        let call = crate::dynamic::tx("System", "remark", vec![vec![1u8, 2, 3]]);
+        let params = PolkadotExtrinsicParamsBuilder::new()
+            .tip(1234)
+            .mortal_unchecked(100, H256::zero(), 64)
+            .build();
+        let tx = client
+            .tx()
+            .create_signed_with_nonce(&call, &FixedSigner, 7, params)
+            .unwrap();
+
+        let extrinsic = ExtrinsicDetails::decode_from(
+            1,
+            tx.encoded(),
+            client,
+            H256::random(),
+            Default::default(),
+            ids,
+        )
+        .unwrap();
+
+        assert_eq!(
+            extrinsic.signature_bytes().unwrap(),
+            &MultiSignature::Sr25519([2; 64]).encode()[..]
+        );
+
+        let signed_extensions = extrinsic.signed_extensions().unwrap();
+        assert_eq!(signed_extensions.nonce(), Some(7));
+        assert_eq!(signed_extensions.tip(), Some(1234));
+        assert_eq!(signed_extensions.era(), Some(Era::mortal(64, 100)));
+
+        let names: Vec<_> = signed_extensions
+            .iter()
+            .map(|e| e.unwrap().name())
+            .collect();
+        assert_eq!(
+            names,
+            metadata
+                .extrinsic()
+                .signed_extensions()
+                .iter()
+                .map(|e| e.identifier())
+                .collect::<Vec<_>>()
+        );
+
+        // The bytes of each extension add up to all of the signed extension bytes:
+        let ext_bytes: Vec<u8> = signed_extensions
+            .iter()
+            .flat_map(|e| e.unwrap().bytes().to_vec())
+            .collect();
+        assert_eq!(extrinsic.signed_extensions_bytes().unwrap(), &ext_bytes[..]);
+
+        // Extensions can be decoded individually too:
+        let nonce = signed_extensions
+            .iter()
+            .find_map(|e| {
+                e.unwrap()
+                    .as_signed_extension::<signed_extensions::CheckNonce>()
+                    .unwrap()
+            })
+            .unwrap();
+        assert_eq!(nonce, 7);
+        let spec_version = signed_extensions
+            .find::<signed_extensions::CheckSpecVersion>()
+            .unwrap();
+        assert_eq!(spec_version, Some(()));
+    }
+
+    #[test]
+    fn extrinsics_can_be_decoded_without_a_block() {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_full.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*bytes).unwrap();
+        let client = client(metadata);
+
+        let call = crate::dynamic::tx("System", "remark", vec![vec![1u8, 2, 3]]);
+        let params = Default::default();
+        let tx = client
+            .tx()
+            .create_signed_with_nonce(&call, &FixedSigner, 3, params)
+            .unwrap();
+
+        let extrinsic = ExtrinsicDetails::decode(tx.encoded(), client.clone()).unwrap();
+        assert!(extrinsic.is_signed());
+        assert_eq!(extrinsic.index(), 0);
+        assert_eq!(extrinsic.pallet_name().unwrap(), "System");
+        assert_eq!(extrinsic.variant_name().unwrap(), "remark");
+        assert_eq!(
+            extrinsic.address_bytes().unwrap(),
+            &FixedSigner.address().encode()[..]
+        );
+        assert_eq!(extrinsic.signed_extensions().unwrap().nonce(), Some(3));
+        let fields = extrinsic.field_values().unwrap();
+        assert_eq!(
+            fields.into_values().next().unwrap().remove_context(),
+            Value::from_bytes([1u8, 2, 3])
+        );
+
+        // Unsigned extrinsics can be decoded too:
+        let tx = client.tx().create_unsigned(&call).unwrap();
+        let extrinsic = ExtrinsicDetails::decode(tx.encoded(), client).unwrap();
+        assert!(!extrinsic.is_signed());
+        assert!(extrinsic.signed_extensions().is_none());
+        assert_eq!(extrinsic.variant_name().unwrap(), "remark");
+    }
 }
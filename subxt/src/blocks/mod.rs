@@ -13,4 +13,7 @@ pub use crate::backend::BlockRef;
 
 pub use block_types::Block;
 pub use blocks_client::BlocksClient;
-pub use extrinsic_types::{ExtrinsicDetails, ExtrinsicEvents, Extrinsics, StaticExtrinsic};
+pub use extrinsic_types::{
+    BatchItemResult, ExtrinsicDetails, ExtrinsicEvents, ExtrinsicSignedExtension,
+    ExtrinsicSignedExtensions, Extrinsics, StaticExtrinsic,
+};
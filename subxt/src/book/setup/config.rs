@@ -87,6 +87,12 @@
 //!
 //! See below for examples of each.
 //!
+//! Alternately, [`crate::config::DynamicConfig`] wraps an existing config such as `PolkadotConfig` and uses
+//! [`crate::config::DynamicExtrinsicParams`], which handles the standard signed extensions in the same way, ignores unknown
+//! signed extensions that encode to nothing, and lets you provide [`scale_value::Value`]s for any others via
+//! [`crate::config::DynamicExtrinsicParamsBuilder::signed_extension()`]. These values are checked against the types given
+//! in the metadata when the transaction is constructed.
+//!
 //! ### Finding out which signed extensions a chain is using.
 //!
 //! In either case, you'll want to find out which signed extensions a chain is using. This information can be obtained from
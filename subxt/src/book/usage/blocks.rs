@@ -37,3 +37,10 @@
 #![doc = include_str!("../../../examples/blocks_subscribing.rs")]
 //! ```
 //!
+//! Extrinsics which aren't in a block (for instance, those waiting in a node's transaction pool)
+//! can be decoded into the same [`crate::blocks::ExtrinsicDetails`] type using
+//! [`crate::blocks::ExtrinsicDetails::decode()`], which only needs an
+//! [`crate::OfflineClient`]. The [signed extensions](crate::blocks::ExtrinsicDetails::signed_extensions())
+//! of a signed extrinsic can be inspected to find out things like the nonce and tip that it was
+//! submitted with.
+//!
@@ -119,8 +119,15 @@
 //! See the `subxt_signer` crate or the `sp_core::Pair` docs for more ways to construct
 //! and work with key pairs.
 //!
-//! If this isn't suitable/available, you can either implement [`crate::tx::Signer`] yourself to use
-//! custom signing logic, or you can use some external signing logic, like so:
+//! If this isn't suitable/available, you can implement [`crate::tx::Signer`] yourself to use
+//! custom signing logic.
+//!
+//! If signing needs to wait on I/O or can fail (for instance when keys are held in a remote key
+//! management service or on a hardware wallet), implement [`crate::tx::AsyncSigner`] instead, and
+//! use methods like [`crate::tx::TxClient::sign_and_submit_then_watch_default_async`] or
+//! [`crate::tx::PartialExtrinsic::sign_async`] to sign and submit transactions with it.
+//!
+//! Alternately, you can use some external signing logic, like so:
 //!
 //! ```rust,no_run
 //! # #[tokio::main]
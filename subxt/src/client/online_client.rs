@@ -65,6 +65,10 @@ impl<T: Config> OnlineClient<T> {
     }
 
     /// Construct a new [`OnlineClient`], providing a URL to connect to.
+    ///
+    /// See [`RpcClient::from_url`] for details on the URLs that are supported. Notably,
+    /// clients connected over HTTP can't subscribe to anything, so things like
+    /// [`crate::tx::TxProgress`] won't report the progress of submitted transactions.
     pub async fn from_url(url: impl AsRef<str>) -> Result<OnlineClient<T>, Error> {
         let client = RpcClient::from_url(url).await?;
         let backend = LegacyBackend::new(client);
@@ -4,6 +4,7 @@
 
 use super::{signed_extensions, ExtrinsicParams};
 use super::{Config, Header};
+use scale_encode::EncodeAsType;
 
 /// The default [`super::ExtrinsicParams`] implementation understands common signed extensions
 /// and how to apply them to a given chain.
@@ -17,6 +18,7 @@ pub type DefaultExtrinsicParams<T> = signed_extensions::AnyOf<
         signed_extensions::CheckMortality<T>,
         signed_extensions::ChargeAssetTxPayment,
         signed_extensions::ChargeTransactionPayment,
+        signed_extensions::CheckMetadataHash,
     ),
 >;
 
@@ -26,10 +28,11 @@ pub type DefaultExtrinsicParams<T> = signed_extensions::AnyOf<
 pub struct DefaultExtrinsicParamsBuilder<T: Config> {
     /// `None` means the tx will be immortal.
     mortality: Option<Mortality<T::Hash>>,
-    /// `None` means we'll use the native token.
-    tip_of_asset_id: Option<u32>,
+    /// `None` means we'll tip in the native token.
+    tip_of_asset: Option<signed_extensions::ChargeAssetTxPaymentParams>,
     tip: u128,
-    tip_of: u128,
+    /// `None` means the metadata hash won't be checked.
+    metadata_hash: Option<[u8; 32]>,
 }
 
 struct Mortality<Hash> {
@@ -47,8 +50,8 @@ impl<T: Config> Default for DefaultExtrinsicParamsBuilder<T> {
         Self {
             mortality: None,
             tip: 0,
-            tip_of: 0,
-            tip_of_asset_id: None,
+            tip_of_asset: None,
+            metadata_hash: None,
         }
     }
 }
@@ -95,18 +98,31 @@ impl<T: Config> DefaultExtrinsicParamsBuilder<T> {
     /// Provide a tip to the block author in the chain's native token.
     pub fn tip(mut self, tip: u128) -> Self {
         self.tip = tip;
-        self.tip_of = tip;
-        self.tip_of_asset_id = None;
+        self.tip_of_asset = None;
         self
     }
 
     /// Provide a tip to the block auther using the token denominated by the `asset_id` provided. This
     /// is not applicable on chains which don't use the `ChargeAssetTxPayment` signed extension; in this
-    /// case, no tip will be given.
-    pub fn tip_of(mut self, tip: u128, asset_id: u32) -> Self {
+    /// case, no tip will be given. The `asset_id` is encoded according to the asset ID type in the
+    /// metadata; see [`signed_extensions::ChargeAssetTxPaymentParams::tip_of()`].
+    pub fn tip_of(
+        mut self,
+        tip: u128,
+        asset_id: impl EncodeAsType + Send + Sync + 'static,
+    ) -> Self {
         self.tip = 0;
-        self.tip_of = tip;
-        self.tip_of_asset_id = Some(asset_id);
+        self.tip_of_asset = Some(signed_extensions::ChargeAssetTxPaymentParams::tip_of(
+            tip, asset_id,
+        ));
+        self
+    }
+
+    /// Include the given metadata hash in the signed data, so that the chain checks it against
+    /// the hash of its own metadata. This is not applicable on chains which don't use the
+    /// `CheckMetadataHash` signed extension; in this case, it will be ignored.
+    pub fn metadata_hash(mut self, hash: [u8; 32]) -> Self {
+        self.metadata_hash = Some(hash);
         self
     }
 
@@ -122,15 +138,19 @@ impl<T: Config> DefaultExtrinsicParamsBuilder<T> {
             signed_extensions::CheckMortalityParams::immortal()
         };
 
-        let charge_asset_tx_params = if let Some(asset_id) = self.tip_of_asset_id {
-            signed_extensions::ChargeAssetTxPaymentParams::tip_of(self.tip, asset_id)
-        } else {
-            signed_extensions::ChargeAssetTxPaymentParams::tip(self.tip)
-        };
+        let charge_asset_tx_params = self
+            .tip_of_asset
+            .unwrap_or_else(|| signed_extensions::ChargeAssetTxPaymentParams::tip(self.tip));
 
         let charge_transaction_params =
             signed_extensions::ChargeTransactionPaymentParams::tip(self.tip);
 
+        let check_metadata_hash_params = if let Some(hash) = self.metadata_hash {
+            signed_extensions::CheckMetadataHashParams::enabled(hash)
+        } else {
+            signed_extensions::CheckMetadataHashParams::disabled()
+        };
+
         (
             (),
             (),
@@ -139,6 +159,7 @@ impl<T: Config> DefaultExtrinsicParamsBuilder<T> {
             check_mortality_params,
             charge_asset_tx_params,
             charge_transaction_params,
+            check_metadata_hash_params,
         )
     }
 }
@@ -0,0 +1,429 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! An [`ExtrinsicParams`] implementation which is driven entirely by the signed extensions
+//! listed in the metadata, so that chains with non-standard signed extensions can be
+//! used without defining a custom [`Config`].
+
+use super::default_extrinsic_params::{DefaultExtrinsicParams, DefaultExtrinsicParamsBuilder};
+use super::extrinsic_params::{ExtrinsicParams, ExtrinsicParamsEncoder, ExtrinsicParamsError};
+use super::signed_extensions::{self, is_type_empty, SignedExtension};
+use super::Config;
+use crate::client::OfflineClientT;
+use scale_encode::EncodeAsType;
+use scale_value::Value;
+use std::collections::HashMap;
+
+/// A [`Config`] which uses the same types as `C`, but uses [`DynamicExtrinsicParams`] to
+/// construct transactions. This allows transactions to be submitted to chains with signed
+/// extensions that Subxt doesn't know about, for instance
+/// `OnlineClient<DynamicConfig<PolkadotConfig>>`.
+pub struct DynamicConfig<C>(std::marker::PhantomData<C>);
+
+impl<C: Config> Config for DynamicConfig<C> {
+    type Hash = C::Hash;
+    type AccountId = C::AccountId;
+    type Address = C::Address;
+    type Signature = C::Signature;
+    type Hasher = C::Hasher;
+    type Header = C::Header;
+    type ExtrinsicParams = DynamicExtrinsicParams<Self>;
+}
+
+/// An [`ExtrinsicParams`] implementation which looks at the signed extensions in the metadata
+/// to work out what to encode. For each signed extension:
+///
+/// - If values were given for it via [`DynamicExtrinsicParamsBuilder::signed_extension()`],
+///   they are encoded according to its `extra` and `additional` types.
+/// - Else if it's one of the signed extensions that [`DefaultExtrinsicParams`] knows about,
+///   it's encoded in the same way.
+/// - Else if its `extra` and `additional` types are both zero sized, nothing is encoded.
+///
+/// Otherwise, an [`ExtrinsicParamsError::UnknownSignedExtension`] error is returned.
+pub struct DynamicExtrinsicParams<T: Config> {
+    params: Vec<Box<dyn ExtrinsicParamsEncoder>>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+/// The [`ExtrinsicParams::OtherParams`] for [`DynamicExtrinsicParams`]. Construct this using
+/// [`DynamicExtrinsicParamsBuilder`].
+pub struct DynamicOtherParams<T: Config> {
+    known: <DefaultExtrinsicParams<T> as ExtrinsicParams<T>>::OtherParams,
+    values: HashMap<String, SignedExtensionValues>,
+}
+
+impl<T: Config> Default for DynamicOtherParams<T> {
+    fn default() -> Self {
+        DynamicExtrinsicParamsBuilder::new().build()
+    }
+}
+
+struct SignedExtensionValues {
+    extra: Value,
+    additional: Value,
+}
+
+impl<T: Config> ExtrinsicParams<T> for DynamicExtrinsicParams<T> {
+    type OtherParams = DynamicOtherParams<T>;
+    type Error = ExtrinsicParamsError;
+
+    fn new<Client: OfflineClientT<T>>(
+        nonce: u64,
+        client: Client,
+        other_params: Self::OtherParams,
+    ) -> Result<Self, Self::Error> {
+        let DynamicOtherParams { known, mut values } = other_params;
+
+        let mut encoders = HashMap::new();
+        add_encoder::<T, signed_extensions::CheckSpecVersion, _>(
+            &mut encoders,
+            nonce,
+            &client,
+            known.0,
+        )?;
+        add_encoder::<T, signed_extensions::CheckTxVersion, _>(
+            &mut encoders,
+            nonce,
+            &client,
+            known.1,
+        )?;
+        add_encoder::<T, signed_extensions::CheckNonce, _>(&mut encoders, nonce, &client, known.2)?;
+        add_encoder::<T, signed_extensions::CheckGenesis<T>, _>(
+            &mut encoders,
+            nonce,
+            &client,
+            known.3,
+        )?;
+        add_encoder::<T, signed_extensions::CheckMortality<T>, _>(
+            &mut encoders,
+            nonce,
+            &client,
+            known.4,
+        )?;
+        add_encoder::<T, signed_extensions::ChargeAssetTxPayment, _>(
+            &mut encoders,
+            nonce,
+            &client,
+            known.5,
+        )?;
+        add_encoder::<T, signed_extensions::ChargeTransactionPayment, _>(
+            &mut encoders,
+            nonce,
+            &client,
+            known.6,
+        )?;
+        add_encoder::<T, signed_extensions::CheckMetadataHash, _>(
+            &mut encoders,
+            nonce,
+            &client,
+            known.7,
+        )?;
+
+        let mut params: Vec<Box<dyn ExtrinsicParamsEncoder>> = Vec::new();
+        let metadata = client.metadata();
+        let types = metadata.types();
+        for ext in metadata.extrinsic().signed_extensions() {
+            let identifier = ext.identifier();
+            if let Some(values) = values.remove(identifier) {
+                let encode = |value: &Value, type_id: u32| {
+                    value.encode_as_type(type_id, types).map_err(|error| {
+                        ExtrinsicParamsError::InvalidSignedExtensionValue {
+                            identifier: identifier.to_owned(),
+                            error,
+                        }
+                    })
+                };
+                params.push(Box::new(EncodedSignedExtension {
+                    extra: encode(&values.extra, ext.extra_ty())?,
+                    additional: encode(&values.additional, ext.additional_ty())?,
+                }));
+            } else if let Some(encoder) = encoders.remove(identifier) {
+                params.push(encoder);
+            } else if !is_type_empty(ext.extra_ty(), types)
+                || !is_type_empty(ext.additional_ty(), types)
+            {
+                return Err(ExtrinsicParamsError::UnknownSignedExtension(
+                    identifier.to_owned(),
+                ));
+            }
+        }
+
+        Ok(DynamicExtrinsicParams {
+            params,
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Config> ExtrinsicParamsEncoder for DynamicExtrinsicParams<T> {
+    fn encode_extra_to(&self, v: &mut Vec<u8>) {
+        for ext in &self.params {
+            ext.encode_extra_to(v);
+        }
+    }
+    fn encode_additional_to(&self, v: &mut Vec<u8>) {
+        for ext in &self.params {
+            ext.encode_additional_to(v);
+        }
+    }
+}
+
+fn add_encoder<T: Config, S: SignedExtension<T>, Client: OfflineClientT<T>>(
+    encoders: &mut HashMap<&'static str, Box<dyn ExtrinsicParamsEncoder>>,
+    nonce: u64,
+    client: &Client,
+    other_params: S::OtherParams,
+) -> Result<(), ExtrinsicParamsError> {
+    let ext = S::new(nonce, client.clone(), other_params).map_err(Into::into)?;
+    encoders.insert(S::NAME, Box::new(ext));
+    Ok(())
+}
+
+/// A signed extension whose values were given as [`Value`]s and have already been encoded.
+struct EncodedSignedExtension {
+    extra: Vec<u8>,
+    additional: Vec<u8>,
+}
+
+impl ExtrinsicParamsEncoder for EncodedSignedExtension {
+    fn encode_extra_to(&self, v: &mut Vec<u8>) {
+        v.extend_from_slice(&self.extra);
+    }
+    fn encode_additional_to(&self, v: &mut Vec<u8>) {
+        v.extend_from_slice(&self.additional);
+    }
+}
+
+/// A builder that outputs the [`DynamicOtherParams`] required for [`DynamicExtrinsicParams`].
+/// This exposes the same methods as [`DefaultExtrinsicParamsBuilder`] for the signed
+/// extensions that are known about, and [`DynamicExtrinsicParamsBuilder::signed_extension()`]
+/// to provide values for any others.
+pub struct DynamicExtrinsicParamsBuilder<T: Config> {
+    known: DefaultExtrinsicParamsBuilder<T>,
+    values: HashMap<String, SignedExtensionValues>,
+}
+
+impl<T: Config> Default for DynamicExtrinsicParamsBuilder<T> {
+    fn default() -> Self {
+        Self {
+            known: DefaultExtrinsicParamsBuilder::new(),
+            values: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Config> DynamicExtrinsicParamsBuilder<T> {
+    /// Configure new extrinsic params. We default to providing no tip
+    /// and using an immortal transaction unless otherwise configured
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Make the transaction mortal. See [`DefaultExtrinsicParamsBuilder::mortal()`].
+    pub fn mortal(mut self, from_block: &T::Header, for_n_blocks: u64) -> Self {
+        self.known = self.known.mortal(from_block, for_n_blocks);
+        self
+    }
+
+    /// Make the transaction mortal, given a block number and block hash which must both point
+    /// to the same block. See [`DefaultExtrinsicParamsBuilder::mortal_unchecked()`].
+    pub fn mortal_unchecked(
+        mut self,
+        from_block_number: u64,
+        from_block_hash: T::Hash,
+        for_n_blocks: u64,
+    ) -> Self {
+        self.known = self
+            .known
+            .mortal_unchecked(from_block_number, from_block_hash, for_n_blocks);
+        self
+    }
+
+    /// Provide a tip to the block author in the chain's native token.
+    pub fn tip(mut self, tip: u128) -> Self {
+        self.known = self.known.tip(tip);
+        self
+    }
+
+    /// Provide a tip to the block author using the token denominated by the `asset_id`
+    /// provided. See [`DefaultExtrinsicParamsBuilder::tip_of()`].
+    pub fn tip_of(
+        mut self,
+        tip: u128,
+        asset_id: impl EncodeAsType + Send + Sync + 'static,
+    ) -> Self {
+        self.known = self.known.tip_of(tip, asset_id);
+        self
+    }
+
+    /// Include the given metadata hash in the signed data. See
+    /// [`DefaultExtrinsicParamsBuilder::metadata_hash()`].
+    pub fn metadata_hash(mut self, hash: [u8; 32]) -> Self {
+        self.known = self.known.metadata_hash(hash);
+        self
+    }
+
+    /// Provide the values to encode for the signed extension with the given identifier. The
+    /// `extra` value is included in the extrinsic, and the `additional` value is only included
+    /// in the signer payload. These must be compatible with the `extra` and `additional` types
+    /// given in the metadata for this signed extension; an empty composite value like
+    /// `Value::unnamed_composite([])` can be given for either if its type is zero sized.
+    ///
+    /// This takes priority over any of the signed extensions that are otherwise known about,
+    /// and is ignored if the chain doesn't use a signed extension with this identifier.
+    pub fn signed_extension(
+        mut self,
+        identifier: impl Into<String>,
+        extra: Value,
+        additional: Value,
+    ) -> Self {
+        self.values.insert(
+            identifier.into(),
+            SignedExtensionValues { extra, additional },
+        );
+        self
+    }
+
+    /// Build the extrinsic parameters.
+    pub fn build(self) -> DynamicOtherParams<T> {
+        DynamicOtherParams {
+            known: self.known.build(),
+            values: self.values,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::signed_extensions::test_utils::{client, metadata_with_signed_extensions};
+    use crate::config::DefaultExtrinsicParamsBuilder;
+    use crate::{Metadata, PolkadotConfig};
+    use codec::{Compact, Decode, Encode};
+    use frame_metadata::v15::SignedExtensionMetadata;
+    use scale_info::{meta_type, TypeInfo};
+
+    type Dynamic = DynamicConfig<PolkadotConfig>;
+
+    fn polkadot_metadata() -> Metadata {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_full.scale").unwrap();
+        Metadata::decode(&mut &*bytes).unwrap()
+    }
+
+    #[derive(TypeInfo)]
+    #[allow(dead_code)]
+    struct CheckCustom(u32);
+    #[derive(TypeInfo)]
+    struct CheckZeroSized;
+
+    /// Metadata with a standard signed extension, a non-standard one, and a
+    /// non-standard zero sized one.
+    fn custom_metadata() -> Metadata {
+        metadata_with_signed_extensions(vec![
+            SignedExtensionMetadata {
+                identifier: "CheckNonce",
+                ty: meta_type::<Compact<u64>>(),
+                additional_signed: meta_type::<()>(),
+            },
+            SignedExtensionMetadata {
+                identifier: "CheckCustom",
+                ty: meta_type::<CheckCustom>(),
+                additional_signed: meta_type::<bool>(),
+            },
+            SignedExtensionMetadata {
+                identifier: "CheckZeroSized",
+                ty: meta_type::<CheckZeroSized>(),
+                additional_signed: meta_type::<()>(),
+            },
+        ])
+    }
+
+    fn encode<T: Config>(params: &T::ExtrinsicParams) -> (Vec<u8>, Vec<u8>) {
+        let mut extra = vec![];
+        let mut additional = vec![];
+        params.encode_extra_to(&mut extra);
+        params.encode_additional_to(&mut additional);
+        (extra, additional)
+    }
+
+    #[test]
+    fn known_signed_extensions_are_encoded_as_by_default() {
+        let metadata = polkadot_metadata();
+
+        let default_params = <PolkadotConfig as Config>::ExtrinsicParams::new(
+            3,
+            client::<PolkadotConfig>(metadata.clone()),
+            DefaultExtrinsicParamsBuilder::new().tip(10).build(),
+        )
+        .unwrap();
+        let dynamic_params = DynamicExtrinsicParams::<Dynamic>::new(
+            3,
+            client::<Dynamic>(metadata),
+            DynamicExtrinsicParamsBuilder::new().tip(10).build(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            encode::<PolkadotConfig>(&default_params),
+            encode::<Dynamic>(&dynamic_params)
+        );
+    }
+
+    #[test]
+    fn unknown_signed_extensions_are_encoded_from_values() {
+        let client = client::<Dynamic>(custom_metadata());
+
+        // Values haven't been given for "CheckCustom", which isn't zero sized:
+        let res = DynamicExtrinsicParams::<Dynamic>::new(3, client.clone(), Default::default());
+        assert!(matches!(
+            res,
+            Err(ExtrinsicParamsError::UnknownSignedExtension(name)) if name == "CheckCustom"
+        ));
+
+        // Now they have; "CheckZeroSized" doesn't need any values:
+        let params = DynamicExtrinsicParams::<Dynamic>::new(
+            3,
+            client.clone(),
+            DynamicExtrinsicParamsBuilder::new()
+                .signed_extension("CheckCustom", Value::u128(100), Value::bool(true))
+                .build(),
+        )
+        .unwrap();
+        assert_eq!(
+            encode::<Dynamic>(&params),
+            ((Compact(3u64), 100u32).encode(), true.encode())
+        );
+
+        // Values take priority over known signed extensions:
+        let params = DynamicExtrinsicParams::<Dynamic>::new(
+            3,
+            client,
+            DynamicExtrinsicParamsBuilder::new()
+                .signed_extension("CheckCustom", Value::u128(100), Value::bool(true))
+                .signed_extension("CheckNonce", Value::u128(5), Value::unnamed_composite([]))
+                .build(),
+        )
+        .unwrap();
+        assert_eq!(
+            encode::<Dynamic>(&params),
+            ((Compact(5u64), 100u32).encode(), true.encode())
+        );
+    }
+
+    #[test]
+    fn signed_extension_values_are_type_checked() {
+        let res = DynamicExtrinsicParams::<Dynamic>::new(
+            3,
+            client::<Dynamic>(custom_metadata()),
+            DynamicExtrinsicParamsBuilder::new()
+                .signed_extension("CheckCustom", Value::string("nope"), Value::bool(true))
+                .build(),
+        );
+        assert!(matches!(
+            res,
+            Err(ExtrinsicParamsError::InvalidSignedExtensionValue { identifier, .. })
+                if identifier == "CheckCustom"
+        ));
+    }
+}
@@ -18,6 +18,14 @@ pub enum ExtrinsicParamsError {
     /// A signed extension was encountered that we don't know about.
     #[error("Error constructing extrinsic parameters: Unknown signed extension '{0}'")]
     UnknownSignedExtension(String),
+    /// A value given for a signed extension doesn't match the type expected by the metadata.
+    #[error("Error constructing extrinsic parameters: Cannot encode the value given for signed extension '{identifier}': {error}")]
+    InvalidSignedExtensionValue {
+        /// The identifier of the signed extension.
+        identifier: String,
+        /// The error encoding the value.
+        error: scale_encode::Error,
+    },
     /// Some custom error.
     #[error("Error constructing extrinsic parameters: {0}")]
     Custom(CustomError),
@@ -9,6 +9,7 @@
 //! Polkadot node.
 
 mod default_extrinsic_params;
+mod dynamic_extrinsic_params;
 mod extrinsic_params;
 
 pub mod polkadot;
@@ -20,6 +21,9 @@ use core::fmt::Debug;
 use serde::{de::DeserializeOwned, Serialize};
 
 pub use default_extrinsic_params::{DefaultExtrinsicParams, DefaultExtrinsicParamsBuilder};
+pub use dynamic_extrinsic_params::{
+    DynamicConfig, DynamicExtrinsicParams, DynamicExtrinsicParamsBuilder, DynamicOtherParams,
+};
 pub use extrinsic_params::{ExtrinsicParams, ExtrinsicParamsEncoder, ExtrinsicParamsError};
 pub use polkadot::{PolkadotConfig, PolkadotExtrinsicParams, PolkadotExtrinsicParamsBuilder};
 pub use signed_extensions::SignedExtension;
@@ -32,16 +36,7 @@ pub use substrate::{SubstrateConfig, SubstrateExtrinsicParams, SubstrateExtrinsi
 // rather than having to `unsafe impl` them ourselves.
 pub trait Config: Sized + Send + Sync + 'static {
     /// The output of the `Hasher` function.
-    type Hash: Debug
-        + Copy
-        + Send
-        + Sync
-        + Decode
-        + AsRef<[u8]>
-        + Serialize
-        + DeserializeOwned
-        + Encode
-        + PartialEq;
+    type Hash: BlockHash;
 
     /// The account ID type.
     type AccountId: Debug + Clone + Encode;
@@ -65,6 +60,40 @@ pub trait Config: Sized + Send + Sync + 'static {
 /// given some [`Config`], this return the other params needed for its `ExtrinsicParams`.
 pub type OtherParamsFor<T> = <<T as Config>::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams;
 
+/// Block hashes must conform to a bunch of things to be used in Subxt.
+pub trait BlockHash:
+    Debug
+    + Copy
+    + Send
+    + Sync
+    + Decode
+    + AsRef<[u8]>
+    + Serialize
+    + DeserializeOwned
+    + Encode
+    + PartialEq
+    + Eq
+    + std::hash::Hash
+    + 'static
+{
+}
+impl<T> BlockHash for T where
+    T: Debug
+        + Copy
+        + Send
+        + Sync
+        + Decode
+        + AsRef<[u8]>
+        + Serialize
+        + DeserializeOwned
+        + Encode
+        + PartialEq
+        + Eq
+        + std::hash::Hash
+        + 'static
+{
+}
+
 /// This represents the hasher used by a node to hash things like block headers
 /// and extrinsics.
 pub trait Hasher {
@@ -91,6 +120,18 @@ pub trait Header: Sized + Encode + Decode {
     /// Return the block number of this header.
     fn number(&self) -> Self::Number;
 
+    /// Return the hash of the parent of the block that this header belongs to, or `None` if
+    /// it can't be found.
+    ///
+    /// By default, this is decoded from the start of the encoded header, which is where
+    /// Substrate based chains put it.
+    fn parent_hash(&self) -> Option<<Self::Hasher as Hasher>::Output>
+    where
+        <Self::Hasher as Hasher>::Output: Decode,
+    {
+        Decode::decode(&mut &*self.encode()).ok()
+    }
+
     /// Hash this header.
     fn hash(&self) -> <Self::Hasher as Hasher>::Output {
         Self::Hasher::hash_of(self)
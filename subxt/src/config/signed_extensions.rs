@@ -12,6 +12,9 @@ use crate::utils::Era;
 use crate::{client::OfflineClientT, Config};
 use codec::{Compact, Encode};
 use core::fmt::Debug;
+use scale_decode::DecodeAsType;
+use scale_encode::EncodeAsType;
+use scale_value::Value;
 use std::collections::HashMap;
 
 /// A single [`SignedExtension`] has a unique name, but is otherwise the
@@ -21,6 +24,11 @@ pub trait SignedExtension<T: Config>: ExtrinsicParams<T> {
     /// The name of the signed extension. This is used to associate it
     /// with the signed extensions that the node is making use of.
     const NAME: &'static str;
+
+    /// The type that the `extra` data of this signed extension (ie the part which is
+    /// included in an extrinsic) is decoded into, for instance when looking at the
+    /// signed extensions of extrinsics in a block.
+    type Decoded: DecodeAsType;
 }
 
 /// The [`CheckSpecVersion`] signed extension.
@@ -48,6 +56,7 @@ impl ExtrinsicParamsEncoder for CheckSpecVersion {
 
 impl<T: Config> SignedExtension<T> for CheckSpecVersion {
     const NAME: &'static str = "CheckSpecVersion";
+    type Decoded = ();
 }
 
 /// The [`CheckNonce`] signed extension.
@@ -75,6 +84,7 @@ impl ExtrinsicParamsEncoder for CheckNonce {
 
 impl<T: Config> SignedExtension<T> for CheckNonce {
     const NAME: &'static str = "CheckNonce";
+    type Decoded = u64;
 }
 
 /// The [`CheckTxVersion`] signed extension.
@@ -102,6 +112,7 @@ impl ExtrinsicParamsEncoder for CheckTxVersion {
 
 impl<T: Config> SignedExtension<T> for CheckTxVersion {
     const NAME: &'static str = "CheckTxVersion";
+    type Decoded = ();
 }
 
 /// The [`CheckGenesis`] signed extension.
@@ -134,6 +145,7 @@ impl<T: Config> ExtrinsicParamsEncoder for CheckGenesis<T> {
 
 impl<T: Config> SignedExtension<T> for CheckGenesis<T> {
     const NAME: &'static str = "CheckGenesis";
+    type Decoded = ();
 }
 
 /// The [`CheckMortality`] signed extension.
@@ -213,20 +225,106 @@ impl<T: Config> ExtrinsicParamsEncoder for CheckMortality<T> {
 
 impl<T: Config> SignedExtension<T> for CheckMortality<T> {
     const NAME: &'static str = "CheckMortality";
+    type Decoded = Era;
 }
 
-/// The [`ChargeAssetTxPayment`] signed extension.
+/// The [`CheckMetadataHash`] signed extension. When enabled, the hash of the chain's
+/// merkleized metadata is included in the data that is signed, so that an offline signer which
+/// decodes the transaction using a proof of the types it needs (rather than the full metadata)
+/// can be sure that those types are correct. See
+/// [`crate::metadata::types::merkleized`] for computing the metadata hash and proofs.
 #[derive(Debug)]
+pub struct CheckMetadataHash {
+    hash: Option<[u8; 32]>,
+}
+
+/// The mode of the [`CheckMetadataHash`] signed extension, which is included in an extrinsic
+/// to say whether a metadata hash was part of the signed data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, DecodeAsType)]
+pub enum CheckMetadataHashMode {
+    /// No metadata hash was signed.
+    Disabled,
+    /// The metadata hash was signed.
+    Enabled,
+}
+
+/// Parameters to configure the [`CheckMetadataHash`] signed extension.
+#[derive(Debug, Default)]
+pub struct CheckMetadataHashParams {
+    hash: Option<[u8; 32]>,
+}
+
+impl CheckMetadataHashParams {
+    /// Don't include a metadata hash in the signed data.
+    pub fn disabled() -> Self {
+        CheckMetadataHashParams { hash: None }
+    }
+    /// Include the given metadata hash in the signed data. If this doesn't match the hash
+    /// of the metadata of the chain, the transaction will be rejected.
+    pub fn enabled(hash: [u8; 32]) -> Self {
+        CheckMetadataHashParams { hash: Some(hash) }
+    }
+}
+
+impl<T: Config> ExtrinsicParams<T> for CheckMetadataHash {
+    type OtherParams = CheckMetadataHashParams;
+    type Error = std::convert::Infallible;
+
+    fn new<Client: OfflineClientT<T>>(
+        _nonce: u64,
+        _client: Client,
+        other_params: Self::OtherParams,
+    ) -> Result<Self, Self::Error> {
+        Ok(CheckMetadataHash {
+            hash: other_params.hash,
+        })
+    }
+}
+
+impl ExtrinsicParamsEncoder for CheckMetadataHash {
+    fn encode_extra_to(&self, v: &mut Vec<u8>) {
+        // The mode; 0 for disabled and 1 for enabled.
+        (self.hash.is_some() as u8).encode_to(v);
+    }
+    fn encode_additional_to(&self, v: &mut Vec<u8>) {
+        self.hash.encode_to(v);
+    }
+}
+
+impl<T: Config> SignedExtension<T> for CheckMetadataHash {
+    const NAME: &'static str = "CheckMetadataHash";
+    type Decoded = CheckMetadataHashMode;
+}
+
+/// The [`ChargeAssetTxPayment`] signed extension.
+#[derive(Debug, DecodeAsType)]
 pub struct ChargeAssetTxPayment {
     tip: Compact<u128>,
-    asset_id: Option<u32>,
+    asset_id: Option<Value<u32>>,
+    /// The encoded `extra` data, which is only available when this is constructed via
+    /// [`ExtrinsicParams::new()`] rather than decoded.
+    #[decode_as_type(skip)]
+    encoded: Option<Vec<u8>>,
+}
+
+impl ChargeAssetTxPayment {
+    /// The tip given to the extrinsic author.
+    pub fn tip(&self) -> u128 {
+        self.tip.0
+    }
+    /// The ID of the asset that the fee and tip are paid in, or `None` if they
+    /// are paid in the native chain token. Depending on the chain, this may be a
+    /// number or something more complex like an XCM `MultiLocation`.
+    pub fn asset_id(&self) -> Option<&Value<u32>> {
+        self.asset_id.as_ref()
+    }
 }
 
 /// Parameters to configure the [`ChargeAssetTxPayment`] signed extension.
 #[derive(Default)]
 pub struct ChargeAssetTxPaymentParams {
     tip: u128,
-    asset_id: Option<u32>,
+    asset_id: Option<Box<dyn EncodeAsType + Send + Sync>>,
 }
 
 impl ChargeAssetTxPaymentParams {
@@ -244,47 +342,89 @@ impl ChargeAssetTxPaymentParams {
             asset_id: None,
         }
     }
-    /// Tip the extrinsic author using the asset ID given.
-    pub fn tip_of(tip: u128, asset_id: u32) -> Self {
+    /// Tip the extrinsic author using the asset ID given. This is encoded according to the
+    /// asset ID type in the metadata, and so can be a `u32`, or something like an XCM
+    /// `MultiLocation` (for instance a [`scale_value::Value`] or a generated type) on chains
+    /// which identify assets that way.
+    pub fn tip_of(tip: u128, asset_id: impl EncodeAsType + Send + Sync + 'static) -> Self {
         ChargeAssetTxPaymentParams {
             tip,
-            asset_id: Some(asset_id),
+            asset_id: Some(Box::new(asset_id)),
         }
     }
 }
 
 impl<T: Config> ExtrinsicParams<T> for ChargeAssetTxPayment {
     type OtherParams = ChargeAssetTxPaymentParams;
-    type Error = std::convert::Infallible;
+    type Error = ExtrinsicParamsError;
 
     fn new<Client: OfflineClientT<T>>(
         _nonce: u64,
-        _client: Client,
+        client: Client,
         other_params: Self::OtherParams,
     ) -> Result<Self, Self::Error> {
+        let tip = Compact(other_params.tip);
+        let metadata = client.metadata();
+        let Some(ext) = metadata
+            .extrinsic()
+            .signed_extensions()
+            .iter()
+            .find(|ext| ext.identifier() == <Self as SignedExtension<T>>::NAME)
+        else {
+            // The chain doesn't use this signed extension, so it won't be encoded.
+            return Ok(ChargeAssetTxPayment {
+                tip,
+                asset_id: None,
+                encoded: None,
+            });
+        };
+
+        // The asset ID type differs between chains, so encode it according to the metadata.
+        let types = metadata.types();
+        let asset_id = other_params.asset_id.as_deref();
+        let encoded = (tip, asset_id)
+            .encode_as_type(ext.extra_ty(), types)
+            .map_err(|error| ExtrinsicParamsError::InvalidSignedExtensionValue {
+                identifier: ext.identifier().to_owned(),
+                error,
+            })?;
+        let decoded = Self::decode_as_type(&mut &*encoded, ext.extra_ty(), types)
+            .map_err(|e| ExtrinsicParamsError::Custom(Box::new(e)))?;
+
         Ok(ChargeAssetTxPayment {
-            tip: Compact(other_params.tip),
-            asset_id: other_params.asset_id,
+            encoded: Some(encoded),
+            ..decoded
         })
     }
 }
 
 impl ExtrinsicParamsEncoder for ChargeAssetTxPayment {
     fn encode_extra_to(&self, v: &mut Vec<u8>) {
-        (self.tip, self.asset_id).encode_to(v);
+        match &self.encoded {
+            Some(encoded) => v.extend_from_slice(encoded),
+            None => (self.tip, None::<u32>).encode_to(v),
+        }
     }
 }
 
 impl<T: Config> SignedExtension<T> for ChargeAssetTxPayment {
     const NAME: &'static str = "ChargeAssetTxPayment";
+    type Decoded = Self;
 }
 
 /// The [`ChargeTransactionPayment`] signed extension.
-#[derive(Debug)]
+#[derive(Debug, DecodeAsType)]
 pub struct ChargeTransactionPayment {
     tip: Compact<u128>,
 }
 
+impl ChargeTransactionPayment {
+    /// The tip given to the extrinsic author.
+    pub fn tip(&self) -> u128 {
+        self.tip.0
+    }
+}
+
 /// Parameters to configure the [`ChargeTransactionPayment`] signed extension.
 #[derive(Default)]
 pub struct ChargeTransactionPaymentParams {
@@ -325,6 +465,7 @@ impl ExtrinsicParamsEncoder for ChargeTransactionPayment {
 
 impl<T: Config> SignedExtension<T> for ChargeTransactionPayment {
     const NAME: &'static str = "ChargeTransactionPayment";
+    type Decoded = Self;
 }
 
 /// This accepts a tuple of [`SignedExtension`]s, and will dynamically make use of whichever
@@ -432,7 +573,7 @@ const _: () = {
 
 /// Checks to see whether the type being given is empty, ie would require
 /// 0 bytes to encode.
-fn is_type_empty(type_id: u32, types: &scale_info::PortableRegistry) -> bool {
+pub(crate) fn is_type_empty(type_id: u32, types: &scale_info::PortableRegistry) -> bool {
     let Some(ty) = types.resolve(type_id) else {
         // Can't resolve; type may not be empty. Not expected to hit this.
         return false;
@@ -451,3 +592,183 @@ fn is_type_empty(type_id: u32, types: &scale_info::PortableRegistry) -> bool {
         | TypeDef::Primitive(_) => false,
     }
 }
+
+#[cfg(test)]
+pub(super) mod test_utils {
+    use crate::client::OfflineClient;
+    use crate::{Config, Metadata};
+    use codec::Decode;
+    use frame_metadata::v15::{
+        CustomMetadata, ExtrinsicMetadata, OuterEnums, RuntimeMetadataV15, SignedExtensionMetadata,
+    };
+    use frame_metadata::RuntimeMetadataPrefixed;
+    use scale_info::{form::MetaForm, meta_type, TypeInfo};
+
+    /// An offline client for the given metadata.
+    pub fn client<T: Config>(metadata: Metadata) -> OfflineClient<T> {
+        OfflineClient::new(
+            T::Hash::decode(&mut &[7u8; 32][..]).unwrap(),
+            crate::backend::RuntimeVersion {
+                spec_version: 1,
+                transaction_version: 2,
+            },
+            metadata,
+        )
+    }
+
+    /// Metadata which contains nothing but the given signed extensions.
+    pub fn metadata_with_signed_extensions(
+        signed_extensions: Vec<SignedExtensionMetadata<MetaForm>>,
+    ) -> Metadata {
+        #[derive(TypeInfo)]
+        #[allow(dead_code)]
+        enum Call {}
+
+        let extrinsic = ExtrinsicMetadata {
+            version: 4,
+            signed_extensions,
+            address_ty: meta_type::<()>(),
+            call_ty: meta_type::<Call>(),
+            signature_ty: meta_type::<()>(),
+            extra_ty: meta_type::<()>(),
+        };
+        let metadata = RuntimeMetadataV15::new(
+            vec![],
+            extrinsic,
+            meta_type::<()>(),
+            vec![],
+            OuterEnums {
+                call_enum_ty: meta_type::<Call>(),
+                event_enum_ty: meta_type::<Call>(),
+                error_enum_ty: meta_type::<Call>(),
+            },
+            CustomMetadata {
+                map: Default::default(),
+            },
+        );
+        let prefixed = RuntimeMetadataPrefixed::from(metadata);
+        Metadata::new(prefixed.try_into().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::test_utils::{client, metadata_with_signed_extensions};
+    use super::*;
+    use crate::PolkadotConfig;
+    use frame_metadata::v15::SignedExtensionMetadata;
+    use scale_info::{meta_type, TypeInfo};
+
+    // A cut down version of the XCM `MultiLocation` type that Asset Hub identifies assets with.
+    #[derive(TypeInfo, Encode)]
+    struct MultiLocation {
+        parents: u8,
+        interior: Junctions,
+    }
+    #[derive(TypeInfo, Encode)]
+    #[allow(dead_code)]
+    enum Junctions {
+        Here,
+        X1(Junction),
+    }
+    #[derive(TypeInfo, Encode)]
+    #[allow(dead_code)]
+    enum Junction {
+        Parachain(#[codec(compact)] u32),
+        GeneralIndex(#[codec(compact)] u128),
+    }
+
+    #[derive(TypeInfo)]
+    #[allow(dead_code)]
+    struct AssetTxPayment<AssetId> {
+        #[codec(compact)]
+        tip: u128,
+        asset_id: Option<AssetId>,
+    }
+
+    fn charge_asset_tx_payment<AssetId: TypeInfo + 'static>(
+        params: ChargeAssetTxPaymentParams,
+    ) -> Result<ChargeAssetTxPayment, ExtrinsicParamsError> {
+        let metadata = metadata_with_signed_extensions(vec![SignedExtensionMetadata {
+            identifier: "ChargeAssetTxPayment",
+            ty: meta_type::<AssetTxPayment<AssetId>>(),
+            additional_signed: meta_type::<()>(),
+        }]);
+        <ChargeAssetTxPayment as ExtrinsicParams<PolkadotConfig>>::new(
+            0,
+            client::<PolkadotConfig>(metadata),
+            params,
+        )
+    }
+
+    fn encode_extra(ext: &ChargeAssetTxPayment) -> Vec<u8> {
+        let mut v = vec![];
+        ext.encode_extra_to(&mut v);
+        v
+    }
+
+    #[test]
+    fn asset_ids_are_encoded_according_to_the_metadata() {
+        // Numeric asset IDs:
+        let ext = charge_asset_tx_payment::<u32>(ChargeAssetTxPaymentParams::tip_of(10, 1984u32))
+            .unwrap();
+        assert_eq!(
+            encode_extra(&ext),
+            (Compact(10u128), Some(1984u32)).encode()
+        );
+        assert_eq!(ext.tip(), 10);
+        assert!(ext.asset_id().is_some());
+
+        // The tip given to the default builder is used too:
+        let params = crate::config::DefaultExtrinsicParamsBuilder::<PolkadotConfig>::new()
+            .tip_of(10, 1984u32)
+            .build();
+        let ext = charge_asset_tx_payment::<u32>(params.5).unwrap();
+        assert_eq!(
+            encode_extra(&ext),
+            (Compact(10u128), Some(1984u32)).encode()
+        );
+
+        // Multi-location asset IDs, given as a value:
+        let asset_id = Value::named_composite([
+            ("parents", Value::u128(0)),
+            (
+                "interior",
+                Value::unnamed_variant(
+                    "X1",
+                    [Value::unnamed_variant("GeneralIndex", [Value::u128(1984)])],
+                ),
+            ),
+        ]);
+        let ext = charge_asset_tx_payment::<MultiLocation>(ChargeAssetTxPaymentParams::tip_of(
+            10, asset_id,
+        ))
+        .unwrap();
+        let expected = MultiLocation {
+            parents: 0,
+            interior: Junctions::X1(Junction::GeneralIndex(1984)),
+        };
+        assert_eq!(
+            encode_extra(&ext),
+            (Compact(10u128), Some(expected)).encode()
+        );
+
+        // No asset ID:
+        let ext =
+            charge_asset_tx_payment::<MultiLocation>(ChargeAssetTxPaymentParams::tip(5)).unwrap();
+        assert_eq!(encode_extra(&ext), (Compact(5u128), None::<()>).encode());
+        assert!(ext.asset_id().is_none());
+    }
+
+    #[test]
+    fn asset_ids_are_type_checked() {
+        let res = charge_asset_tx_payment::<MultiLocation>(ChargeAssetTxPaymentParams::tip_of(
+            10, 1984u32,
+        ));
+        assert!(matches!(
+            res,
+            Err(ExtrinsicParamsError::InvalidSignedExtensionValue { identifier, .. })
+                if identifier == "ChargeAssetTxPayment"
+        ));
+    }
+}
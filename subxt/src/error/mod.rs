@@ -62,6 +62,12 @@ pub enum Error {
     /// Error constructing the appropriate extrinsic params.
     #[error("{0}")]
     ExtrinsicParams(#[from] ExtrinsicParamsError),
+    /// Error signing a transaction.
+    #[error("Signing error: {0}")]
+    Signing(Box<dyn std::error::Error + Send + Sync + 'static>),
+    /// Error working with an offline signing request or response.
+    #[error("Offline signing error: {0}")]
+    OfflineSigning(#[from] OfflineSigningError),
     /// Block related error.
     #[error("Block error: {0}")]
     Block(#[from] BlockError),
@@ -111,6 +117,17 @@ pub enum RpcError {
     /// The RPC subscription dropped.
     #[error("RPC error: subscription dropped.")]
     SubscriptionDropped,
+    /// The RPC server cannot handle the request at the moment.
+    #[error("RPC error: limit reached.")]
+    LimitReached,
+    /// The connection was lost and is being re-established. Subscriptions end with
+    /// this error, and can be subscribed to again once it has been handed back.
+    #[error("RPC error: the connection was lost and is being re-established: {0}")]
+    DisconnectedWillReconnect(String),
+    /// The RPC client cannot subscribe to anything, for instance because it talks
+    /// to the node over HTTP. Contains the name of the subscription method.
+    #[error("RPC error: subscriptions are not supported by this client (tried to call {0}).")]
+    SubscriptionsNotSupported(String),
 }
 
 /// Block error
@@ -130,6 +147,9 @@ pub enum BlockError {
     /// Decoding error.
     #[error("Cannot decode extrinsic: {0}")]
     DecodingError(codec::Error),
+    /// The extrinsic was not decoded from a block, and so we don't know where to find its events.
+    #[error("The extrinsic was not decoded from a block, so its events cannot be fetched")]
+    NoBlockHash,
 }
 
 impl BlockError {
@@ -157,6 +177,50 @@ pub enum TransactionError {
     /// The transaction was dropped.
     #[error("The transaction was dropped: {0}")]
     Dropped(String),
+    /// The mortality period of the transaction ended before it made it into a block.
+    #[error("The transaction's mortality period ended before it made it into a block")]
+    Expired,
+    /// The transaction did not make it into a block before the deadline given to
+    /// [`crate::tx::ResilientSubmitter`] passed.
+    #[error("The transaction did not make it into a block before the deadline passed")]
+    DeadlineExceeded,
+    /// The transaction was submitted the maximum number of times allowed by
+    /// [`crate::tx::ResilientSubmitter`], and did not make it into a block.
+    #[error("The transaction did not make it into a block after {attempts} attempts; the last attempt failed with: {last_error}")]
+    AttemptsExhausted {
+        /// How many times the transaction was submitted.
+        attempts: u32,
+        /// The error that the last attempt failed with.
+        last_error: String,
+    },
+}
+
+/// Something went wrong working with a [`crate::tx::SigningRequest`] or
+/// [`crate::tx::SigningResponse`].
+#[derive(Clone, Debug, Eq, thiserror::Error, PartialEq)]
+#[non_exhaustive]
+pub enum OfflineSigningError {
+    /// The request or response is in a format version that we don't support.
+    #[error("Unsupported offline signing format version {0}")]
+    UnsupportedVersion(u8),
+    /// The request was created for a different chain to the one that the client is connected to.
+    #[error("The signing request was created for a chain with a different genesis hash")]
+    GenesisHashMismatch,
+    /// The request was created for a different runtime version to the one the client is using.
+    #[error("The signing request was created for spec version {request_spec_version} and transaction version {request_transaction_version}, but the client is at spec version {spec_version} and transaction version {transaction_version}")]
+    RuntimeVersionMismatch {
+        /// The spec version that the request was created for.
+        request_spec_version: u32,
+        /// The transaction version that the request was created for.
+        request_transaction_version: u32,
+        /// The spec version of the client.
+        spec_version: u32,
+        /// The transaction version of the client.
+        transaction_version: u32,
+    },
+    /// The signing response was not produced by signing the request it's being used with.
+    #[error("The signing response is not for this signing request")]
+    ResponseMismatch,
 }
 
 /// Something went wrong trying to encode a storage address.
@@ -104,6 +104,11 @@ impl<T: Config> Events<T> {
         self.block_hash
     }
 
+    /// The metadata used to decode these events.
+    pub(crate) fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
     /// Iterate over all of the events, using metadata to dynamically
     /// decode them as we go, and returning the raw bytes and other associated
     /// details. If an error occurs, all subsequent iterations return `None`.
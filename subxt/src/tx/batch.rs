@@ -0,0 +1,225 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Build `Utility` batch calls out of other transaction payloads.
+
+use super::tx_payload::call_to_outer_enum_value;
+use crate::{dynamic::Value, error::Error, metadata::Metadata, tx::TxPayload};
+use std::sync::Arc;
+
+/// Which of the `Utility` pallet's batch calls a [`BatchPayload`] is dispatched with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatchKind {
+    /// `Utility::batch`: calls are dispatched in order, stopping at the first one which
+    /// fails. Calls dispatched before the failure are not reverted.
+    Batch,
+    /// `Utility::batch_all`: calls are dispatched in order, and if any of them fail then
+    /// the whole batch is reverted.
+    BatchAll,
+    /// `Utility::force_batch`: every call is dispatched, regardless of whether any fail.
+    ForceBatch,
+}
+
+impl BatchKind {
+    /// The name of the `Utility` call for this kind of batch.
+    pub fn call_name(&self) -> &'static str {
+        match self {
+            BatchKind::Batch => "batch",
+            BatchKind::BatchAll => "batch_all",
+            BatchKind::ForceBatch => "force_batch",
+        }
+    }
+}
+
+/// A transaction payload which dispatches a list of other (static or dynamic) transaction
+/// payloads via one of the `Utility` pallet's batch calls.
+///
+/// Each call is encoded as the runtime's outer call enum type (as given by the metadata),
+/// so no codegen of that `RuntimeCall` enum is needed.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use subxt::dynamic::Value;
+/// use subxt::tx::BatchPayload;
+///
+/// let remark = subxt::dynamic::tx("System", "remark", vec![Value::from_bytes("Hello")]);
+/// let transfer = subxt::dynamic::tx(
+///     "Balances",
+///     "transfer_keep_alive",
+///     vec![
+///         Value::unnamed_variant("Id", [Value::from_bytes([0u8; 32])]),
+///         Value::u128(1_000),
+///     ],
+/// );
+///
+/// let batch = BatchPayload::batch_all().call(remark).call(transfer);
+/// ```
+#[derive(Clone)]
+pub struct BatchPayload {
+    kind: BatchKind,
+    // Dev Note: Arc used to enable easy cloning (given that we can't have dyn Clone).
+    calls: Vec<Arc<dyn TxPayload + Send + Sync + 'static>>,
+}
+
+impl std::fmt::Debug for BatchPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchPayload")
+            .field("kind", &self.kind)
+            .field("calls", &self.calls.len())
+            .finish()
+    }
+}
+
+impl BatchPayload {
+    /// Create a new, empty batch which will be dispatched with the given kind of batch call.
+    pub fn new(kind: BatchKind) -> Self {
+        BatchPayload {
+            kind,
+            calls: Vec::new(),
+        }
+    }
+
+    /// Create a new, empty batch to be dispatched with `Utility::batch`.
+    pub fn batch() -> Self {
+        Self::new(BatchKind::Batch)
+    }
+
+    /// Create a new, empty batch to be dispatched with `Utility::batch_all`.
+    pub fn batch_all() -> Self {
+        Self::new(BatchKind::BatchAll)
+    }
+
+    /// Create a new, empty batch to be dispatched with `Utility::force_batch`.
+    pub fn force_batch() -> Self {
+        Self::new(BatchKind::ForceBatch)
+    }
+
+    /// Add a call to the end of the batch.
+    pub fn call<Call>(mut self, call: Call) -> Self
+    where
+        Call: TxPayload + Send + Sync + 'static,
+    {
+        self.calls.push(Arc::new(call));
+        self
+    }
+
+    /// Add each of the calls given to the end of the batch.
+    pub fn calls<Call>(mut self, calls: impl IntoIterator<Item = Call>) -> Self
+    where
+        Call: TxPayload + Send + Sync + 'static,
+    {
+        for call in calls {
+            self.calls.push(Arc::new(call));
+        }
+        self
+    }
+
+    /// The kind of batch call that this will be dispatched with.
+    pub fn kind(&self) -> BatchKind {
+        self.kind
+    }
+
+    /// The number of calls in the batch.
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Are there any calls in the batch?
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+}
+
+impl TxPayload for BatchPayload {
+    fn encode_call_data_to(&self, metadata: &Metadata, out: &mut Vec<u8>) -> Result<(), Error> {
+        // Each call is handed to the batch call as a variant of the outer call enum.
+        let calls = self
+            .calls
+            .iter()
+            .map(|call| call_to_outer_enum_value(&**call, metadata))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        crate::dynamic::tx(
+            "Utility",
+            self.kind.call_name(),
+            vec![Value::unnamed_composite(calls)],
+        )
+        .encode_call_data_to(metadata, out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tx::Payload;
+    use codec::{Compact, Decode, Encode};
+
+    fn metadata() -> Metadata {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_full.scale").unwrap();
+        Metadata::decode(&mut &*bytes).unwrap()
+    }
+
+    #[test]
+    fn encodes_calls_into_a_batch() {
+        let metadata = metadata();
+        let remark = crate::dynamic::tx("System", "remark", vec![Value::from_bytes("Hello")]);
+        let set_heap_pages = crate::dynamic::tx("System", "set_heap_pages", vec![Value::u128(1)]);
+
+        let utility = metadata.pallet_by_name("Utility").unwrap();
+        for kind in [BatchKind::Batch, BatchKind::BatchAll, BatchKind::ForceBatch] {
+            let batch = BatchPayload::new(kind)
+                .call(remark.clone())
+                .call(set_heap_pages.clone());
+
+            let mut expected = vec![
+                utility.index(),
+                utility
+                    .call_variant_by_name(kind.call_name())
+                    .unwrap()
+                    .index,
+            ];
+            Compact(2u32).encode_to(&mut expected);
+            remark
+                .encode_call_data_to(&metadata, &mut expected)
+                .unwrap();
+            set_heap_pages
+                .encode_call_data_to(&metadata, &mut expected)
+                .unwrap();
+
+            assert_eq!(batch.encode_call_data(&metadata).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn validates_calls_in_the_batch() {
+        let metadata = metadata();
+        let valid_hash = metadata
+            .pallet_by_name("System")
+            .unwrap()
+            .call_hash("remark")
+            .unwrap();
+        let remark = |hash| {
+            Payload::new_static(
+                "System",
+                "remark",
+                crate::dynamic::tx("System", "remark", vec![Value::from_bytes("Hello")])
+                    .call_data()
+                    .clone(),
+                hash,
+            )
+        };
+
+        let batch = BatchPayload::batch().call(remark(valid_hash));
+        assert!(batch.encode_call_data(&metadata).is_ok());
+
+        let batch = BatchPayload::batch().call(remark([0; 32]));
+        assert!(matches!(
+            batch.encode_call_data(&metadata),
+            Err(Error::Metadata(
+                crate::error::MetadataError::IncompatibleCodegen
+            ))
+        ));
+    }
+}
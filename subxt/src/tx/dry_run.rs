@@ -0,0 +1,423 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Types and decoding logic for the result of dry running an extrinsic.
+
+use crate::{
+    blocks::ExtrinsicEvents,
+    config::Config,
+    error::{DispatchError, Error},
+    events::{self, Phase},
+    metadata::Metadata,
+    tx::{TransactionInvalid, TransactionUnknown},
+};
+use codec::{Compact, Decode, Encode};
+use derivative::Derivative;
+use scale_decode::visitor::{decode_with_visitor, IgnoreVisitor};
+
+/// The result of dry running an extrinsic with [`crate::tx::SubmittableExtrinsic::dry_run()`].
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct DryRunReport<T: Config> {
+    outcome: DryRunOutcome,
+    events: Option<ExtrinsicEvents<T>>,
+}
+
+impl<T: Config> DryRunReport<T> {
+    pub(crate) fn new(outcome: DryRunOutcome, events: Option<ExtrinsicEvents<T>>) -> Self {
+        DryRunReport { outcome, events }
+    }
+
+    /// What would happen if the extrinsic was submitted.
+    pub fn outcome(&self) -> &DryRunOutcome {
+        &self.outcome
+    }
+
+    /// Consume this result, returning the [`DryRunOutcome`].
+    pub fn into_outcome(self) -> DryRunOutcome {
+        self.outcome
+    }
+
+    /// Would the extrinsic be included in a block, and the call succeed?
+    pub fn is_success(&self) -> bool {
+        matches!(self.outcome, DryRunOutcome::Success)
+    }
+
+    /// The events that would be emitted by the extrinsic. This is only available if the runtime
+    /// supports the `DryRunApi`, and is `None` otherwise.
+    ///
+    /// # Note
+    ///
+    /// Since the extrinsic isn't actually in a block, each event is given the phase
+    /// [`Phase::ApplyExtrinsic(0)`](Phase::ApplyExtrinsic) and no topics.
+    pub fn events(&self) -> Option<&ExtrinsicEvents<T>> {
+        self.events.as_ref()
+    }
+}
+
+/// What would happen if an extrinsic which was dry run was submitted.
+#[derive(Debug, PartialEq)]
+pub enum DryRunOutcome {
+    /// The extrinsic would be included in a block, and the call would succeed.
+    Success,
+    /// The extrinsic would be included in a block (and so fees would be paid),
+    /// but the call would fail with the given error.
+    DispatchError(DispatchError),
+    /// The extrinsic is invalid, and so would not be included in a block.
+    Invalid(TransactionInvalid),
+    /// The validity of the extrinsic could not be determined, and so it would
+    /// not be included in a block.
+    Unknown(TransactionUnknown),
+    /// The extrinsic would not be included in a block, but the reason given (a
+    /// `TransactionValidityError`) could not be decoded, for instance because it is newer
+    /// than this version of Subxt. This contains the undecoded bytes of the error.
+    UndecodedValidityError(Vec<u8>),
+}
+
+impl DryRunOutcome {
+    /// Decode the bytes returned from `BlockBuilder_apply_extrinsic`.
+    pub(crate) fn decode_from(bytes: &[u8], metadata: Metadata) -> Result<Self, Error> {
+        // We are given an ApplyExtrinsicResult, which is basically a
+        // `Result<Result<(), DispatchError>, TransactionValidityError>`.
+        match bytes {
+            [0, 0, ..] => Ok(DryRunOutcome::Success),
+            [0, 1, rest @ ..] => {
+                let dispatch_error = DispatchError::decode_from(rest, metadata)?;
+                Ok(DryRunOutcome::DispatchError(dispatch_error))
+            }
+            // Any `TransactionValidityError` means that the extrinsic won't be included, so
+            // don't fail if we can't decode the details of it.
+            [1, rest @ ..] => {
+                let outcome = match rest {
+                    [0, details @ ..] => TransactionInvalid::decode(&mut &*details)
+                        .ok()
+                        .map(DryRunOutcome::Invalid),
+                    [1, details @ ..] => TransactionUnknown::decode(&mut &*details)
+                        .ok()
+                        .map(DryRunOutcome::Unknown),
+                    _ => None,
+                };
+                Ok(outcome.unwrap_or_else(|| DryRunOutcome::UndecodedValidityError(rest.to_vec())))
+            }
+            // unable to decode the bytes; they aren't what we expect.
+            _ => Err(Error::Unknown(bytes.to_vec())),
+        }
+    }
+}
+
+/// The name of the runtime API trait that dry runs extrinsics and returns their events.
+pub(crate) const DRY_RUN_API: &str = "DryRunApi";
+/// The name of the runtime API method that dry runs extrinsics and returns their events.
+pub(crate) const DRY_RUN_EXTRINSIC: &str = "dry_run_extrinsic";
+
+/// Does the runtime support the `DryRunApi` (and so can we find the events of an extrinsic)?
+pub(crate) fn supports_dry_run_api(metadata: &Metadata) -> bool {
+    metadata
+        .runtime_api_trait_by_name(DRY_RUN_API)
+        .and_then(|api| api.method_by_name(DRY_RUN_EXTRINSIC))
+        .is_some()
+}
+
+/// Decode the events from the bytes returned from `DryRunApi_dry_run_extrinsic`. These are a
+/// `Result<ExtrinsicDryRunEffects, Error>`, and we return `None` if they are an error (for
+/// instance because the runtime doesn't implement the API).
+pub(crate) fn decode_dry_run_events<T: Config>(
+    bytes: &[u8],
+    metadata: &Metadata,
+    ext_hash: T::Hash,
+    block_hash: T::Hash,
+) -> Result<Option<ExtrinsicEvents<T>>, Error> {
+    let output_ty = metadata
+        .runtime_api_trait_by_name(DRY_RUN_API)
+        .and_then(|api| api.method_by_name(DRY_RUN_EXTRINSIC))
+        .map(|method| method.output_ty())
+        .ok_or_else(|| dry_run_api_error("method not found"))?;
+    let types = metadata.types();
+
+    // Find the type of the `Ok` variant of the result.
+    let cursor = &mut &*bytes;
+    let variant_index = u8::decode(cursor)?;
+    let variants = match types.resolve(output_ty).map(|t| &t.type_def) {
+        Some(scale_info::TypeDef::Variant(v)) => &v.variants,
+        _ => return Err(dry_run_api_error("output is not a Result")),
+    };
+    let ok_variant = variants
+        .iter()
+        .find(|v| v.name == "Ok")
+        .ok_or_else(|| dry_run_api_error("output is not a Result"))?;
+    if variant_index != ok_variant.index {
+        return Ok(None);
+    }
+    let effects_ty = ok_variant
+        .fields
+        .first()
+        .ok_or_else(|| dry_run_api_error("output is not a Result"))?
+        .ty
+        .id;
+
+    // Skip to the `emitted_events` field of the effects, and then find the type of each event.
+    let fields = match types.resolve(effects_ty).map(|t| &t.type_def) {
+        Some(scale_info::TypeDef::Composite(c)) => &c.fields,
+        _ => return Err(dry_run_api_error("effects are not a struct")),
+    };
+    let mut event_ty = None;
+    for field in fields {
+        if field.name.as_deref() == Some("emitted_events") {
+            event_ty = match types.resolve(field.ty.id).map(|t| &t.type_def) {
+                Some(scale_info::TypeDef::Sequence(s)) => Some(s.type_param.id),
+                _ => None,
+            };
+            break;
+        }
+        decode_with_visitor(cursor, field.ty.id, types, IgnoreVisitor)
+            .map_err(scale_decode::Error::from)?;
+    }
+    let event_ty = event_ty.ok_or_else(|| dry_run_api_error("no emitted_events found"))?;
+
+    // Turn each event into an event record, which is what `Events` expects.
+    let num_events = <Compact<u32>>::decode(cursor)?.0;
+    let mut event_records = Compact(num_events).encode();
+    for _ in 0..num_events {
+        let start = bytes.len() - cursor.len();
+        decode_with_visitor(cursor, event_ty, types, IgnoreVisitor)
+            .map_err(scale_decode::Error::from)?;
+        let end = bytes.len() - cursor.len();
+
+        Phase::ApplyExtrinsic(0).encode_to(&mut event_records);
+        event_records.extend_from_slice(&bytes[start..end]);
+        Vec::<T::Hash>::new().encode_to(&mut event_records);
+    }
+
+    let events = events::Events::new(metadata.clone(), block_hash, event_records);
+    Ok(Some(ExtrinsicEvents::new(ext_hash, 0, events)))
+}
+
+fn dry_run_api_error(reason: &str) -> Error {
+    Error::Other(format!(
+        "Cannot decode the result of {DRY_RUN_API}_{DRY_RUN_EXTRINSIC}: {reason}"
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::test_utils::mock_client_with_metadata;
+    use crate::tx::SubmittableExtrinsic;
+    use assert_matches::assert_matches;
+    use frame_metadata::v15::{
+        CustomMetadata, ExtrinsicMetadata, OuterEnums, PalletEventMetadata, PalletMetadata,
+        RuntimeApiMetadata, RuntimeApiMethodMetadata, RuntimeApiMethodParamMetadata,
+        RuntimeMetadataV15,
+    };
+    use frame_metadata::RuntimeMetadataPrefixed;
+    use scale_info::{meta_type, TypeInfo};
+
+    #[allow(unused)]
+    #[derive(Encode, TypeInfo)]
+    enum RuntimeEvent {
+        Test(Event),
+    }
+
+    #[allow(unused)]
+    #[derive(Encode, TypeInfo)]
+    enum Event {
+        Happened { value: u32 },
+    }
+
+    // Roughly the shape of the `ExtrinsicDryRunEffects` returned from the `DryRunApi`.
+    #[derive(Encode, TypeInfo)]
+    struct ExtrinsicDryRunEffects {
+        execution_result: Result<(), Vec<u8>>,
+        emitted_events: Vec<RuntimeEvent>,
+        forwarded_xcms: Vec<u8>,
+    }
+
+    #[allow(unused)]
+    #[derive(Encode, TypeInfo)]
+    enum DryRunError {
+        Unimplemented,
+    }
+
+    /// Build metadata with an event and, optionally, the `DryRunApi`.
+    fn metadata(with_dry_run_api: bool) -> Metadata {
+        let pallets = vec![PalletMetadata {
+            name: "Test",
+            storage: None,
+            calls: None,
+            event: Some(PalletEventMetadata {
+                ty: meta_type::<Event>(),
+            }),
+            constants: vec![],
+            error: None,
+            index: 0,
+            docs: vec![],
+        }];
+
+        let extrinsic = ExtrinsicMetadata {
+            version: 4,
+            signed_extensions: vec![],
+            address_ty: meta_type::<()>(),
+            call_ty: meta_type::<()>(),
+            signature_ty: meta_type::<()>(),
+            extra_ty: meta_type::<()>(),
+        };
+
+        let apis = if with_dry_run_api {
+            vec![RuntimeApiMetadata {
+                name: DRY_RUN_API,
+                methods: vec![RuntimeApiMethodMetadata {
+                    name: DRY_RUN_EXTRINSIC,
+                    inputs: vec![RuntimeApiMethodParamMetadata {
+                        name: "extrinsic",
+                        ty: meta_type::<Vec<u8>>(),
+                    }],
+                    output: meta_type::<Result<ExtrinsicDryRunEffects, DryRunError>>(),
+                    docs: vec![],
+                }],
+                docs: vec![],
+            }]
+        } else {
+            vec![]
+        };
+
+        let meta = RuntimeMetadataV15::new(
+            pallets,
+            extrinsic,
+            meta_type::<()>(),
+            apis,
+            OuterEnums {
+                call_enum_ty: meta_type::<()>(),
+                event_enum_ty: meta_type::<RuntimeEvent>(),
+                error_enum_ty: meta_type::<()>(),
+            },
+            CustomMetadata {
+                map: Default::default(),
+            },
+        );
+        let runtime_metadata: RuntimeMetadataPrefixed = meta.into();
+        Metadata::new(runtime_metadata.try_into().unwrap())
+    }
+
+    #[test]
+    fn apply_extrinsic_results_are_decoded() {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_full.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*bytes).unwrap();
+        let decode = |bytes: &[u8]| DryRunOutcome::decode_from(bytes, metadata.clone());
+
+        assert_matches!(decode(&[0, 0]), Ok(DryRunOutcome::Success));
+        assert_matches!(
+            decode(&[0, 1, 2]),
+            Ok(DryRunOutcome::DispatchError(DispatchError::BadOrigin))
+        );
+        assert_matches!(
+            decode(&[1, 0, 4]),
+            Ok(DryRunOutcome::Invalid(TransactionInvalid::BadProof))
+        );
+        assert_matches!(
+            decode(&[1, 1, 2, 7]),
+            Ok(DryRunOutcome::Unknown(TransactionUnknown::Custom(7)))
+        );
+        assert_matches!(decode(&[2]), Err(Error::Unknown(_)));
+
+        // Validity errors which can't be decoded are still validity errors:
+        assert_matches!(
+            decode(&[1, 0]),
+            Ok(DryRunOutcome::UndecodedValidityError(b)) if b == [0]
+        );
+        assert_matches!(
+            decode(&[1, 2, 3]),
+            Ok(DryRunOutcome::UndecodedValidityError(b)) if b == [2, 3]
+        );
+        assert_matches!(decode(&[1]), Ok(DryRunOutcome::UndecodedValidityError(b)) if b.is_empty());
+
+        // Module errors are decoded using the metadata:
+        let balances = metadata.pallet_by_name("Balances").unwrap();
+        let bytes = [&[0, 1, 3, balances.index(), 2][..], &[0; 3]].concat();
+        let Ok(DryRunOutcome::DispatchError(DispatchError::Module(err))) = decode(&bytes) else {
+            panic!("expected a module error");
+        };
+        let details = err.details().unwrap();
+        assert_eq!(details.pallet.name(), "Balances");
+        assert_eq!(details.variant.name, "InsufficientBalance");
+    }
+
+    #[test]
+    fn legacy_dry_run_results_are_validity_errors_if_undecodable() {
+        use crate::backend::legacy::rpc_methods::{DryRunResult, DryRunResultBytes};
+
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_full.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*bytes).unwrap();
+        let decode =
+            |bytes: &[u8]| DryRunResultBytes(bytes.to_vec()).into_dry_run_result(&metadata);
+
+        // A truncated `TransactionInvalid`, and a `TransactionValidityError` variant
+        // that we don't know about:
+        for bytes in [&[1, 0][..], &[1, 9, 1, 2]] {
+            assert_matches!(decode(bytes), Ok(DryRunResult::TransactionValidityError));
+        }
+    }
+
+    #[tokio::test]
+    async fn dry_run_without_dry_run_api_has_no_events() {
+        let (backend, api) = mock_client_with_metadata(metadata(false)).await;
+        backend.set_call_response("BlockBuilder_apply_extrinsic", vec![0u8, 0]);
+
+        let tx = SubmittableExtrinsic::from_bytes(api, vec![4, 1, 2, 3, 4]);
+        let result = tx.dry_run().await.unwrap();
+        assert!(result.is_success());
+        assert!(result.events().is_none());
+    }
+
+    #[tokio::test]
+    async fn dry_run_with_dry_run_api_has_events() {
+        let (backend, api) = mock_client_with_metadata(metadata(true)).await;
+        let tx_bytes = vec![4, 1, 2, 3, 4];
+        backend.set_call_response_for_params(
+            "BlockBuilder_apply_extrinsic",
+            tx_bytes.clone(),
+            vec![1u8, 0, 1],
+        );
+        let effects: Result<_, DryRunError> = Ok(ExtrinsicDryRunEffects {
+            execution_result: Err(vec![1, 2, 3]),
+            emitted_events: vec![
+                RuntimeEvent::Test(Event::Happened { value: 1 }),
+                RuntimeEvent::Test(Event::Happened { value: 2 }),
+            ],
+            forwarded_xcms: vec![],
+        });
+        backend.set_call_response_for_params(
+            "DryRunApi_dry_run_extrinsic",
+            tx_bytes.clone(),
+            effects.encode(),
+        );
+
+        let tx = SubmittableExtrinsic::from_bytes(api, tx_bytes);
+        let result = tx.dry_run().await.unwrap();
+        assert_eq!(
+            result.outcome(),
+            &DryRunOutcome::Invalid(TransactionInvalid::Payment)
+        );
+
+        let events = result.events().unwrap();
+        assert_eq!(events.extrinsic_hash(), tx.hash());
+        let values: Vec<_> = events
+            .iter()
+            .map(|ev| {
+                let ev = ev.unwrap();
+                assert_eq!(ev.variant_name(), "Happened");
+                ev.field_bytes().to_vec()
+            })
+            .collect();
+        assert_eq!(values, vec![1u32.encode(), 2u32.encode()]);
+
+        // If the runtime doesn't implement the API then we get no events back:
+        let effects: Result<ExtrinsicDryRunEffects, _> = Err(DryRunError::Unimplemented);
+        backend.set_call_response_for_params(
+            "DryRunApi_dry_run_extrinsic",
+            tx.encoded().to_vec(),
+            effects.encode(),
+        );
+        assert!(tx.dry_run().await.unwrap().events().is_none());
+    }
+}
@@ -0,0 +1,164 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Types returned from the `TransactionPaymentApi` and `TransactionPaymentCallApi` runtime
+//! APIs, which are used to estimate the fees that an extrinsic or call will incur.
+
+use crate::utils::Weight;
+use codec::{Decode, Encode};
+
+/// The class of a call, which determines how it's treated when blocks are built.
+#[derive(Encode, Decode, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DispatchClass {
+    /// A normal call.
+    Normal,
+    /// An operational call, which can use some block space reserved for such calls.
+    Operational,
+    /// A mandatory call, which is always included in a block regardless of its weight.
+    /// Inherents are typically mandatory.
+    Mandatory,
+}
+
+/// Information about an extrinsic or call, as returned from `TransactionPaymentApi_query_info`
+/// and `TransactionPaymentCallApi_query_call_info`.
+#[derive(Encode, Decode, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RuntimeDispatchInfo {
+    /// The weight of the call.
+    pub weight: Weight,
+    /// The class of the call.
+    pub class: DispatchClass,
+    /// The inclusion fee of the call. This doesn't include any tip, and so is only
+    /// a part of the fee that will be paid.
+    pub partial_fee: u128,
+}
+
+/// A breakdown of the fee that an extrinsic or call will incur, as returned from
+/// `TransactionPaymentApi_query_fee_details` and `TransactionPaymentCallApi_query_call_fee_details`.
+#[derive(Encode, Decode, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeDetails {
+    /// The fee for including the extrinsic in a block. This is `None` for unsigned
+    /// extrinsics, which don't pay fees.
+    pub inclusion_fee: Option<InclusionFee>,
+    /// The tip paid to the block author.
+    pub tip: u128,
+}
+
+impl FeeDetails {
+    /// The final fee that will be paid; the sum of the inclusion fee and the tip.
+    pub fn final_fee(&self) -> u128 {
+        self.inclusion_fee
+            .map(|fee| fee.inclusion_fee())
+            .unwrap_or(0)
+            .saturating_add(self.tip)
+    }
+}
+
+/// A breakdown of the fee for including an extrinsic or call in a block.
+#[derive(Encode, Decode, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InclusionFee {
+    /// The minimum fee paid by every extrinsic.
+    pub base_fee: u128,
+    /// The fee paid based on the length of the extrinsic in bytes.
+    pub len_fee: u128,
+    /// The fee paid based on the weight of the call, adjusted to account for how busy
+    /// the chain is.
+    pub adjusted_weight_fee: u128,
+}
+
+impl InclusionFee {
+    /// The total inclusion fee; the sum of the base, length and adjusted weight fees.
+    pub fn inclusion_fee(&self) -> u128 {
+        self.base_fee
+            .saturating_add(self.len_fee)
+            .saturating_add(self.adjusted_weight_fee)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::test_utils::mock_client;
+    use codec::Compact;
+
+    // The types as they are encoded by the runtime:
+    fn dispatch_info_bytes() -> Vec<u8> {
+        (Compact(1_000u64), Compact(2_000u64), 1u8, 300u128).encode()
+    }
+    fn fee_details_bytes() -> Vec<u8> {
+        (Some((100u128, 20u128, 180u128)), 5u128).encode()
+    }
+
+    #[tokio::test]
+    async fn fees_are_estimated_for_extrinsics() {
+        let (backend, api) = mock_client("polkadot_metadata_full.scale").await;
+        backend.set_call_response("TransactionPaymentApi_query_info", dispatch_info_bytes());
+        backend.set_call_response(
+            "TransactionPaymentApi_query_fee_details",
+            fee_details_bytes(),
+        );
+
+        let call = crate::dynamic::tx("System", "remark", vec![vec![1u8, 2, 3]]);
+        let tx = api.tx().create_unsigned(&call).unwrap();
+
+        let info = tx.dispatch_info_estimate().await.unwrap();
+        assert_eq!(
+            info,
+            RuntimeDispatchInfo {
+                weight: Weight::from_parts(1_000, 2_000),
+                class: DispatchClass::Operational,
+                partial_fee: 300,
+            }
+        );
+        assert_eq!(tx.partial_fee_estimate().await.unwrap(), 300);
+
+        let details = tx.fee_details_estimate().await.unwrap();
+        assert_eq!(
+            details,
+            FeeDetails {
+                inclusion_fee: Some(InclusionFee {
+                    base_fee: 100,
+                    len_fee: 20,
+                    adjusted_weight_fee: 180,
+                }),
+                tip: 5,
+            }
+        );
+        assert_eq!(details.final_fee(), 305);
+    }
+
+    #[tokio::test]
+    async fn fees_are_estimated_for_calls() {
+        let (backend, api) = mock_client("polkadot_metadata_full.scale").await;
+        let call = crate::dynamic::tx("System", "remark", vec![vec![1u8, 2, 3]]);
+
+        // The call data is followed by its length:
+        let mut params = api.tx().call_data(&call).unwrap();
+        (params.len() as u32).encode_to(&mut params);
+        backend.set_call_response_for_params(
+            "TransactionPaymentCallApi_query_call_info",
+            params.clone(),
+            dispatch_info_bytes(),
+        );
+        backend.set_call_response_for_params(
+            "TransactionPaymentCallApi_query_call_fee_details",
+            params,
+            fee_details_bytes(),
+        );
+
+        let info = api.tx().call_dispatch_info_estimate(&call).await.unwrap();
+        assert_eq!(info.partial_fee, 300);
+        assert_eq!(info.class, DispatchClass::Operational);
+
+        let details = api.tx().call_fee_details_estimate(&call).await.unwrap();
+        assert_eq!(details.inclusion_fee.unwrap().inclusion_fee(), 300);
+
+        // No response was given for any other call:
+        let other_call = crate::dynamic::tx("System", "remark", vec![vec![4u8]]);
+        assert!(api
+            .tx()
+            .call_dispatch_info_estimate(&other_call)
+            .await
+            .is_err());
+    }
+}
@@ -9,6 +9,14 @@
 //! additional and signed extra parameters are used when constructing an extrinsic, and is a part
 //! of the chain configuration (see [`crate::config::Config`]).
 
+mod batch;
+mod dry_run;
+mod fees;
+mod multisig;
+mod nonce_manager;
+mod offline_signing;
+mod proxy;
+mod resilient_submitter;
 mod signer;
 mod tx_client;
 mod tx_payload;
@@ -20,7 +28,15 @@ mod tx_progress;
 pub use self::signer::PairSigner;
 
 pub use self::{
-    signer::Signer,
+    batch::{BatchKind, BatchPayload},
+    dry_run::{DryRunOutcome, DryRunReport},
+    fees::{DispatchClass, FeeDetails, InclusionFee, RuntimeDispatchInfo},
+    multisig::{Multisig, MultisigDetails, Timepoint},
+    nonce_manager::NonceManager,
+    offline_signing::{DecodedCall, SignedExtensionDetails, SigningRequest, SigningResponse},
+    proxy::{pure_proxy_account_id, ProxyPayload},
+    resilient_submitter::{Attempt, ResilientSubmitter, SubmissionOutcome, TipPolicy},
+    signer::{AsyncSigner, Signer},
     tx_client::{
         PartialExtrinsic, SubmittableExtrinsic, TransactionInvalid, TransactionUnknown, TxClient,
         ValidationResult,
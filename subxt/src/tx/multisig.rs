@@ -0,0 +1,514 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Work with multisig accounts, via the `Multisig` pallet.
+
+use super::tx_payload::call_to_outer_enum_value;
+use crate::{
+    client::{OfflineClientT, OnlineClientT},
+    dynamic::Value,
+    error::Error,
+    tx::{DynamicPayload, TxPayload},
+    utils::{account_id_from_entropy, Encoded, Static, Weight},
+    Config,
+};
+use codec::{Decode, Encode};
+use derivative::Derivative;
+use scale_value::Composite;
+use sp_core_hashing::blake2_256;
+
+/// The block number and extrinsic index at which a multisig operation was first approved.
+/// Every approval after the first must provide this to identify the operation.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Encode,
+    Decode,
+    scale_encode::EncodeAsType,
+    scale_decode::DecodeAsType,
+)]
+pub struct Timepoint {
+    /// The block number at which the operation was first approved.
+    pub height: u32,
+    /// The index of the approving extrinsic in that block.
+    pub index: u32,
+}
+
+impl Timepoint {
+    /// Create a new [`Timepoint`].
+    pub fn new(height: u32, index: u32) -> Self {
+        Timepoint { height, index }
+    }
+}
+
+/// Details about a multisig operation which is waiting for approvals.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Debug(bound = ""))]
+pub struct MultisigDetails<T: Config> {
+    /// When the operation was first approved.
+    pub when: Timepoint,
+    /// The amount reserved from the depositor to store the operation.
+    pub deposit: u128,
+    /// The account which made the deposit (ie the first to approve the operation).
+    pub depositor: T::AccountId,
+    /// The signatories which have approved the operation so far.
+    pub approvals: Vec<T::AccountId>,
+}
+
+/// A helper for working with a multisig account made up of some signatories and a threshold
+/// of them which must approve an operation for it to be dispatched.
+///
+/// This can derive the multisig account id, build the `Multisig` pallet calls needed to
+/// approve, execute and cancel operations which wrap any other [`TxPayload`], look up pending
+/// operations, and estimate the weight of a call to execute.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use subxt::dynamic::Value;
+/// use subxt::tx::Multisig;
+/// use subxt::{OnlineClient, PolkadotConfig};
+/// use subxt_signer::sr25519::dev;
+///
+/// let api = OnlineClient::<PolkadotConfig>::new().await?;
+///
+/// let signatories = [dev::alice(), dev::bob(), dev::charlie()].map(|k| k.public_key().into());
+/// let multisig = Multisig::new(api.clone(), signatories, 2);
+/// let call = subxt::dynamic::tx("System", "remark", vec![Value::from_bytes("Hello")]);
+///
+/// // Alice kicks things off by approving the call hash:
+/// let alice = dev::alice();
+/// let approve = multisig.approve_as_multi(
+///     &alice.public_key().into(),
+///     multisig.call_hash(&call)?,
+///     None,
+///     Default::default(),
+/// )?;
+/// api.tx()
+///     .sign_and_submit_then_watch_default(&approve, &alice)
+///     .await?
+///     .wait_for_finalized_success()
+///     .await?;
+///
+/// // Bob then provides the call, which is executed since the threshold is reached:
+/// let bob = dev::bob();
+/// let pending = multisig.pending(multisig.call_hash(&call)?).await?.unwrap();
+/// let max_weight = multisig.estimate_weight(&call).await?;
+/// let execute = multisig.as_multi(&bob.public_key().into(), &call, Some(pending.when), max_weight)?;
+/// api.tx()
+///     .sign_and_submit_then_watch_default(&execute, &bob)
+///     .await?
+///     .wait_for_finalized_success()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Derivative)]
+#[derivative(Clone(bound = "C: Clone"), Debug(bound = "C: std::fmt::Debug"))]
+pub struct Multisig<T: Config, C> {
+    client: C,
+    // Sorted by their encoded bytes, as the pallet expects.
+    signatories: Vec<T::AccountId>,
+    threshold: u16,
+}
+
+impl<T: Config, C> Multisig<T, C> {
+    /// Create a new [`Multisig`] from the accounts which make it up and the number of them
+    /// which must approve an operation. The order of signatories does not matter, and any
+    /// duplicates are ignored.
+    pub fn new(
+        client: C,
+        signatories: impl IntoIterator<Item = T::AccountId>,
+        threshold: u16,
+    ) -> Self {
+        let mut signatories: Vec<_> = signatories.into_iter().collect();
+        signatories.sort_by_cached_key(|s| s.encode());
+        signatories.dedup_by_key(|s| s.encode());
+        Multisig {
+            client,
+            signatories,
+            threshold,
+        }
+    }
+
+    /// The signatories of this multisig, in the order that the pallet expects them.
+    pub fn signatories(&self) -> &[T::AccountId] {
+        &self.signatories
+    }
+
+    /// The number of signatories which must approve an operation.
+    pub fn threshold(&self) -> u16 {
+        self.threshold
+    }
+
+    /// The signatories other than the one given, which is what the `Multisig` calls expect
+    /// to be given. Returns an error if the signer is not a signatory of this multisig.
+    pub fn other_signatories(&self, signer: &T::AccountId) -> Result<Vec<T::AccountId>, Error> {
+        let signer = signer.encode();
+        if !self.signatories.iter().any(|s| s.encode() == signer) {
+            return Err(Error::Other(
+                "The signer is not one of the multisig signatories".into(),
+            ));
+        }
+        Ok(self
+            .signatories
+            .iter()
+            .filter(|s| s.encode() != signer)
+            .cloned()
+            .collect())
+    }
+
+    // The other signatories in the form we need to hand them to the calls.
+    fn other_signatories_value(&self, signer: &T::AccountId) -> Result<Value, Error> {
+        let other_signatories = self
+            .other_signatories(signer)?
+            .into_iter()
+            .map(|s| Value::from_bytes(s.encode()));
+        Ok(Value::unnamed_composite(other_signatories))
+    }
+}
+
+impl<T: Config, C> Multisig<T, C>
+where
+    T::AccountId: Decode,
+{
+    /// The account id of this multisig. This is derived in the same way that the `Multisig`
+    /// pallet derives it, from the signatories and threshold.
+    pub fn account_id(&self) -> Result<T::AccountId, Error> {
+        let entropy =
+            (b"modlpy/utilisuba", &self.signatories, self.threshold).using_encoded(blake2_256);
+        Ok(account_id_from_entropy(&entropy)?)
+    }
+}
+
+impl<T: Config, C: OfflineClientT<T>> Multisig<T, C> {
+    /// The hash of some call, which identifies a multisig operation to execute that call.
+    pub fn call_hash<Call: TxPayload>(&self, call: &Call) -> Result<[u8; 32], Error> {
+        let call_data = call.encode_call_data(&self.client.metadata())?;
+        Ok(blake2_256(&call_data))
+    }
+
+    /// Approve the given call, executing it if this is the final approval needed.
+    ///
+    /// `maybe_timepoint` should be `None` if this is the first approval, and otherwise
+    /// [`MultisigDetails::when`] of the pending operation. If this executes the call, then
+    /// `max_weight` must be at least the weight of the call (see [`Multisig::estimate_weight()`]).
+    pub fn as_multi<Call: TxPayload>(
+        &self,
+        signer: &T::AccountId,
+        call: &Call,
+        maybe_timepoint: Option<Timepoint>,
+        max_weight: Weight,
+    ) -> Result<DynamicPayload, Error> {
+        let args = Composite::named([
+            ("threshold", Value::u128(self.threshold as u128)),
+            ("other_signatories", self.other_signatories_value(signer)?),
+            (
+                "maybe_timepoint",
+                option_value(maybe_timepoint.map(Into::into)),
+            ),
+            (
+                "call",
+                call_to_outer_enum_value(call, &self.client.metadata())?,
+            ),
+            ("max_weight", max_weight.into()),
+        ]);
+        Ok(crate::dynamic::tx("Multisig", "as_multi", args))
+    }
+
+    /// Approve the call with the given hash without providing the call itself. This can't
+    /// be used to give the final approval needed; use [`Multisig::as_multi()`] for that.
+    ///
+    /// `maybe_timepoint` should be `None` if this is the first approval, and otherwise
+    /// [`MultisigDetails::when`] of the pending operation.
+    pub fn approve_as_multi(
+        &self,
+        signer: &T::AccountId,
+        call_hash: [u8; 32],
+        maybe_timepoint: Option<Timepoint>,
+        max_weight: Weight,
+    ) -> Result<DynamicPayload, Error> {
+        let args = Composite::named([
+            ("threshold", Value::u128(self.threshold as u128)),
+            ("other_signatories", self.other_signatories_value(signer)?),
+            (
+                "maybe_timepoint",
+                option_value(maybe_timepoint.map(Into::into)),
+            ),
+            ("call_hash", Value::from_bytes(call_hash)),
+            ("max_weight", max_weight.into()),
+        ]);
+        Ok(crate::dynamic::tx("Multisig", "approve_as_multi", args))
+    }
+
+    /// Cancel the pending operation with the given timepoint and call hash. Only the
+    /// depositor of the operation can cancel it.
+    pub fn cancel_as_multi(
+        &self,
+        signer: &T::AccountId,
+        timepoint: Timepoint,
+        call_hash: [u8; 32],
+    ) -> Result<DynamicPayload, Error> {
+        let args = Composite::named([
+            ("threshold", Value::u128(self.threshold as u128)),
+            ("other_signatories", self.other_signatories_value(signer)?),
+            ("timepoint", timepoint.into()),
+            ("call_hash", Value::from_bytes(call_hash)),
+        ]);
+        Ok(crate::dynamic::tx("Multisig", "cancel_as_multi", args))
+    }
+
+    /// Immediately execute the given call from a multisig with a threshold of 1.
+    pub fn as_multi_threshold_1<Call: TxPayload>(
+        &self,
+        signer: &T::AccountId,
+        call: &Call,
+    ) -> Result<DynamicPayload, Error> {
+        let args = Composite::named([
+            ("other_signatories", self.other_signatories_value(signer)?),
+            (
+                "call",
+                call_to_outer_enum_value(call, &self.client.metadata())?,
+            ),
+        ]);
+        Ok(crate::dynamic::tx("Multisig", "as_multi_threshold_1", args))
+    }
+}
+
+impl<T: Config, C: OnlineClientT<T>> Multisig<T, C>
+where
+    T::AccountId: Decode,
+{
+    /// Look up the pending operation for the call with the given hash at the latest block,
+    /// returning `None` if there isn't one.
+    pub async fn pending(&self, call_hash: [u8; 32]) -> Result<Option<MultisigDetails<T>>, Error> {
+        #[derive(scale_decode::DecodeAsType)]
+        #[decode_as_type(trait_bounds = "AccountId: Decode")]
+        struct PendingMultisig<AccountId> {
+            when: Timepoint,
+            deposit: u128,
+            depositor: Static<AccountId>,
+            approvals: Vec<Static<AccountId>>,
+        }
+
+        let address = crate::dynamic::storage(
+            "Multisig",
+            "Multisigs",
+            vec![
+                Static(Encoded(self.account_id()?.encode())),
+                Static(Encoded(call_hash.to_vec())),
+            ],
+        );
+        let Some(pending) = self
+            .client
+            .storage()
+            .at_latest()
+            .await?
+            .fetch(&address)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let pending: PendingMultisig<T::AccountId> = pending.as_type()?;
+        Ok(Some(MultisigDetails {
+            when: pending.when,
+            deposit: pending.deposit,
+            depositor: pending.depositor.0,
+            approvals: pending.approvals.into_iter().map(|a| a.0).collect(),
+        }))
+    }
+}
+
+impl<T: Config, C: OnlineClientT<T>> Multisig<T, C> {
+    /// Estimate the weight of some call at the latest block, using
+    /// [`crate::tx::TxClient::call_dispatch_info_estimate()`]. This can be handed to
+    /// [`Multisig::as_multi()`] as the `max_weight` needed to execute the call.
+    pub async fn estimate_weight<Call: TxPayload>(&self, call: &Call) -> Result<Weight, Error> {
+        let info = self.client.tx().call_dispatch_info_estimate(call).await?;
+        Ok(info.weight)
+    }
+}
+
+impl From<Timepoint> for Value {
+    fn from(timepoint: Timepoint) -> Self {
+        Value::named_composite([
+            ("height", Value::u128(timepoint.height as u128)),
+            ("index", Value::u128(timepoint.index as u128)),
+        ])
+    }
+}
+
+fn option_value(value: Option<Value>) -> Value {
+    match value {
+        Some(value) => Value::unnamed_variant("Some", [value]),
+        None => Value::unnamed_variant("None", []),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::mock::MockBackend;
+    use crate::backend::test_utils::{first_block_hash, mock_client};
+    use crate::utils::AccountId32;
+    use crate::{OnlineClient, PolkadotConfig};
+    use codec::Compact;
+
+    async fn client() -> (MockBackend<PolkadotConfig>, OnlineClient<PolkadotConfig>) {
+        mock_client("polkadot_metadata_full.scale").await
+    }
+
+    fn accounts() -> [AccountId32; 3] {
+        [
+            AccountId32([3; 32]),
+            AccountId32([1; 32]),
+            AccountId32([2; 32]),
+        ]
+    }
+
+    fn remark() -> crate::tx::DynamicPayload {
+        crate::dynamic::tx("System", "remark", vec![Value::from_bytes("Hello")])
+    }
+
+    #[tokio::test]
+    async fn account_id_is_derived_from_sorted_signatories() {
+        let (_, api) = client().await;
+        let [a, b, c] = accounts();
+
+        let multisig: Multisig<PolkadotConfig, _> =
+            Multisig::new(api.clone(), [a.clone(), b.clone(), c.clone()], 2);
+        let reordered: Multisig<PolkadotConfig, _> =
+            Multisig::new(api.clone(), [c.clone(), b.clone(), a.clone(), b], 2);
+        assert_eq!(multisig.signatories(), reordered.signatories());
+        assert_eq!(
+            multisig.account_id().unwrap(),
+            reordered.account_id().unwrap()
+        );
+
+        let sorted = vec![
+            AccountId32([1; 32]),
+            AccountId32([2; 32]),
+            AccountId32([3; 32]),
+        ];
+        let expected = AccountId32(blake2_256(&(b"modlpy/utilisuba", sorted, 2u16).encode()));
+        assert_eq!(multisig.account_id().unwrap(), expected);
+
+        // The threshold is a part of the account id:
+        let multisig_3: Multisig<PolkadotConfig, _> = Multisig::new(api, [a, c], 3);
+        assert_ne!(
+            multisig.account_id().unwrap(),
+            multisig_3.account_id().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn builds_multisig_calls() {
+        let (_, api) = client().await;
+        let metadata = api.metadata();
+        let multisig = Multisig::new(api, accounts(), 2);
+        let signer = AccountId32([2; 32]);
+
+        let pallet = metadata.pallet_by_name("Multisig").unwrap();
+        let call_index = |name| pallet.call_variant_by_name(name).unwrap().index;
+        let mut other_signatories = Compact(2u32).encode();
+        other_signatories.extend([1u8; 32]);
+        other_signatories.extend([3u8; 32]);
+        let remark_data = remark().encode_call_data(&metadata).unwrap();
+        let timepoint = Timepoint::new(10, 2);
+        let max_weight = Weight::from_parts(1000, 100);
+
+        let as_multi = multisig
+            .as_multi(&signer, &remark(), Some(timepoint), max_weight)
+            .unwrap();
+        let mut expected = vec![pallet.index(), call_index("as_multi")];
+        2u16.encode_to(&mut expected);
+        expected.extend(&other_signatories);
+        Some(timepoint).encode_to(&mut expected);
+        expected.extend(&remark_data);
+        max_weight.encode_to(&mut expected);
+        assert_eq!(as_multi.encode_call_data(&metadata).unwrap(), expected);
+
+        let call_hash = multisig.call_hash(&remark()).unwrap();
+        assert_eq!(call_hash, blake2_256(&remark_data));
+        let approve = multisig
+            .approve_as_multi(&signer, call_hash, None, max_weight)
+            .unwrap();
+        let mut expected = vec![pallet.index(), call_index("approve_as_multi")];
+        2u16.encode_to(&mut expected);
+        expected.extend(&other_signatories);
+        None::<Timepoint>.encode_to(&mut expected);
+        expected.extend(call_hash);
+        max_weight.encode_to(&mut expected);
+        assert_eq!(approve.encode_call_data(&metadata).unwrap(), expected);
+
+        let cancel = multisig
+            .cancel_as_multi(&signer, timepoint, call_hash)
+            .unwrap();
+        let mut expected = vec![pallet.index(), call_index("cancel_as_multi")];
+        2u16.encode_to(&mut expected);
+        expected.extend(&other_signatories);
+        timepoint.encode_to(&mut expected);
+        expected.extend(call_hash);
+        assert_eq!(cancel.encode_call_data(&metadata).unwrap(), expected);
+
+        // Only signatories can build these calls:
+        assert!(multisig
+            .cancel_as_multi(&AccountId32([4; 32]), timepoint, call_hash)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn looks_up_pending_operations() {
+        let (backend, api) = client().await;
+        let multisig = Multisig::new(api.clone(), accounts(), 2);
+        let call_hash = multisig.call_hash(&remark()).unwrap();
+
+        assert!(multisig.pending(call_hash).await.unwrap().is_none());
+
+        let address = crate::dynamic::storage(
+            "Multisig",
+            "Multisigs",
+            vec![
+                Value::from_bytes(multisig.account_id().unwrap()),
+                Value::from_bytes(call_hash),
+            ],
+        );
+        let key = api.storage().address_bytes(&address).unwrap();
+        let value = (
+            Timepoint::new(10, 2),
+            500u128,
+            AccountId32([1; 32]),
+            vec![AccountId32([1; 32])],
+        );
+        backend.set_storage(first_block_hash(), key, value.encode());
+
+        let pending = multisig.pending(call_hash).await.unwrap().unwrap();
+        assert_eq!(pending.when, Timepoint::new(10, 2));
+        assert_eq!(pending.deposit, 500);
+        assert_eq!(pending.depositor, AccountId32([1; 32]));
+        assert_eq!(pending.approvals, vec![AccountId32([1; 32])]);
+    }
+
+    #[tokio::test]
+    async fn estimates_call_weight() {
+        let (backend, api) = client().await;
+        let multisig = Multisig::new(api, accounts(), 2);
+
+        // A `RuntimeDispatchInfo`; the weight, dispatch class and partial fee.
+        let call_info = (Weight::from_parts(1234, 56), 0u8, 1_000u128);
+        backend.set_call_response(
+            "TransactionPaymentCallApi_query_call_info",
+            call_info.encode(),
+        );
+
+        let weight = multisig.estimate_weight(&remark()).await.unwrap();
+        assert_eq!(weight, Weight::from_parts(1234, 56));
+    }
+}
@@ -0,0 +1,524 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use crate::{
+    backend::{legacy::LegacyRpcMethods, rpc::RpcClient},
+    client::OnlineClientT,
+    config::{Config, ExtrinsicParams},
+    error::{Error, RpcError},
+    tx::{
+        AsyncSigner as AsyncSignerT, Signer as SignerT, SubmittableExtrinsic, TransactionInvalid,
+        TxPayload, TxProgress,
+    },
+};
+use codec::Encode;
+use derivative::Derivative;
+use futures::lock::Mutex as AsyncMutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// The error code that Substrate based nodes hand back from `author_submitExtrinsic` and
+/// friends when the transaction pool deems a transaction to be invalid.
+#[cfg(feature = "jsonrpsee")]
+const POOL_INVALID_TX: i32 = 1010;
+
+/// The reasons that Substrate based nodes (as of Polkadot v1.0.0) give when rejecting
+/// a transaction because its nonce is too low ([`TransactionInvalid::Stale`]) or too high
+/// ([`TransactionInvalid::Future`]). These are the `&'static str` representations of the
+/// corresponding `InvalidTransaction` variants in `sp-runtime`.
+const STALE_MESSAGE: &str = "Transaction is outdated";
+const FUTURE_MESSAGE: &str = "Transaction will be valid in the future";
+
+/// Hands out account nonces locally, so that many transactions can be created from the
+/// same account in quick succession without them being given the same nonce.
+///
+/// The first time a nonce is needed for some account, it's fetched from the node using
+/// the `system_accountNextIndex` RPC method, which takes into account any transactions
+/// from the account that are already in the node's transaction pool. After that, each
+/// nonce handed out is one more than the last. If the node rejects a transaction because
+/// its nonce is stale or too far in the future, the nonce for that account is forgotten
+/// and fetched from the node again the next time it's needed. This happens automatically
+/// for transactions submitted via the [`NonceManager`], and can be done manually with
+/// [`NonceManager::resync`] or [`NonceManager::handle_invalid`] otherwise.
+///
+/// This is cheap to clone, and clones share the same nonces, so it can be handed to each
+/// task that is submitting transactions.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use subxt::backend::rpc::RpcClient;
+/// use subxt::tx::NonceManager;
+/// use subxt::{OnlineClient, PolkadotConfig};
+/// use subxt_signer::sr25519::dev;
+///
+/// let rpc_client = RpcClient::from_url("ws://127.0.0.1:9944").await.unwrap();
+/// let api = OnlineClient::<PolkadotConfig>::from_rpc_client(rpc_client.clone())
+///     .await
+///     .unwrap();
+/// let nonce_manager = NonceManager::new(api, rpc_client);
+///
+/// let remarks = (0u8..100).map(|n| {
+///     let nonce_manager = nonce_manager.clone();
+///     async move {
+///         let call = subxt::dynamic::tx("System", "remark", vec![vec![n]]);
+///         nonce_manager.sign_and_submit_default(&call, &dev::alice()).await
+///     }
+/// });
+/// let hashes = futures::future::join_all(remarks).await;
+/// # }
+/// ```
+#[derive(Derivative)]
+#[derivative(Clone(bound = "C: Clone"))]
+pub struct NonceManager<T: Config, C> {
+    client: C,
+    rpc_methods: LegacyRpcMethods<T>,
+    // Keyed by the SCALE encoded account ID. Each account has its own lock, so that
+    // only one nonce is fetched from the node at a time for each account.
+    nonces: Arc<Mutex<HashMap<Vec<u8>, Arc<AsyncMutex<Option<u64>>>>>>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Config, C> std::fmt::Debug for NonceManager<T, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NonceManager")
+            .field("accounts", &self.nonces.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl<T, C> NonceManager<T, C>
+where
+    T: Config,
+    T::AccountId: Serialize,
+    C: OnlineClientT<T>,
+{
+    /// Create a new [`NonceManager`], which creates and submits transactions using the
+    /// given client, and fetches nonces from the node using the given RPC client.
+    pub fn new(client: C, rpc_client: RpcClient) -> Self {
+        NonceManager {
+            client,
+            rpc_methods: LegacyRpcMethods::new(rpc_client),
+            nonces: Default::default(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    fn account_nonce_lock(&self, account_id: &T::AccountId) -> Arc<AsyncMutex<Option<u64>>> {
+        self.nonces
+            .lock()
+            .unwrap()
+            .entry(account_id.encode())
+            .or_default()
+            .clone()
+    }
+
+    /// Return the nonce to use for the next transaction from the given account, fetching
+    /// it from the node if we don't know it yet.
+    pub async fn next_nonce(&self, account_id: &T::AccountId) -> Result<u64, Error> {
+        let lock = self.account_nonce_lock(account_id);
+        let mut next_nonce = lock.lock().await;
+
+        let nonce = match *next_nonce {
+            Some(nonce) => nonce,
+            None => {
+                self.rpc_methods
+                    .system_account_next_index(account_id)
+                    .await?
+            }
+        };
+        *next_nonce = Some(nonce + 1);
+        Ok(nonce)
+    }
+
+    /// Hand back a nonce obtained from [`NonceManager::next_nonce`] which was not used, so
+    /// that it's handed out again. If later nonces have been handed out in the meantime,
+    /// the nonce for the account is resynced instead, since there would otherwise be a gap.
+    async fn release_nonce(&self, account_id: &T::AccountId, nonce: u64) {
+        let lock = self.account_nonce_lock(account_id);
+        let mut next_nonce = lock.lock().await;
+
+        *next_nonce = if *next_nonce == Some(nonce + 1) {
+            Some(nonce)
+        } else {
+            None
+        };
+    }
+
+    /// Forget the nonce for the given account, so that it's fetched from the node again the
+    /// next time it's needed.
+    pub async fn resync(&self, account_id: &T::AccountId) {
+        let lock = self.account_nonce_lock(account_id);
+        *lock.lock().await = None;
+    }
+
+    /// Resync the nonce for the given account if a transaction from it was found to be
+    /// [`TransactionInvalid::Stale`] or [`TransactionInvalid::Future`], for instance via
+    /// [`SubmittableExtrinsic::validate`]. Returns true if the nonce was resynced.
+    pub async fn handle_invalid(
+        &self,
+        account_id: &T::AccountId,
+        invalid: &TransactionInvalid,
+    ) -> bool {
+        let is_nonce_error = is_nonce_invalid(invalid);
+        if is_nonce_error {
+            self.resync(account_id).await;
+        }
+        is_nonce_error
+    }
+
+    /// Resync the nonce for the given account if some error is the node rejecting
+    /// a transaction from it because its nonce is stale or too far in the future.
+    /// Returns true if the nonce was resynced.
+    pub async fn handle_error(&self, account_id: &T::AccountId, error: &Error) -> bool {
+        let is_nonce_error = invalid_from_error(error).is_some_and(|i| is_nonce_invalid(&i));
+        if is_nonce_error {
+            self.resync(account_id).await;
+        }
+        is_nonce_error
+    }
+
+    /// Creates a signed extrinsic without submitting it, using the next nonce for the
+    /// signer's account.
+    pub async fn create_signed<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        other_params: <T::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams,
+    ) -> Result<SubmittableExtrinsic<T, C>, Error>
+    where
+        Call: TxPayload,
+        Signer: SignerT<T>,
+    {
+        let account_id = signer.account_id();
+        let account_nonce = self.next_nonce(&account_id).await?;
+        let res =
+            self.client
+                .tx()
+                .create_signed_with_nonce(call, signer, account_nonce, other_params);
+        if res.is_err() {
+            self.release_nonce(&account_id, account_nonce).await;
+        }
+        res
+    }
+
+    /// Like [`NonceManager::create_signed`], but signs the extrinsic using an
+    /// [`AsyncSignerT`].
+    pub async fn create_signed_async<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        other_params: <T::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams,
+    ) -> Result<SubmittableExtrinsic<T, C>, Error>
+    where
+        Call: TxPayload,
+        Signer: AsyncSignerT<T>,
+    {
+        let account_id = signer.account_id();
+        let account_nonce = self.next_nonce(&account_id).await?;
+        let res = self
+            .client
+            .tx()
+            .create_signed_with_nonce_async(call, signer, account_nonce, other_params)
+            .await;
+        if res.is_err() {
+            self.release_nonce(&account_id, account_nonce).await;
+        }
+        res
+    }
+
+    /// Creates and signs an extrinsic using the next nonce for the signer's account, and
+    /// submits it to the chain. Passes default parameters to construct the "signed extra"
+    /// and "additional" payloads needed by the extrinsic.
+    ///
+    /// See [`crate::tx::TxClient::sign_and_submit_then_watch_default`].
+    pub async fn sign_and_submit_then_watch_default<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+    ) -> Result<TxProgress<T, C>, Error>
+    where
+        Call: TxPayload,
+        Signer: SignerT<T>,
+        <T::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams: Default,
+    {
+        self.sign_and_submit_then_watch(call, signer, Default::default())
+            .await
+    }
+
+    /// Creates and signs an extrinsic using the next nonce for the signer's account, and
+    /// submits it to the chain.
+    ///
+    /// See [`crate::tx::TxClient::sign_and_submit_then_watch`].
+    pub async fn sign_and_submit_then_watch<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        other_params: <T::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams,
+    ) -> Result<TxProgress<T, C>, Error>
+    where
+        Call: TxPayload,
+        Signer: SignerT<T>,
+    {
+        let tx = self.create_signed(call, signer, other_params).await?;
+        let res = tx.submit_and_watch().await;
+        self.handle_result(&signer.account_id(), res).await
+    }
+
+    /// Creates and signs an extrinsic using the next nonce for the signer's account, and
+    /// submits it to the chain for block inclusion. Passes default parameters to construct
+    /// the "signed extra" and "additional" payloads needed by the extrinsic.
+    ///
+    /// See [`crate::tx::TxClient::sign_and_submit_default`].
+    pub async fn sign_and_submit_default<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+    ) -> Result<T::Hash, Error>
+    where
+        Call: TxPayload,
+        Signer: SignerT<T>,
+        <T::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams: Default,
+    {
+        self.sign_and_submit(call, signer, Default::default()).await
+    }
+
+    /// Creates and signs an extrinsic using the next nonce for the signer's account, and
+    /// submits it to the chain for block inclusion.
+    ///
+    /// See [`crate::tx::TxClient::sign_and_submit`].
+    pub async fn sign_and_submit<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        other_params: <T::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams,
+    ) -> Result<T::Hash, Error>
+    where
+        Call: TxPayload,
+        Signer: SignerT<T>,
+    {
+        let tx = self.create_signed(call, signer, other_params).await?;
+        let res = tx.submit().await;
+        self.handle_result(&signer.account_id(), res).await
+    }
+
+    async fn handle_result<R>(
+        &self,
+        account_id: &T::AccountId,
+        res: Result<R, Error>,
+    ) -> Result<R, Error> {
+        if let Err(e) = &res {
+            self.handle_error(account_id, e).await;
+        }
+        res
+    }
+}
+
+/// Is a transaction invalid because of its nonce?
+fn is_nonce_invalid(invalid: &TransactionInvalid) -> bool {
+    matches!(
+        invalid,
+        TransactionInvalid::Stale | TransactionInvalid::Future
+    )
+}
+
+/// If this error is the node rejecting a transaction because of its nonce, return the
+/// corresponding [`TransactionInvalid`]. Nodes hand back the reason for rejecting a
+/// transaction as a string rather than as a SCALE encoded `TransactionValidityError`,
+/// so this is all that we have to go on.
+fn invalid_from_error(error: &Error) -> Option<TransactionInvalid> {
+    let Error::Rpc(RpcError::ClientError(e)) = error else {
+        return None;
+    };
+
+    // When using jsonrpsee, we can look at the error code and data directly.
+    #[cfg(feature = "jsonrpsee")]
+    if let Some(jsonrpsee::core::Error::Call(e)) = e.downcast_ref::<jsonrpsee::core::Error>() {
+        if e.code() != POOL_INVALID_TX {
+            return None;
+        }
+        let reason: String = serde_json::from_str(e.data()?.get()).ok()?;
+        return invalid_from_reason(&reason);
+    }
+
+    invalid_from_reason(&e.to_string())
+}
+
+fn invalid_from_reason(reason: &str) -> Option<TransactionInvalid> {
+    if reason.contains(STALE_MESSAGE) {
+        Some(TransactionInvalid::Stale)
+    } else if reason.contains(FUTURE_MESSAGE) {
+        Some(TransactionInvalid::Future)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::rpc::{RawRpcFuture, RawRpcSubscription, RpcClientT};
+    use crate::backend::test_utils::{mock_client, FixedSigner};
+    use crate::utils::AccountId32;
+    use serde_json::value::RawValue;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// Answers `system_accountNextIndex` with whatever the nonce has been set to.
+    #[derive(Clone, Default)]
+    struct NextIndex(Arc<AtomicU64>);
+
+    impl NextIndex {
+        fn set(&self, nonce: u64) {
+            self.0.store(nonce, Ordering::SeqCst)
+        }
+    }
+
+    impl RpcClientT for NextIndex {
+        fn request_raw<'a>(
+            &'a self,
+            method: &'a str,
+            _params: Option<Box<RawValue>>,
+        ) -> RawRpcFuture<'a, Box<RawValue>> {
+            Box::pin(async move {
+                assert_eq!(method, "system_accountNextIndex");
+                let nonce = self.0.load(Ordering::SeqCst).to_string();
+                Ok(RawValue::from_string(nonce).unwrap())
+            })
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            _sub: &'a str,
+            _params: Option<Box<RawValue>>,
+            _unsub: &'a str,
+        ) -> RawRpcFuture<'a, RawRpcSubscription> {
+            Box::pin(async { Err(RpcError::SubscriptionDropped) })
+        }
+    }
+
+    async fn setup(
+        nonce: u64,
+    ) -> (
+        NextIndex,
+        NonceManager<crate::PolkadotConfig, crate::OnlineClient<crate::PolkadotConfig>>,
+    ) {
+        let (_backend, api) = mock_client("polkadot_metadata_small.scale").await;
+        let next_index = NextIndex::default();
+        next_index.set(nonce);
+        let nonce_manager = NonceManager::new(api, RpcClient::new(next_index.clone()));
+        (next_index, nonce_manager)
+    }
+
+    #[tokio::test]
+    async fn hands_out_increasing_nonces() {
+        let (_, nonce_manager) = setup(5).await;
+        let alice = AccountId32([1; 32]);
+
+        let mut nonces =
+            futures::future::try_join_all((0..10).map(|_| nonce_manager.next_nonce(&alice)))
+                .await
+                .unwrap();
+        nonces.sort();
+        assert_eq!(nonces, (5..15).collect::<Vec<_>>());
+
+        // Other accounts are unaffected:
+        let bob = AccountId32([2; 32]);
+        assert_eq!(nonce_manager.next_nonce(&bob).await.unwrap(), 5);
+        assert_eq!(nonce_manager.next_nonce(&bob).await.unwrap(), 6);
+    }
+
+    #[tokio::test]
+    async fn resyncs_after_nonce_errors() {
+        let (next_index, nonce_manager) = setup(5).await;
+        let alice = AccountId32([1; 32]);
+
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 5);
+        next_index.set(10);
+
+        assert!(
+            !nonce_manager
+                .handle_invalid(&alice, &TransactionInvalid::Payment)
+                .await
+        );
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 6);
+
+        assert!(
+            nonce_manager
+                .handle_invalid(&alice, &TransactionInvalid::Stale)
+                .await
+        );
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 10);
+
+        let err = Error::Rpc(RpcError::ClientError(
+            "Invalid Transaction: Transaction will be valid in the future".into(),
+        ));
+        next_index.set(3);
+        assert!(nonce_manager.handle_error(&alice, &err).await);
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 3);
+
+        // Other errors which happen to mention the same thing are ignored:
+        let err = Error::Other("Transaction is outdated".into());
+        next_index.set(20);
+        assert!(!nonce_manager.handle_error(&alice, &err).await);
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 4);
+    }
+
+    #[cfg(feature = "jsonrpsee")]
+    #[test]
+    fn decodes_jsonrpsee_errors() {
+        use jsonrpsee::types::ErrorObject;
+
+        let error = |code, reason: &str| {
+            let e = ErrorObject::owned(code, "Invalid Transaction", Some(reason));
+            Error::Rpc(RpcError::ClientError(Box::new(
+                jsonrpsee::core::Error::Call(e),
+            )))
+        };
+
+        assert_eq!(
+            invalid_from_error(&error(POOL_INVALID_TX, "Transaction is outdated")),
+            Some(TransactionInvalid::Stale)
+        );
+        assert_eq!(
+            invalid_from_error(&error(
+                POOL_INVALID_TX,
+                "Transaction will be valid in the future"
+            )),
+            Some(TransactionInvalid::Future)
+        );
+        assert_eq!(
+            invalid_from_error(&error(POOL_INVALID_TX, "Inability to pay some fees")),
+            None
+        );
+        assert_eq!(
+            invalid_from_error(&error(1000, "Transaction is outdated")),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn unused_nonces_are_handed_out_again() {
+        let (_, nonce_manager) = setup(5).await;
+        let alice = AccountId32([1; 32]);
+
+        // This call can't be encoded, so the nonce isn't used:
+        let bad_call = crate::dynamic::tx("NotAPallet", "remark", vec![vec![1u8]]);
+        assert!(nonce_manager
+            .create_signed(&bad_call, &FixedSigner, Default::default())
+            .await
+            .is_err());
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 5);
+
+        // If another nonce has been handed out in the meantime, we resync instead:
+        let (next_index, nonce_manager) = setup(5).await;
+        let nonce = nonce_manager.next_nonce(&alice).await.unwrap();
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 6);
+        next_index.set(7);
+        nonce_manager.release_nonce(&alice, nonce).await;
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 7);
+    }
+}
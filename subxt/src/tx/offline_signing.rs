@@ -0,0 +1,488 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! A portable format for carrying a transaction to another (possibly air-gapped) machine to
+//! be signed, and for carrying the signature back again.
+
+use super::tx_client::encode_signed_extrinsic;
+use crate::{
+    client::OfflineClientT,
+    config::Config,
+    dynamic::DecodedValue,
+    error::{Error, MetadataError, OfflineSigningError},
+    metadata::Metadata,
+    tx::{Signer as SignerT, SubmittableExtrinsic},
+    utils::Encoded,
+};
+use codec::{Decode, Encode};
+use scale_decode::{DecodeAsFields, DecodeAsType};
+use serde::{Deserialize, Serialize};
+use sp_core_hashing::blake2_256;
+
+/// The version of the signing request and response formats that we produce and understand.
+const FORMAT_VERSION: u8 = 1;
+
+/// Everything needed to sign a transaction, along with enough context to show the person
+/// signing it what they are signing. Create one with
+/// [`crate::tx::PartialExtrinsic::signing_request()`], carry it to the machine which will
+/// sign it (as JSON via [`SigningRequest::to_json()`], or as compact SCALE encoded bytes via
+/// [`SigningRequest::to_bytes()`]), and then hand back the [`SigningResponse`] to
+/// [`SigningRequest::into_submittable()`] to obtain a transaction ready to submit.
+///
+/// **Note:** The decoded call and signed extension details are produced by the machine which
+/// created the request. A signer which has the chain metadata to hand can check these
+/// against [`SigningRequest::call_data()`] and the bytes of each signed extension.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningRequest {
+    version: u8,
+    #[serde(with = "impl_serde::serialize")]
+    genesis_hash: Vec<u8>,
+    spec_version: u32,
+    transaction_version: u32,
+    #[serde(with = "impl_serde::serialize")]
+    call_data: Vec<u8>,
+    call: DecodedCall,
+    signed_extensions: Vec<SignedExtensionDetails>,
+}
+
+/// A human readable description of the call being signed.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub struct DecodedCall {
+    /// The name of the pallet that the call belongs to.
+    pub pallet: String,
+    /// The name of the call.
+    pub call: String,
+    /// The arguments given to the call.
+    pub args: String,
+}
+
+/// The values given for a single signed extension in a transaction being signed.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedExtensionDetails {
+    /// The name of the signed extension.
+    pub identifier: String,
+    /// The SCALE encoded value that is included in the transaction.
+    #[serde(with = "impl_serde::serialize")]
+    pub extra: Vec<u8>,
+    /// The SCALE encoded value that is signed but not included in the transaction.
+    #[serde(with = "impl_serde::serialize")]
+    pub additional: Vec<u8>,
+    /// A human readable description of the extra value.
+    pub decoded_extra: String,
+    /// A human readable description of the additional value.
+    pub decoded_additional: String,
+}
+
+impl SigningRequest {
+    pub(crate) fn new<T: Config, C: OfflineClientT<T>>(
+        client: &C,
+        call_data: &[u8],
+        extra_params: &[u8],
+        additional_params: &[u8],
+    ) -> Result<Self, Error> {
+        let metadata = client.metadata();
+        let runtime_version = client.runtime_version();
+
+        // Split the params up into the values for each signed extension.
+        let extra = &mut &*extra_params;
+        let additional = &mut &*additional_params;
+        let signed_extensions = metadata
+            .extrinsic()
+            .signed_extensions()
+            .iter()
+            .map(|ext| {
+                let (extra, decoded_extra) = decode_value(extra, ext.extra_ty(), &metadata)?;
+                let (additional, decoded_additional) =
+                    decode_value(additional, ext.additional_ty(), &metadata)?;
+                Ok(SignedExtensionDetails {
+                    identifier: ext.identifier().to_owned(),
+                    extra,
+                    additional,
+                    decoded_extra,
+                    decoded_additional,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(SigningRequest {
+            version: FORMAT_VERSION,
+            genesis_hash: client.genesis_hash().encode(),
+            spec_version: runtime_version.spec_version,
+            transaction_version: runtime_version.transaction_version,
+            call_data: call_data.to_vec(),
+            call: decode_call(call_data, &metadata)?,
+            signed_extensions,
+        })
+    }
+
+    /// The SCALE encoded genesis hash of the chain that the transaction is for.
+    pub fn genesis_hash(&self) -> &[u8] {
+        &self.genesis_hash
+    }
+
+    /// The spec version of the runtime that the transaction was created for.
+    pub fn spec_version(&self) -> u32 {
+        self.spec_version
+    }
+
+    /// The transaction version of the runtime that the transaction was created for.
+    pub fn transaction_version(&self) -> u32 {
+        self.transaction_version
+    }
+
+    /// The SCALE encoded call data of the transaction.
+    pub fn call_data(&self) -> &[u8] {
+        &self.call_data
+    }
+
+    /// A human readable description of the call being signed.
+    pub fn call(&self) -> &DecodedCall {
+        &self.call
+    }
+
+    /// The values given for each of the signed extensions in the transaction.
+    pub fn signed_extensions(&self) -> &[SignedExtensionDetails] {
+        &self.signed_extensions
+    }
+
+    /// Return the signer payload for the transaction. These are the bytes that must be
+    /// signed in order to produce a valid signature for it.
+    pub fn signer_payload(&self) -> Vec<u8> {
+        let extra = self.signed_extensions.iter().flat_map(|e| &e.extra);
+        let additional = self.signed_extensions.iter().flat_map(|e| &e.additional);
+        let bytes: Vec<u8> = self
+            .call_data
+            .iter()
+            .chain(extra)
+            .chain(additional)
+            .copied()
+            .collect();
+        if bytes.len() > 256 {
+            blake2_256(&bytes).to_vec()
+        } else {
+            bytes
+        }
+    }
+
+    /// Sign this request with the given signer, producing a [`SigningResponse`] to hand back.
+    pub fn sign<T: Config, Signer: SignerT<T>>(&self, signer: &Signer) -> SigningResponse {
+        let signature = signer.sign(&self.signer_payload());
+        SigningResponse::new::<T>(self, &signer.address(), &signature)
+    }
+
+    /// Combine this request with the [`SigningResponse`] produced by signing it, to obtain a
+    /// transaction ready to submit. This fails if the response was not produced by signing
+    /// this request, or if the request was created for a different chain or runtime version
+    /// than the client is using.
+    pub fn into_submittable<T: Config, C: OfflineClientT<T>>(
+        self,
+        client: C,
+        response: &SigningResponse,
+    ) -> Result<SubmittableExtrinsic<T, C>, Error> {
+        if client.genesis_hash().encode() != self.genesis_hash {
+            return Err(OfflineSigningError::GenesisHashMismatch.into());
+        }
+        let runtime_version = client.runtime_version();
+        if runtime_version.spec_version != self.spec_version
+            || runtime_version.transaction_version != self.transaction_version
+        {
+            return Err(OfflineSigningError::RuntimeVersionMismatch {
+                request_spec_version: self.spec_version,
+                request_transaction_version: self.transaction_version,
+                spec_version: runtime_version.spec_version,
+                transaction_version: runtime_version.transaction_version,
+            }
+            .into());
+        }
+        if response.payload_hash[..] != blake2_256(&self.signer_payload())[..] {
+            return Err(OfflineSigningError::ResponseMismatch.into());
+        }
+
+        let extra: Vec<u8> = self
+            .signed_extensions
+            .into_iter()
+            .flat_map(|e| e.extra)
+            .collect();
+        let extrinsic = encode_signed_extrinsic(
+            &Encoded(response.address.clone()),
+            &Encoded(response.signature.clone()),
+            &extra,
+            &self.call_data,
+        );
+        Ok(SubmittableExtrinsic::from_bytes(client, extrinsic))
+    }
+
+    /// Serialize this request to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("signing requests always serialize to JSON; qed")
+    }
+
+    /// Deserialize a request from the JSON produced by [`SigningRequest::to_json()`].
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let request: Self = serde_json::from_str(json)?;
+        check_version(request.version)?;
+        Ok(request)
+    }
+
+    /// Encode this request into compact SCALE encoded bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encode()
+    }
+
+    /// Decode a request from the bytes produced by [`SigningRequest::to_bytes()`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_version(bytes.first().copied().unwrap_or_default())?;
+        Ok(Self::decode(&mut &*bytes)?)
+    }
+}
+
+/// The signature (and address of the signer) produced by signing a [`SigningRequest`]. This
+/// can be serialized in the same ways that the request can, in order to carry it back to the
+/// machine which will submit the transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningResponse {
+    version: u8,
+    // A hash of the signer payload, to check that this is used with the right request.
+    #[serde(with = "impl_serde::serialize")]
+    payload_hash: Vec<u8>,
+    #[serde(with = "impl_serde::serialize")]
+    address: Vec<u8>,
+    #[serde(with = "impl_serde::serialize")]
+    signature: Vec<u8>,
+}
+
+impl SigningResponse {
+    /// Create a response to the given request from the address of the signer and the
+    /// signature that they produced by signing [`SigningRequest::signer_payload()`].
+    pub fn new<T: Config>(
+        request: &SigningRequest,
+        address: &T::Address,
+        signature: &T::Signature,
+    ) -> Self {
+        SigningResponse {
+            version: FORMAT_VERSION,
+            payload_hash: blake2_256(&request.signer_payload()).to_vec(),
+            address: address.encode(),
+            signature: signature.encode(),
+        }
+    }
+
+    /// The SCALE encoded address of the signer.
+    pub fn address(&self) -> &[u8] {
+        &self.address
+    }
+
+    /// The SCALE encoded signature.
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// Serialize this response to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("signing responses always serialize to JSON; qed")
+    }
+
+    /// Deserialize a response from the JSON produced by [`SigningResponse::to_json()`].
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let response: Self = serde_json::from_str(json)?;
+        check_version(response.version)?;
+        Ok(response)
+    }
+
+    /// Encode this response into compact SCALE encoded bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encode()
+    }
+
+    /// Decode a response from the bytes produced by [`SigningResponse::to_bytes()`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_version(bytes.first().copied().unwrap_or_default())?;
+        Ok(Self::decode(&mut &*bytes)?)
+    }
+}
+
+fn check_version(version: u8) -> Result<(), Error> {
+    if version != FORMAT_VERSION {
+        return Err(OfflineSigningError::UnsupportedVersion(version).into());
+    }
+    Ok(())
+}
+
+/// Decode a value of the given type from the start of the input, returning the bytes that
+/// were decoded along with a human readable description of them.
+fn decode_value(
+    input: &mut &[u8],
+    type_id: u32,
+    metadata: &Metadata,
+) -> Result<(Vec<u8>, String), Error> {
+    let start = *input;
+    let value = DecodedValue::decode_as_type(input, type_id, metadata.types())?;
+    let bytes = start[..start.len() - input.len()].to_vec();
+    Ok((bytes, value.to_string()))
+}
+
+/// Describe the call given by some call data.
+fn decode_call(call_data: &[u8], metadata: &Metadata) -> Result<DecodedCall, Error> {
+    let input = &mut &*call_data;
+    let pallet_index = u8::decode(input)?;
+    let call_index = u8::decode(input)?;
+    let pallet = metadata.pallet_by_index_err(pallet_index)?;
+    let call = pallet
+        .call_variant_by_index(call_index)
+        .ok_or(MetadataError::VariantIndexNotFound(call_index))?;
+
+    let mut fields = call
+        .fields
+        .iter()
+        .map(|f| scale_decode::Field::new(f.ty.id, f.name.as_deref()));
+    let args = <scale_value::Composite<scale_value::scale::TypeId>>::decode_as_fields(
+        input,
+        &mut fields,
+        metadata.types(),
+    )?;
+
+    Ok(DecodedCall {
+        pallet: pallet.name().to_owned(),
+        call: call.name.clone(),
+        args: args.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::test_utils::FixedSigner;
+    use crate::backend::RuntimeVersion;
+    use crate::config::polkadot::PolkadotExtrinsicParamsBuilder;
+    use crate::{OfflineClient, PolkadotConfig};
+
+    fn client(spec_version: u32) -> OfflineClient<PolkadotConfig> {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_small.scale").unwrap();
+        OfflineClient::new(
+            [1; 32].into(),
+            RuntimeVersion {
+                spec_version,
+                transaction_version: 1,
+            },
+            Metadata::decode(&mut &*bytes).unwrap(),
+        )
+    }
+
+    fn partial_extrinsic(
+        client: &OfflineClient<PolkadotConfig>,
+    ) -> crate::tx::PartialExtrinsic<PolkadotConfig, OfflineClient<PolkadotConfig>> {
+        let call = crate::dynamic::tx("System", "remark", vec![vec![1u8, 2, 3]]);
+        let params = PolkadotExtrinsicParamsBuilder::new().tip(5).build();
+        client
+            .tx()
+            .create_partial_signed_with_nonce(&call, 7, params)
+            .unwrap()
+    }
+
+    #[test]
+    fn round_trips_through_json_and_bytes() {
+        let client = client(1);
+        let request = partial_extrinsic(&client).signing_request().unwrap();
+
+        assert_eq!(request.call().pallet, "System");
+        assert_eq!(request.call().call, "remark");
+        assert_eq!(request.genesis_hash(), &[1; 32]);
+        assert_eq!(request.spec_version(), 1);
+        let identifiers: Vec<_> = request
+            .signed_extensions()
+            .iter()
+            .map(|e| e.identifier.as_str())
+            .collect();
+        assert!(identifiers.contains(&"CheckNonce"));
+
+        let json = request.to_json();
+        assert!(json.contains("\"callData\":\"0x"));
+        assert_eq!(SigningRequest::from_json(&json).unwrap(), request);
+        assert_eq!(
+            SigningRequest::from_bytes(&request.to_bytes()).unwrap(),
+            request
+        );
+
+        let response = request.sign(&FixedSigner);
+        assert_eq!(
+            SigningResponse::from_json(&response.to_json()).unwrap(),
+            response
+        );
+        assert_eq!(
+            SigningResponse::from_bytes(&response.to_bytes()).unwrap(),
+            response
+        );
+
+        // Versions that we don't know about are rejected:
+        let mut bytes = request.to_bytes();
+        bytes[0] = 2;
+        assert!(matches!(
+            SigningRequest::from_bytes(&bytes),
+            Err(Error::OfflineSigning(
+                OfflineSigningError::UnsupportedVersion(2)
+            ))
+        ));
+    }
+
+    #[test]
+    fn reassembles_the_signed_extrinsic() {
+        let client = client(1);
+        let partial = partial_extrinsic(&client);
+        let request = partial.signing_request().unwrap();
+
+        assert_eq!(request.signer_payload(), partial.signer_payload());
+
+        let response = SigningResponse::from_json(&request.sign(&FixedSigner).to_json()).unwrap();
+        let tx = request.into_submittable(client, &response).unwrap();
+        assert_eq!(tx.encoded(), partial.sign(&FixedSigner).encoded());
+    }
+
+    #[test]
+    fn rejects_mismatched_requests() {
+        let client = client(1);
+        let request = partial_extrinsic(&client).signing_request().unwrap();
+        let response = request.sign(&FixedSigner);
+
+        // A response to some other request:
+        let other_call = crate::dynamic::tx("System", "remark", vec![vec![4u8]]);
+        let other_request = client
+            .tx()
+            .create_partial_signed_with_nonce(&other_call, 7, Default::default())
+            .unwrap()
+            .signing_request()
+            .unwrap();
+        assert!(matches!(
+            other_request.into_submittable(client.clone(), &response),
+            Err(Error::OfflineSigning(OfflineSigningError::ResponseMismatch))
+        ));
+
+        // A client on a different runtime version:
+        assert!(matches!(
+            request.clone().into_submittable(self::client(2), &response),
+            Err(Error::OfflineSigning(
+                OfflineSigningError::RuntimeVersionMismatch {
+                    request_spec_version: 1,
+                    spec_version: 2,
+                    ..
+                }
+            ))
+        ));
+
+        // A client on a different chain:
+        let other_chain = OfflineClient::<PolkadotConfig>::new(
+            [2; 32].into(),
+            client.runtime_version(),
+            client.metadata(),
+        );
+        assert!(matches!(
+            request.into_submittable(other_chain, &response),
+            Err(Error::OfflineSigning(
+                OfflineSigningError::GenesisHashMismatch
+            ))
+        ));
+    }
+}
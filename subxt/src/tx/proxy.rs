@@ -0,0 +1,293 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Dispatch calls on behalf of other accounts, via the `Proxy` pallet.
+
+use super::tx_payload::call_to_outer_enum_value;
+use crate::{
+    dynamic::Value,
+    error::{Error, MetadataError},
+    metadata::Metadata,
+    tx::TxPayload,
+    utils::{account_id_from_entropy, Encoded, Static},
+    Config,
+};
+use codec::{Decode, Encode};
+use scale_encode::EncodeAsType;
+use sp_core_hashing::blake2_256;
+use std::sync::Arc;
+
+/// A transaction payload which dispatches some other (static or dynamic) transaction payload
+/// on behalf of a "real" account, via the `Proxy` pallet's `proxy` or `proxy_announced` calls.
+/// The result of the call that was dispatched can be found using
+/// [`crate::blocks::ExtrinsicEvents::proxy_result()`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use subxt::dynamic::Value;
+/// use subxt::tx::ProxyPayload;
+/// use subxt::utils::{AccountId32, MultiAddress};
+///
+/// let real: MultiAddress<AccountId32, ()> = AccountId32([1; 32]).into();
+/// let remark = subxt::dynamic::tx("System", "remark", vec![Value::from_bytes("Hello")]);
+///
+/// // Dispatch the remark from the real account, using one of its "Any" proxies:
+/// let proxied = ProxyPayload::proxy(real, remark)
+///     .force_proxy_type(Value::unnamed_variant("Any", []));
+/// ```
+#[derive(Clone)]
+pub struct ProxyPayload {
+    delegate: Option<Encoded>,
+    real: Encoded,
+    force_proxy_type: Option<ProxyType>,
+    // Dev Note: Arc used to enable easy cloning (given that we can't have dyn Clone).
+    call: Arc<dyn TxPayload + Send + Sync + 'static>,
+}
+
+impl std::fmt::Debug for ProxyPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProxyPayload")
+            .field("delegate", &self.delegate)
+            .field("real", &self.real)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProxyPayload {
+    /// Dispatch the given call on behalf of the `real` account, using `Proxy::proxy`. The
+    /// signer of the transaction must be a proxy of the real account.
+    ///
+    /// The address given should be the address type used by the chain (typically
+    /// [`Config::Address`]).
+    pub fn proxy<Address, Call>(real: Address, call: Call) -> Self
+    where
+        Address: Encode,
+        Call: TxPayload + Send + Sync + 'static,
+    {
+        ProxyPayload {
+            delegate: None,
+            real: Encoded(real.encode()),
+            force_proxy_type: None,
+            call: Arc::new(call),
+        }
+    }
+
+    /// Dispatch the given call on behalf of the `real` account, using `Proxy::proxy_announced`.
+    /// The call must have previously been announced by the `delegate` account, which is a time
+    /// delayed proxy of the real account.
+    ///
+    /// The addresses given should be the address type used by the chain (typically
+    /// [`Config::Address`]).
+    pub fn proxy_announced<Address, Call>(delegate: Address, real: Address, call: Call) -> Self
+    where
+        Address: Encode,
+        Call: TxPayload + Send + Sync + 'static,
+    {
+        ProxyPayload {
+            delegate: Some(Encoded(delegate.encode())),
+            real: Encoded(real.encode()),
+            force_proxy_type: None,
+            call: Arc::new(call),
+        }
+    }
+
+    /// Only dispatch the call if the proxy is of the given type. This can be a
+    /// [`Value`] or a statically generated `ProxyType`.
+    pub fn force_proxy_type(
+        mut self,
+        proxy_type: impl EncodeAsType + Send + Sync + 'static,
+    ) -> Self {
+        self.force_proxy_type = Some(ProxyType(Arc::new(proxy_type)));
+        self
+    }
+}
+
+impl TxPayload for ProxyPayload {
+    fn encode_call_data_to(&self, metadata: &Metadata, out: &mut Vec<u8>) -> Result<(), Error> {
+        let call = call_to_outer_enum_value(&*self.call, metadata)?;
+        let real = Static(self.real.clone());
+
+        match &self.delegate {
+            None => {
+                #[derive(scale_encode::EncodeAsType)]
+                struct Proxy<'a> {
+                    real: Static<Encoded>,
+                    force_proxy_type: Option<&'a ProxyType>,
+                    call: Value,
+                }
+                let args = Proxy {
+                    real,
+                    force_proxy_type: self.force_proxy_type.as_ref(),
+                    call,
+                };
+                crate::tx::Payload::new("Proxy", "proxy", args).encode_call_data_to(metadata, out)
+            }
+            Some(delegate) => {
+                #[derive(scale_encode::EncodeAsType)]
+                struct ProxyAnnounced<'a> {
+                    delegate: Static<Encoded>,
+                    real: Static<Encoded>,
+                    force_proxy_type: Option<&'a ProxyType>,
+                    call: Value,
+                }
+                let args = ProxyAnnounced {
+                    delegate: Static(delegate.clone()),
+                    real,
+                    force_proxy_type: self.force_proxy_type.as_ref(),
+                    call,
+                };
+                crate::tx::Payload::new("Proxy", "proxy_announced", args)
+                    .encode_call_data_to(metadata, out)
+            }
+        }
+    }
+}
+
+/// Derive the account id of a pure proxy in the same way that the `Proxy` pallet does when
+/// `Proxy::create_pure` is called.
+///
+/// - `spawner` is the account which called `create_pure`.
+/// - `proxy_type` and `index` are the arguments given to `create_pure`. The proxy type can be a
+///   [`Value`] or a statically generated `ProxyType`.
+/// - `height` and `ext_index` are the block number and index of the extrinsic in that block in
+///   which `create_pure` was called.
+pub fn pure_proxy_account_id<T: Config>(
+    metadata: &Metadata,
+    spawner: &T::AccountId,
+    proxy_type: impl EncodeAsType,
+    index: u16,
+    height: u32,
+    ext_index: u32,
+) -> Result<T::AccountId, Error>
+where
+    T::AccountId: Decode,
+{
+    // The proxy type is encoded as part of this, so find out what shape it has from the
+    // `create_pure` call, which is given one.
+    let create_pure = metadata
+        .pallet_by_name_err("Proxy")?
+        .call_variant_by_name("create_pure")
+        .ok_or_else(|| MetadataError::CallNameNotFound("create_pure".to_owned()))?;
+    let proxy_type_field = create_pure
+        .fields
+        .iter()
+        .find(|f| f.name.as_deref() == Some("proxy_type"))
+        .ok_or_else(|| Error::Other("No 'proxy_type' argument in Proxy::create_pure".into()))?;
+    let proxy_type = proxy_type.encode_as_type(proxy_type_field.ty.id, metadata.types())?;
+
+    let entropy = (
+        b"modlpy/proxy____",
+        spawner,
+        height,
+        ext_index,
+        Encoded(proxy_type),
+        index,
+    )
+        .using_encoded(blake2_256);
+    Ok(account_id_from_entropy(&entropy)?)
+}
+
+// Dev Note: `EncodeAsType` isn't implemented for `Arc<dyn EncodeAsType>`, so we wrap it.
+#[derive(Clone)]
+struct ProxyType(Arc<dyn EncodeAsType + Send + Sync + 'static>);
+
+impl EncodeAsType for ProxyType {
+    fn encode_as_type_to(
+        &self,
+        type_id: u32,
+        types: &scale_info::PortableRegistry,
+        out: &mut Vec<u8>,
+    ) -> Result<(), scale_encode::Error> {
+        self.0.encode_as_type_to(type_id, types, out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::{AccountId32, MultiAddress};
+    use crate::PolkadotConfig;
+
+    fn metadata() -> Metadata {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_full.scale").unwrap();
+        Metadata::decode(&mut &*bytes).unwrap()
+    }
+
+    fn address(byte: u8) -> MultiAddress<AccountId32, ()> {
+        AccountId32([byte; 32]).into()
+    }
+
+    #[test]
+    fn wraps_calls_in_proxy_calls() {
+        let metadata = metadata();
+        let remark = crate::dynamic::tx("System", "remark", vec![Value::from_bytes("Hello")]);
+        let remark_data = remark.encode_call_data(&metadata).unwrap();
+        let pallet = metadata.pallet_by_name("Proxy").unwrap();
+        let call_index = |name| pallet.call_variant_by_name(name).unwrap().index;
+
+        let proxy = ProxyPayload::proxy(address(1), remark.clone());
+        let mut expected = vec![pallet.index(), call_index("proxy")];
+        address(1).encode_to(&mut expected);
+        None::<u8>.encode_to(&mut expected);
+        expected.extend(&remark_data);
+        assert_eq!(proxy.encode_call_data(&metadata).unwrap(), expected);
+
+        // "Any" is the first proxy type variant:
+        let proxy_announced = ProxyPayload::proxy_announced(address(2), address(1), remark)
+            .force_proxy_type(Value::unnamed_variant("Any", []));
+        let mut expected = vec![pallet.index(), call_index("proxy_announced")];
+        address(2).encode_to(&mut expected);
+        address(1).encode_to(&mut expected);
+        Some(0u8).encode_to(&mut expected);
+        expected.extend(&remark_data);
+        assert_eq!(
+            proxy_announced.encode_call_data(&metadata).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn derives_pure_proxy_account_ids() {
+        let metadata = metadata();
+        let spawner = AccountId32([1; 32]);
+        let any = || Value::unnamed_variant("Any", []);
+
+        let pure =
+            pure_proxy_account_id::<PolkadotConfig>(&metadata, &spawner, any(), 0, 10, 2).unwrap();
+        let expected = AccountId32(blake2_256(
+            &(b"modlpy/proxy____", &spawner, 10u32, 2u32, 0u8, 0u16).encode(),
+        ));
+        assert_eq!(pure, expected);
+
+        // Each of the arguments leads to a different account:
+        let others = [
+            pure_proxy_account_id::<PolkadotConfig>(&metadata, &spawner, any(), 1, 10, 2),
+            pure_proxy_account_id::<PolkadotConfig>(&metadata, &spawner, any(), 0, 11, 2),
+            pure_proxy_account_id::<PolkadotConfig>(&metadata, &spawner, any(), 0, 10, 3),
+            pure_proxy_account_id::<PolkadotConfig>(
+                &metadata,
+                &spawner,
+                Value::unnamed_variant("Staking", []),
+                0,
+                10,
+                2,
+            ),
+        ];
+        for other in others {
+            assert_ne!(other.unwrap(), pure);
+        }
+
+        // Proxy types which don't exist are an error:
+        assert!(pure_proxy_account_id::<PolkadotConfig>(
+            &metadata,
+            &spawner,
+            Value::unnamed_variant("Nope", []),
+            0,
+            10,
+            2
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,715 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use crate::{
+    backend::rpc::middleware::is_transient_error,
+    blocks::ExtrinsicEvents,
+    client::OnlineClientT,
+    config::{
+        Config, DefaultExtrinsicParams, DefaultExtrinsicParamsBuilder, ExtrinsicParams, Header,
+    },
+    error::{Error, TransactionError},
+    tx::{AsyncSigner as AsyncSignerT, TxPayload, TxStatus},
+};
+use derivative::Derivative;
+use futures::{future, stream, StreamExt};
+use std::time::Duration;
+
+/// How the tip given to the block author changes each time a transaction is resubmitted
+/// by a [`ResilientSubmitter`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TipPolicy {
+    /// Give the same tip on every attempt.
+    Fixed(u128),
+    /// Start with an `initial` tip, and add `increment` to it on each subsequent attempt,
+    /// up to some `max` tip.
+    Linear {
+        /// The tip to give on the first attempt.
+        initial: u128,
+        /// How much to add to the tip on each subsequent attempt.
+        increment: u128,
+        /// The largest tip to give.
+        max: u128,
+    },
+    /// Start with an `initial` tip, and multiply it by `multiplier` on each subsequent attempt,
+    /// up to some `max` tip.
+    Exponential {
+        /// The tip to give on the first attempt.
+        initial: u128,
+        /// What to multiply the tip by on each subsequent attempt.
+        multiplier: u128,
+        /// The largest tip to give.
+        max: u128,
+    },
+}
+
+impl Default for TipPolicy {
+    fn default() -> Self {
+        TipPolicy::Fixed(0)
+    }
+}
+
+impl TipPolicy {
+    /// The tip to give on the given attempt, where the first attempt is attempt 0.
+    pub fn tip_for_attempt(&self, attempt: u32) -> u128 {
+        match *self {
+            TipPolicy::Fixed(tip) => tip,
+            TipPolicy::Linear {
+                initial,
+                increment,
+                max,
+            } => increment
+                .saturating_mul(attempt as u128)
+                .saturating_add(initial)
+                .min(max),
+            TipPolicy::Exponential {
+                initial,
+                multiplier,
+                max,
+            } => (0..attempt)
+                .try_fold(initial, |tip, _| {
+                    let tip = tip.saturating_mul(multiplier);
+                    // Once we've hit the max, there's no need to keep going.
+                    if tip >= max {
+                        Err(max)
+                    } else {
+                        Ok(tip)
+                    }
+                })
+                .unwrap_or_else(|max| max)
+                .min(max),
+        }
+    }
+}
+
+/// Details about an attempt to submit a transaction, which are handed to the function given
+/// to [`ResilientSubmitter::submit_with_params`] in order to build the parameters for it.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct Attempt<'a, T: Config> {
+    /// Which attempt this is, starting from 0.
+    pub number: u32,
+    /// The tip to give for this attempt, according to the [`TipPolicy`].
+    pub tip: u128,
+    /// How many blocks the transaction should be mortal for, or `None` if it should be immortal.
+    pub mortal_for: Option<u64>,
+    /// The current best block, which a mortal transaction should be mortal from.
+    pub best_block: &'a T::Header,
+}
+
+/// The outcome of a transaction submitted via a [`ResilientSubmitter`] making it into a block
+/// and succeeding.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct SubmissionOutcome<T: Config> {
+    /// The events emitted by the transaction. These also provide the hash of the block that
+    /// the transaction is in and the hash of the transaction itself.
+    pub events: ExtrinsicEvents<T>,
+    /// How many times the transaction was submitted.
+    pub attempts: u32,
+    /// The tip given by the transaction that succeeded.
+    pub tip: u128,
+}
+
+/// Submits transactions, and rebuilds, re-signs and resubmits them if they don't make it into
+/// a block. This handles transactions being reported as dropped or invalid, transaction status
+/// subscriptions ending early or failing, and mortal transactions expiring before they are
+/// included.
+///
+/// Each attempt is mortal from the current best block (unless configured to be immortal), and
+/// gives a tip according to the configured [`TipPolicy`]. Once the transaction makes it into a
+/// block (a finalized block by default), the events it emitted are returned if it succeeded, or
+/// a [`crate::error::DispatchError`] if it failed, in which case it is not resubmitted.
+///
+/// Attempts use a fresh nonce fetched from the node for as long as each previous attempt was
+/// reported as dropped or invalid, or expired. Once we lose track of an attempt (for instance
+/// because the subscription to its status was lost, or submitting it failed part way through),
+/// it may still make it into a block, and so every later attempt reuses its nonce. These later
+/// attempts then replace it if it's still waiting to be included, which needs them to give a
+/// larger tip, and are rejected as invalid if it's already been included.
+///
+/// **Note:** A transaction reported as dropped or invalid may, with some small probability,
+/// still make it into a block. Since the attempt after it uses a fresh nonce, this could lead to
+/// the call being executed more than once.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::time::Duration;
+/// use subxt::tx::{ResilientSubmitter, TipPolicy};
+/// use subxt::{OnlineClient, PolkadotConfig};
+/// use subxt_signer::sr25519::dev;
+///
+/// let api = OnlineClient::<PolkadotConfig>::new().await.unwrap();
+///
+/// let submitter = ResilientSubmitter::new(api)
+///     .max_attempts(5)
+///     .deadline(Duration::from_secs(120))
+///     .tip_policy(TipPolicy::Linear { initial: 0, increment: 1_000, max: 10_000 });
+///
+/// let call = subxt::dynamic::tx("System", "remark", vec![b"Hello".to_vec()]);
+/// let outcome = submitter.submit(&call, &dev::alice()).await.unwrap();
+/// println!("Succeeded after {} attempts", outcome.attempts);
+/// # }
+/// ```
+#[derive(Derivative)]
+#[derivative(Debug(bound = "C: std::fmt::Debug"), Clone(bound = "C: Clone"))]
+pub struct ResilientSubmitter<T: Config, C> {
+    client: C,
+    tip_policy: TipPolicy,
+    mortal_for: Option<u64>,
+    max_attempts: u32,
+    deadline: Option<Duration>,
+    wait_for_finalized: bool,
+    _marker: std::marker::PhantomData<T>,
+}
+
+/// How a single attempt to submit a transaction ended.
+enum AttemptResult<T: Config> {
+    /// The transaction made it into a block and succeeded.
+    Success(ExtrinsicEvents<T>),
+    /// The transaction did not make it into a block, and can be resubmitted.
+    Retry(Error),
+    /// We lost track of the transaction, and it may yet make it into a block. It can be
+    /// resubmitted, but only with the same nonce.
+    Lost(Error),
+}
+
+impl<T, C> ResilientSubmitter<T, C>
+where
+    T: Config,
+    C: OnlineClientT<T>,
+{
+    /// Create a new [`ResilientSubmitter`]. By default, transactions are mortal for 32 blocks,
+    /// give no tip, are submitted at most 5 times, have no deadline, and are waited on until
+    /// they are in a finalized block.
+    pub fn new(client: C) -> Self {
+        ResilientSubmitter {
+            client,
+            tip_policy: TipPolicy::default(),
+            mortal_for: Some(32),
+            max_attempts: 5,
+            deadline: None,
+            wait_for_finalized: true,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Set how the tip given changes on each attempt.
+    pub fn tip_policy(mut self, tip_policy: TipPolicy) -> Self {
+        self.tip_policy = tip_policy;
+        self
+    }
+
+    /// Make each attempt mortal for (roughly; it'll be rounded to a power of two) the given
+    /// number of blocks, starting at the best block at the time of the attempt. If the
+    /// transaction hasn't made it into a block by the time it expires, it's resubmitted.
+    pub fn mortal_for(mut self, n_blocks: u64) -> Self {
+        self.mortal_for = Some(n_blocks);
+        self
+    }
+
+    /// Make each attempt immortal.
+    pub fn immortal(mut self) -> Self {
+        self.mortal_for = None;
+        self
+    }
+
+    /// The maximum number of times to submit the transaction.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Give up if the transaction hasn't made it into a block within the given time.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// If true (the default), wait until the transaction is in a finalized block. If false,
+    /// return as soon as it's in a best block.
+    pub fn wait_for_finalized(mut self, wait_for_finalized: bool) -> Self {
+        self.wait_for_finalized = wait_for_finalized;
+        self
+    }
+
+    /// Submit a transaction, resubmitting it as needed until it makes it into a block, we
+    /// run out of attempts or the deadline passes. The given function is called before each
+    /// attempt to build the parameters for it, and should use the details it's given to set
+    /// the mortality and tip of the transaction.
+    pub async fn submit_with_params<Call, Signer, F>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        params: F,
+    ) -> Result<SubmissionOutcome<T>, Error>
+    where
+        Call: TxPayload,
+        Signer: AsyncSignerT<T>,
+        F: Fn(&Attempt<'_, T>) -> <T::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams,
+    {
+        let attempts = self.submit_with_retries(call, signer, params);
+
+        let Some(deadline) = self.deadline else {
+            return attempts.await;
+        };
+        let attempts = std::pin::pin!(attempts);
+        let deadline = futures_timer::Delay::new(deadline);
+        match future::select(attempts, deadline).await {
+            future::Either::Left((res, _)) => res,
+            future::Either::Right(_) => Err(TransactionError::DeadlineExceeded.into()),
+        }
+    }
+
+    async fn submit_with_retries<Call, Signer, F>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        params: F,
+    ) -> Result<SubmissionOutcome<T>, Error>
+    where
+        Call: TxPayload,
+        Signer: AsyncSignerT<T>,
+        F: Fn(&Attempt<'_, T>) -> <T::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams,
+    {
+        let account_id = signer.account_id();
+        let mut number = 0;
+        // Set to the nonce of the first attempt that we lose track of.
+        let mut nonce = None;
+        loop {
+            let tip = self.tip_policy.tip_for_attempt(number);
+            let res = self
+                .make_attempt(number, tip, &account_id, &mut nonce, call, signer, &params)
+                .await?;
+
+            number += 1;
+            let err = match res {
+                AttemptResult::Success(events) => {
+                    return Ok(SubmissionOutcome {
+                        events,
+                        attempts: number,
+                        tip,
+                    })
+                }
+                AttemptResult::Retry(err) | AttemptResult::Lost(err) => err,
+            };
+
+            if number >= self.max_attempts {
+                return Err(TransactionError::AttemptsExhausted {
+                    attempts: number,
+                    last_error: err.to_string(),
+                }
+                .into());
+            }
+            tracing::debug!("Resubmitting transaction (attempt {number}): {err}");
+        }
+    }
+
+    /// Build, sign and submit a single attempt. Failing to build or sign the transaction
+    /// (for instance because a request to the node failed) counts as a failed attempt.
+    ///
+    /// If `nonce` is given, the transaction uses it. Otherwise, a fresh nonce is fetched, and
+    /// `nonce` is set to it if we lose track of the transaction.
+    #[allow(clippy::too_many_arguments)]
+    async fn make_attempt<Call, Signer, F>(
+        &self,
+        number: u32,
+        tip: u128,
+        account_id: &T::AccountId,
+        nonce: &mut Option<u64>,
+        call: &Call,
+        signer: &Signer,
+        params: &F,
+    ) -> Result<AttemptResult<T>, Error>
+    where
+        Call: TxPayload,
+        Signer: AsyncSignerT<T>,
+        F: Fn(&Attempt<'_, T>) -> <T::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams,
+    {
+        let best_block = match self.client.blocks().at_latest().await {
+            Ok(best_block) => best_block,
+            Err(e) => return Ok(AttemptResult::Retry(e)),
+        };
+        let attempt = Attempt {
+            number,
+            tip,
+            mortal_for: self.mortal_for,
+            best_block: best_block.header(),
+        };
+
+        let account_nonce = match *nonce {
+            Some(nonce) => nonce,
+            None => match self.client.tx().account_nonce(account_id).await {
+                Ok(account_nonce) => account_nonce,
+                Err(e) => return Ok(AttemptResult::Retry(e)),
+            },
+        };
+        let tx = match self
+            .client
+            .tx()
+            .create_signed_with_nonce_async(call, signer, account_nonce, params(&attempt))
+            .await
+        {
+            Ok(tx) => tx,
+            Err(e) => return Ok(AttemptResult::Retry(e)),
+        };
+
+        // Mortal transactions expire once the best block gets past the end of their
+        // mortality period (rounded up to the nearest power of two, as it will be).
+        let expires_at = attempt.mortal_for.map(|n_blocks| {
+            let period = n_blocks
+                .checked_next_power_of_two()
+                .unwrap_or(1 << 16)
+                .clamp(4, 1 << 16);
+            best_block.number().into() + period
+        });
+
+        let res = self.watch_attempt(&tx, expires_at).await?;
+        if let AttemptResult::Lost(_) = res {
+            *nonce = Some(account_nonce);
+        }
+        Ok(res)
+    }
+
+    /// Submit a transaction and wait for it to make it into a block. Errors which mean that
+    /// the transaction should be resubmitted are handed back as [`AttemptResult::Retry`], or
+    /// [`AttemptResult::Lost`] if it may still make it into a block.
+    async fn watch_attempt(
+        &self,
+        tx: &crate::tx::SubmittableExtrinsic<T, C>,
+        expires_at: Option<u64>,
+    ) -> Result<AttemptResult<T>, Error> {
+        enum Event<S> {
+            Status(S),
+            StatusesEnded,
+            BestBlock(u64),
+        }
+
+        let progress = match tx.submit_and_watch().await {
+            Ok(progress) => progress,
+            // The node may have received the transaction if the connection failed.
+            Err(Error::Rpc(e)) if is_transient_error(&e) => {
+                return Ok(AttemptResult::Lost(Error::Rpc(e)))
+            }
+            Err(e) => return Ok(AttemptResult::Retry(e)),
+        };
+        let statuses = progress
+            .map(Event::Status)
+            .chain(stream::once(future::ready(Event::StatusesEnded)));
+        let best_blocks = match expires_at {
+            Some(_) => {
+                let best_blocks = match self.client.backend().stream_best_block_headers().await {
+                    Ok(best_blocks) => best_blocks,
+                    Err(e) => return Ok(AttemptResult::Retry(e)),
+                };
+                best_blocks
+                    .filter_map(|res| async move { res.ok() })
+                    .map(|(header, _)| Event::BestBlock(header.number().into()))
+                    .boxed()
+            }
+            None => stream::pending().boxed(),
+        };
+        let mut events = stream::select(statuses, best_blocks);
+
+        while let Some(event) = events.next().await {
+            let status = match event {
+                Event::Status(Ok(status)) => status,
+                // Having lost track of the transaction, it's only definitely not going to make
+                // it into a block if it's expired.
+                Event::Status(Err(e @ Error::Transaction(TransactionError::Expired))) => {
+                    return Ok(AttemptResult::Retry(e))
+                }
+                Event::Status(Err(e)) => return Ok(AttemptResult::Lost(e)),
+                Event::StatusesEnded => {
+                    return Ok(AttemptResult::Lost(
+                        crate::error::RpcError::SubscriptionDropped.into(),
+                    ))
+                }
+                Event::BestBlock(number) => {
+                    if expires_at.is_some_and(|expires_at| number >= expires_at) {
+                        return Ok(AttemptResult::Retry(TransactionError::Expired.into()));
+                    }
+                    continue;
+                }
+            };
+
+            let in_block = match status {
+                TxStatus::InBestBlock(in_block) if !self.wait_for_finalized => in_block,
+                TxStatus::InFinalizedBlock(in_block) => in_block,
+                TxStatus::Error { message } => {
+                    return Ok(AttemptResult::Lost(TransactionError::Error(message).into()))
+                }
+                TxStatus::Invalid { message } => {
+                    return Ok(AttemptResult::Retry(
+                        TransactionError::Invalid(message).into(),
+                    ))
+                }
+                TxStatus::Dropped { message } => {
+                    return Ok(AttemptResult::Retry(
+                        TransactionError::Dropped(message).into(),
+                    ))
+                }
+                _ => continue,
+            };
+            return in_block
+                .wait_for_success()
+                .await
+                .map(AttemptResult::Success);
+        }
+
+        Ok(AttemptResult::Lost(
+            crate::error::RpcError::SubscriptionDropped.into(),
+        ))
+    }
+}
+
+impl<T, C> ResilientSubmitter<T, C>
+where
+    T: Config<ExtrinsicParams = DefaultExtrinsicParams<T>>,
+    C: OnlineClientT<T>,
+{
+    /// Submit a transaction on a chain which uses [`DefaultExtrinsicParams`], resubmitting it
+    /// as needed until it makes it into a block, we run out of attempts or the deadline passes.
+    pub async fn submit<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+    ) -> Result<SubmissionOutcome<T>, Error>
+    where
+        Call: TxPayload,
+        Signer: AsyncSignerT<T>,
+    {
+        self.submit_with_params(call, signer, |attempt| {
+            let params = DefaultExtrinsicParamsBuilder::new().tip(attempt.tip);
+            match attempt.mortal_for {
+                Some(n_blocks) => params.mortal(attempt.best_block, n_blocks),
+                None => params,
+            }
+            .build()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::test_utils::{header, mock_client, mock_tx_client, FixedSigner, Hash};
+    use crate::backend::TransactionStatus;
+    use codec::Encode;
+
+    #[test]
+    fn tip_policies() {
+        let tips = |policy: TipPolicy| {
+            (0..5)
+                .map(|n| policy.tip_for_attempt(n))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(tips(TipPolicy::Fixed(5)), vec![5, 5, 5, 5, 5]);
+        assert_eq!(
+            tips(TipPolicy::Linear {
+                initial: 1,
+                increment: 10,
+                max: 25
+            }),
+            vec![1, 11, 21, 25, 25]
+        );
+        assert_eq!(
+            tips(TipPolicy::Exponential {
+                initial: 1,
+                multiplier: 3,
+                max: 20
+            }),
+            vec![1, 3, 9, 20, 20]
+        );
+        assert_eq!(
+            TipPolicy::Exponential {
+                initial: 2,
+                multiplier: 2,
+                max: u128::MAX
+            }
+            .tip_for_attempt(u32::MAX),
+            u128::MAX
+        );
+    }
+
+    #[tokio::test]
+    async fn resubmits_dropped_transactions() {
+        let (backend, api) = mock_tx_client().await;
+        let call = crate::dynamic::tx("System", "remark", vec![vec![1u8, 2, 3]]);
+        backend.push_call_response("AccountNonceApi_account_nonce", 7u32.encode());
+        backend.push_call_response("AccountNonceApi_account_nonce", 8u32.encode());
+
+        // The transaction we expect to be built on the second attempt, with a fresh nonce:
+        let expected_tx = api
+            .tx()
+            .create_signed_with_nonce(
+                &call,
+                &FixedSigner,
+                8,
+                DefaultExtrinsicParamsBuilder::new().tip(110).build(),
+            )
+            .unwrap();
+        let block_hash = backend.add_block(
+            header(2, Hash::zero()),
+            vec![expected_tx.encoded().to_vec()],
+        );
+
+        backend.push_transaction_statuses(vec![
+            TransactionStatus::Validated,
+            TransactionStatus::Dropped {
+                message: "dropped".into(),
+            },
+        ]);
+        backend.push_transaction_statuses(vec![
+            TransactionStatus::InBestBlock {
+                hash: block_hash.into(),
+            },
+            TransactionStatus::InFinalizedBlock {
+                hash: block_hash.into(),
+            },
+        ]);
+
+        let submitter = ResilientSubmitter::new(api)
+            .immortal()
+            .tip_policy(TipPolicy::Linear {
+                initial: 100,
+                increment: 10,
+                max: 1000,
+            });
+        let outcome = tokio::spawn(async move { submitter.submit(&call, &FixedSigner).await })
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(outcome.attempts, 2);
+        assert_eq!(outcome.tip, 110);
+        assert_eq!(outcome.events.block_hash(), block_hash);
+        assert_eq!(outcome.events.extrinsic_hash(), expected_tx.hash());
+
+        let submitted = backend.submitted_extrinsics();
+        assert_eq!(submitted.len(), 2);
+        assert_eq!(submitted[1], expected_tx.encoded());
+    }
+
+    #[tokio::test]
+    async fn reuses_the_nonce_of_transactions_it_loses_track_of() {
+        let (backend, api) = mock_tx_client().await;
+        let call = crate::dynamic::tx("System", "remark", vec![vec![1u8, 2, 3]]);
+        backend.push_call_response("AccountNonceApi_account_nonce", 7u32.encode());
+        backend.push_call_response("AccountNonceApi_account_nonce", 8u32.encode());
+
+        // The first attempt may still make it into a block, so the second replaces it:
+        let expected_tx = api
+            .tx()
+            .create_signed_with_nonce(
+                &call,
+                &FixedSigner,
+                7,
+                DefaultExtrinsicParamsBuilder::new().tip(110).build(),
+            )
+            .unwrap();
+        let block_hash = backend.add_block(
+            header(2, Hash::zero()),
+            vec![expected_tx.encoded().to_vec()],
+        );
+
+        backend.push_transaction_statuses_then_drop(vec![TransactionStatus::Validated]);
+        backend.push_transaction_statuses(vec![TransactionStatus::InFinalizedBlock {
+            hash: block_hash.into(),
+        }]);
+
+        let submitter = ResilientSubmitter::new(api)
+            .immortal()
+            .tip_policy(TipPolicy::Linear {
+                initial: 100,
+                increment: 10,
+                max: 1000,
+            });
+        let outcome = submitter.submit(&call, &FixedSigner).await.unwrap();
+
+        assert_eq!(outcome.attempts, 2);
+        assert_eq!(outcome.events.extrinsic_hash(), expected_tx.hash());
+        assert_eq!(backend.submitted_extrinsics()[1], expected_tx.encoded());
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let (backend, api) = mock_tx_client().await;
+        let call = crate::dynamic::tx("System", "remark", vec![vec![1u8, 2, 3]]);
+
+        for _ in 0..3 {
+            backend.push_transaction_statuses(vec![TransactionStatus::Invalid {
+                message: "bad".into(),
+            }]);
+        }
+
+        let err = ResilientSubmitter::new(api)
+            .max_attempts(3)
+            .submit(&call, &FixedSigner)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::Transaction(TransactionError::AttemptsExhausted { attempts: 3, .. })
+        ));
+        assert_eq!(backend.submitted_extrinsics().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn failing_to_build_a_transaction_counts_as_an_attempt() {
+        // No response is given for `AccountNonceApi_account_nonce`, so fetching the
+        // nonce fails each time.
+        let (backend, api) = mock_client("polkadot_metadata_small.scale").await;
+        let call = crate::dynamic::tx("System", "remark", vec![vec![1u8, 2, 3]]);
+
+        let err = ResilientSubmitter::new(api)
+            .max_attempts(2)
+            .submit(&call, &FixedSigner)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::Transaction(TransactionError::AttemptsExhausted { attempts: 2, .. })
+        ));
+        assert!(backend.submitted_extrinsics().is_empty());
+    }
+
+    #[tokio::test]
+    async fn resubmits_expired_transactions() {
+        let (backend, api) = mock_tx_client().await;
+        let call = crate::dynamic::tx("System", "remark", vec![vec![1u8, 2, 3]]);
+
+        // Statuses never progress beyond this, so we rely on the mortality expiring.
+        backend.push_transaction_statuses(vec![TransactionStatus::Validated]);
+        let submitter = ResilientSubmitter::new(api)
+            .mortal_for(4)
+            .max_attempts(1)
+            .deadline(Duration::from_secs(10));
+
+        let submission = submitter.submit(&call, &FixedSigner);
+        let new_blocks = async {
+            // Wait for the transaction to be submitted, and then add blocks until it expires.
+            while backend.submitted_extrinsics().is_empty() {
+                tokio::task::yield_now().await;
+            }
+            for n in 2..=5 {
+                backend.add_block(header(n, Hash::zero()), vec![]);
+            }
+        };
+        let (res, _) = futures::join!(submission, new_blocks);
+
+        assert!(matches!(
+            res.unwrap_err(),
+            Error::Transaction(TransactionError::AttemptsExhausted { last_error, .. })
+                if last_error.contains("mortality period ended")
+        ));
+    }
+}
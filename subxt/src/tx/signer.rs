@@ -5,7 +5,9 @@
 //! A library to **sub**mit e**xt**rinsics to a
 //! [substrate](https://github.com/paritytech/substrate) node via RPC.
 
+use crate::error::Error;
 use crate::Config;
+use async_trait::async_trait;
 
 /// Signing transactions requires a [`Signer`]. This is responsible for
 /// providing the "from" account that the transaction is being signed by,
@@ -24,6 +26,44 @@ pub trait Signer<T: Config> {
     fn sign(&self, signer_payload: &[u8]) -> T::Signature;
 }
 
+/// Like [`Signer`], except that signing can wait on I/O and can fail. Use this to sign
+/// transactions with keys that live somewhere else, such as in a remote key management
+/// service or on a hardware wallet, via methods like [`crate::tx::TxClient::sign_and_submit_async`]
+/// and [`crate::tx::PartialExtrinsic::sign_async`].
+///
+/// Every [`Signer`] is also an [`AsyncSigner`], so those methods accept either.
+#[async_trait]
+pub trait AsyncSigner<T: Config> {
+    /// Return the "from" account ID.
+    fn account_id(&self) -> T::AccountId;
+
+    /// Return the "from" address.
+    fn address(&self) -> T::Address;
+
+    /// Takes a signer payload for an extrinsic, and returns a signature based on it, or
+    /// an error (usually [`Error::Signing`]) if the payload could not be signed.
+    async fn sign(&self, signer_payload: &[u8]) -> Result<T::Signature, Error>;
+}
+
+#[async_trait]
+impl<T, S> AsyncSigner<T> for S
+where
+    T: Config,
+    S: Signer<T> + Sync,
+{
+    fn account_id(&self) -> T::AccountId {
+        Signer::account_id(self)
+    }
+
+    fn address(&self) -> T::Address {
+        Signer::address(self)
+    }
+
+    async fn sign(&self, signer_payload: &[u8]) -> Result<T::Signature, Error> {
+        Ok(Signer::sign(self, signer_payload))
+    }
+}
+
 #[cfg(feature = "substrate-compat")]
 pub use pair_signer::PairSigner;
 
@@ -8,10 +8,17 @@ use crate::error::DecodeError;
 use crate::{
     backend::{BackendExt, BlockRef, TransactionStatus},
     client::{OfflineClientT, OnlineClientT},
-    config::{Config, ExtrinsicParams, ExtrinsicParamsEncoder, Hasher},
-    error::{Error, MetadataError},
-    tx::{Signer as SignerT, TxPayload, TxProgress},
-    utils::{Encoded, PhantomDataSendSync},
+    config::{
+        signed_extensions::{CheckMortality, SignedExtension},
+        Config, ExtrinsicParams, ExtrinsicParamsEncoder, Hasher,
+    },
+    error::Error,
+    metadata::Metadata,
+    tx::{
+        dry_run, AsyncSigner as AsyncSignerT, DryRunOutcome, DryRunReport, FeeDetails,
+        RuntimeDispatchInfo, Signer as SignerT, SigningRequest, TxPayload, TxProgress,
+    },
+    utils::{strip_compact_prefix, Encoded, Era, PhantomDataSendSync},
 };
 use codec::{Compact, Decode, Encode};
 use derivative::Derivative;
@@ -44,19 +51,10 @@ impl<T: Config, C: OfflineClientT<T>> TxClient<T, C> {
     where
         Call: TxPayload,
     {
-        if let Some(details) = call.validation_details() {
-            let expected_hash = self
-                .client
-                .metadata()
-                .pallet_by_name_err(details.pallet_name)?
-                .call_hash(details.call_name)
-                .ok_or_else(|| MetadataError::CallNameNotFound(details.call_name.to_owned()))?;
-
-            if details.hash != expected_hash {
-                return Err(MetadataError::IncompatibleCodegen.into());
-            }
+        match call.validation_details() {
+            Some(details) => details.validate(&self.client.metadata()),
+            None => Ok(()),
         }
-        Ok(())
     }
 
     /// Return the SCALE encoded bytes representing the call data of the transaction.
@@ -132,7 +130,9 @@ impl<T: Config, C: OfflineClientT<T>> TxClient<T, C> {
         Ok(PartialExtrinsic {
             client: self.client.clone(),
             call_data,
-            additional_and_extra_params,
+            extra_params: encode_with(|v| additional_and_extra_params.encode_extra_to(v)),
+            additional_params: encode_with(|v| additional_and_extra_params.encode_additional_to(v)),
+            _marker: PhantomDataSendSync::new(),
         })
     }
 
@@ -160,6 +160,25 @@ impl<T: Config, C: OfflineClientT<T>> TxClient<T, C> {
         // 3. Sign and construct an extrinsic from these details.
         Ok(partial_signed.sign(signer))
     }
+
+    /// Creates a signed extrinsic without submitting it, using an [`AsyncSignerT`] which
+    /// may wait on I/O and may fail to sign it.
+    pub async fn create_signed_with_nonce_async<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        account_nonce: u64,
+        other_params: <T::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams,
+    ) -> Result<SubmittableExtrinsic<T, C>, Error>
+    where
+        Call: TxPayload,
+        Signer: AsyncSignerT<T>,
+    {
+        self.validate(call)?;
+        let partial_signed =
+            self.create_partial_signed_with_nonce(call, account_nonce, other_params)?;
+        partial_signed.sign_async(signer).await
+    }
 }
 
 impl<T, C> TxClient<T, C>
@@ -191,6 +210,63 @@ where
         Ok(account_nonce)
     }
 
+    /// This returns the weight and class of the given call, along with an estimate for what it
+    /// is expected to cost to execute, without needing to construct and sign an extrinsic first.
+    ///
+    /// # Note
+    ///
+    /// The part of the fee which depends on the length of the extrinsic is based on the length
+    /// of the call alone, and so will be a little lower than for a signed extrinsic, which also
+    /// contains an address, signature and signed extensions.
+    pub async fn call_dispatch_info_estimate<Call>(
+        &self,
+        call: &Call,
+    ) -> Result<RuntimeDispatchInfo, Error>
+    where
+        Call: TxPayload,
+    {
+        self.call_transaction_payment_call_api("TransactionPaymentCallApi_query_call_info", call)
+            .await
+    }
+
+    /// This returns a breakdown of what the given call is expected to cost to execute, without
+    /// needing to construct and sign an extrinsic first.
+    ///
+    /// # Note
+    ///
+    /// See [`TxClient::call_dispatch_info_estimate()`] for a note on how this differs from
+    /// estimating the fee of a signed extrinsic.
+    pub async fn call_fee_details_estimate<Call>(&self, call: &Call) -> Result<FeeDetails, Error>
+    where
+        Call: TxPayload,
+    {
+        self.call_transaction_payment_call_api(
+            "TransactionPaymentCallApi_query_call_fee_details",
+            call,
+        )
+        .await
+    }
+
+    // Call some `TransactionPaymentCallApi` method at the latest block, handing it the given call.
+    async fn call_transaction_payment_call_api<R, Call>(
+        &self,
+        method: &str,
+        call: &Call,
+    ) -> Result<R, Error>
+    where
+        R: Decode,
+        Call: TxPayload,
+    {
+        let mut params = self.call_data(call)?;
+        (params.len() as u32).encode_to(&mut params);
+        let latest_block_ref = self.client.backend().latest_best_block_ref().await?;
+
+        self.client
+            .backend()
+            .call_decoding::<R>(method, Some(&params), latest_block_ref.hash())
+            .await
+    }
+
     /// Creates a partial signed extrinsic, without submitting it.
     pub async fn create_partial_signed<Call>(
         &self,
@@ -305,13 +381,107 @@ where
             .submit()
             .await
     }
+
+    /// Creates a signed extrinsic, without submitting it, using an [`AsyncSignerT`] which
+    /// may wait on I/O and may fail to sign it.
+    pub async fn create_signed_async<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        other_params: <T::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams,
+    ) -> Result<SubmittableExtrinsic<T, C>, Error>
+    where
+        Call: TxPayload,
+        Signer: AsyncSignerT<T>,
+    {
+        let account_nonce = self.account_nonce(&signer.account_id()).await?;
+        self.create_signed_with_nonce_async(call, signer, account_nonce, other_params)
+            .await
+    }
+
+    /// Like [`TxClient::sign_and_submit_then_watch_default`], but signs the extrinsic
+    /// using an [`AsyncSignerT`].
+    pub async fn sign_and_submit_then_watch_default_async<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+    ) -> Result<TxProgress<T, C>, Error>
+    where
+        Call: TxPayload,
+        Signer: AsyncSignerT<T>,
+        <T::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams: Default,
+    {
+        self.sign_and_submit_then_watch_async(call, signer, Default::default())
+            .await
+    }
+
+    /// Like [`TxClient::sign_and_submit_then_watch`], but signs the extrinsic
+    /// using an [`AsyncSignerT`].
+    pub async fn sign_and_submit_then_watch_async<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        other_params: <T::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams,
+    ) -> Result<TxProgress<T, C>, Error>
+    where
+        Call: TxPayload,
+        Signer: AsyncSignerT<T>,
+    {
+        self.create_signed_async(call, signer, other_params)
+            .await?
+            .submit_and_watch()
+            .await
+    }
+
+    /// Like [`TxClient::sign_and_submit_default`], but signs the extrinsic
+    /// using an [`AsyncSignerT`].
+    pub async fn sign_and_submit_default_async<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+    ) -> Result<T::Hash, Error>
+    where
+        Call: TxPayload,
+        Signer: AsyncSignerT<T>,
+        <T::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams: Default,
+    {
+        self.sign_and_submit_async(call, signer, Default::default())
+            .await
+    }
+
+    /// Like [`TxClient::sign_and_submit`], but signs the extrinsic using an [`AsyncSignerT`].
+    pub async fn sign_and_submit_async<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        other_params: <T::ExtrinsicParams as ExtrinsicParams<T>>::OtherParams,
+    ) -> Result<T::Hash, Error>
+    where
+        Call: TxPayload,
+        Signer: AsyncSignerT<T>,
+    {
+        self.create_signed_async(call, signer, other_params)
+            .await?
+            .submit()
+            .await
+    }
 }
 
 /// This payload contains the information needed to produce an extrinsic.
 pub struct PartialExtrinsic<T: Config, C> {
     client: C,
     call_data: Vec<u8>,
-    additional_and_extra_params: T::ExtrinsicParams,
+    // The params are stored encoded, since `T::ExtrinsicParams` may not be `Send` or `Sync`
+    // and we'd like to be able to hold onto this while waiting for an async signer.
+    extra_params: Vec<u8>,
+    additional_params: Vec<u8>,
+    _marker: PhantomDataSendSync<T>,
+}
+
+fn encode_with(f: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    f(&mut bytes);
+    bytes
 }
 
 impl<T, C> PartialExtrinsic<T, C>
@@ -327,9 +497,8 @@ where
         F: for<'a> FnOnce(Cow<'a, [u8]>) -> R,
     {
         let mut bytes = self.call_data.clone();
-        self.additional_and_extra_params.encode_extra_to(&mut bytes);
-        self.additional_and_extra_params
-            .encode_additional_to(&mut bytes);
+        bytes.extend(&self.extra_params);
+        bytes.extend(&self.additional_params);
         if bytes.len() > 256 {
             f(Cow::Borrowed(blake2_256(&bytes).as_ref()))
         } else {
@@ -349,6 +518,17 @@ where
         &self.call_data
     }
 
+    /// Create a [`SigningRequest`] for this extrinsic, which can be serialized and handed to
+    /// another (possibly offline) machine to sign. See [`SigningRequest`] for more.
+    pub fn signing_request(&self) -> Result<SigningRequest, Error> {
+        SigningRequest::new(
+            &self.client,
+            &self.call_data,
+            &self.extra_params,
+            &self.additional_params,
+        )
+    }
+
     /// Convert this [`PartialExtrinsic`] into a [`SubmittableExtrinsic`], ready to submit.
     /// The provided `signer` is responsible for providing the "from" address for the transaction,
     /// as well as providing a signature to attach to it.
@@ -362,6 +542,20 @@ where
         self.sign_with_address_and_signature(&signer.address(), &signature)
     }
 
+    /// Convert this [`PartialExtrinsic`] into a [`SubmittableExtrinsic`], ready to submit.
+    /// This is like [`PartialExtrinsic::sign()`], except that the provided `signer` can wait
+    /// on I/O while signing, and can fail to sign.
+    pub async fn sign_async<Signer>(
+        &self,
+        signer: &Signer,
+    ) -> Result<SubmittableExtrinsic<T, C>, Error>
+    where
+        Signer: AsyncSignerT<T>,
+    {
+        let signature = signer.sign(&self.signer_payload()).await?;
+        Ok(self.sign_with_address_and_signature(&signer.address(), &signature))
+    }
+
     /// Convert this [`PartialExtrinsic`] into a [`SubmittableExtrinsic`], ready to submit.
     /// An address, and something representing a signature that can be SCALE encoded, are both
     /// needed in order to construct it. If you have a `Signer` to hand, you can use
@@ -371,35 +565,73 @@ where
         address: &T::Address,
         signature: &T::Signature,
     ) -> SubmittableExtrinsic<T, C> {
-        // Encode the extrinsic (into the format expected by protocol version 4)
-        let extrinsic = {
-            let mut encoded_inner = Vec::new();
-            // "is signed" + transaction protocol version (4)
-            (0b10000000 + 4u8).encode_to(&mut encoded_inner);
-            // from address for signature
-            address.encode_to(&mut encoded_inner);
-            // the signature
-            signature.encode_to(&mut encoded_inner);
-            // attach custom extra params
-            self.additional_and_extra_params
-                .encode_extra_to(&mut encoded_inner);
-            // and now, call data (remembering that it's been encoded already and just needs appending)
-            encoded_inner.extend(&self.call_data);
-            // now, prefix byte length:
-            let len = Compact(
-                u32::try_from(encoded_inner.len()).expect("extrinsic size expected to be <4GB"),
-            );
-            let mut encoded = Vec::new();
-            len.encode_to(&mut encoded);
-            encoded.extend(encoded_inner);
-            encoded
-        };
+        let extrinsic =
+            encode_signed_extrinsic(address, signature, &self.extra_params, &self.call_data);
 
         // Return an extrinsic ready to be submitted.
         SubmittableExtrinsic::from_bytes(self.client.clone(), extrinsic)
     }
 }
 
+/// Encode a signed extrinsic (into the format expected by protocol version 4) from its parts.
+pub(crate) fn encode_signed_extrinsic(
+    address: &impl Encode,
+    signature: &impl Encode,
+    extra_params: &[u8],
+    call_data: &[u8],
+) -> Vec<u8> {
+    let mut encoded_inner = Vec::new();
+    // "is signed" + transaction protocol version (4)
+    (0b10000000 + 4u8).encode_to(&mut encoded_inner);
+    // from address for signature
+    address.encode_to(&mut encoded_inner);
+    // the signature
+    signature.encode_to(&mut encoded_inner);
+    // attach custom extra params
+    encoded_inner.extend(extra_params);
+    // and now, call data (remembering that it's been encoded already and just needs appending)
+    encoded_inner.extend(call_data);
+    // now, prefix byte length:
+    let len =
+        Compact(u32::try_from(encoded_inner.len()).expect("extrinsic size expected to be <4GB"));
+    let mut encoded = Vec::new();
+    len.encode_to(&mut encoded);
+    encoded.extend(encoded_inner);
+    encoded
+}
+
+/// Find the [`Era`] of a signed extrinsic from its bytes, if it has one.
+fn extrinsic_era<T: Config>(metadata: &Metadata, extrinsic: &[u8]) -> Option<Era> {
+    let (_, bytes) = strip_compact_prefix(extrinsic).ok()?;
+    let (first_byte, cursor) = bytes.split_first()?;
+    let is_signed = first_byte & 0b1000_0000 != 0;
+    if !is_signed {
+        return None;
+    }
+
+    let cursor = &mut &*cursor;
+    let skip = |cursor: &mut &[u8], type_id: u32| {
+        scale_decode::visitor::decode_with_visitor(
+            cursor,
+            type_id,
+            metadata.types(),
+            scale_decode::visitor::IgnoreVisitor,
+        )
+        .ok()
+    };
+
+    // Skip the address and signature, and then each signed extension until we find the era.
+    skip(cursor, metadata.extrinsic().address_ty())?;
+    skip(cursor, metadata.extrinsic().signature_ty())?;
+    for ext in metadata.extrinsic().signed_extensions() {
+        if ext.identifier() == <CheckMortality<T> as SignedExtension<T>>::NAME {
+            return Era::decode(cursor).ok();
+        }
+        skip(cursor, ext.extra_ty())?;
+    }
+    None
+}
+
 /// This represents an extrinsic that has been signed and is ready to submit.
 pub struct SubmittableExtrinsic<T, C> {
     client: C,
@@ -464,7 +696,13 @@ where
             .submit_transaction(&self.encoded.0)
             .await?;
 
-        Ok(TxProgress::new(sub, self.client.clone(), ext_hash))
+        // If the transaction is mortal, we can look for it in finalized blocks until it
+        // expires if we lose track of it.
+        let progress = TxProgress::new(sub, self.client.clone(), ext_hash);
+        match extrinsic_era::<T>(&self.client.metadata(), self.encoded()) {
+            Some(era) => Ok(progress.with_lazy_finalized_block_fallback(era)),
+            None => Ok(progress),
+        }
     }
 
     /// Submits the extrinsic to the chain for block inclusion.
@@ -542,25 +780,84 @@ where
         ValidationResult::try_from_bytes(res)
     }
 
+    /// Dry run the extrinsic at the latest block, to find out whether it would be included in a
+    /// block and whether the call would succeed, without submitting it (and so without paying any
+    /// fees).
+    ///
+    /// If the runtime supports the `DryRunApi`, the events that the extrinsic would emit are
+    /// returned too. No runtime API reports the storage changes that an extrinsic would make, and
+    /// so these are not returned.
+    pub async fn dry_run(&self) -> Result<DryRunReport<T>, Error> {
+        let latest_block_ref = self.client.backend().latest_best_block_ref().await?;
+        self.dry_run_at(latest_block_ref).await
+    }
+
+    /// Dry run the extrinsic at the given block. See [`SubmittableExtrinsic::dry_run()`].
+    pub async fn dry_run_at(
+        &self,
+        at: impl Into<BlockRef<T::Hash>>,
+    ) -> Result<DryRunReport<T>, Error> {
+        let block_hash = at.into().hash();
+        let metadata = self.client.metadata();
+
+        let res = self
+            .client
+            .backend()
+            .call(
+                "BlockBuilder_apply_extrinsic",
+                Some(self.encoded()),
+                block_hash,
+            )
+            .await?;
+        let outcome = DryRunOutcome::decode_from(&res, metadata.clone())?;
+
+        let events = if dry_run::supports_dry_run_api(&metadata) {
+            let method = format!("{}_{}", dry_run::DRY_RUN_API, dry_run::DRY_RUN_EXTRINSIC);
+            let res = self
+                .client
+                .backend()
+                .call(&method, Some(self.encoded()), block_hash)
+                .await?;
+            dry_run::decode_dry_run_events(&res, &metadata, self.hash(), block_hash)?
+        } else {
+            None
+        };
+
+        Ok(DryRunReport::new(outcome, events))
+    }
+
     /// This returns an estimate for what the extrinsic is expected to cost to execute, less any tips.
     /// The actual amount paid can vary from block to block based on node traffic and other factors.
     pub async fn partial_fee_estimate(&self) -> Result<u128, Error> {
+        Ok(self.dispatch_info_estimate().await?.partial_fee)
+    }
+
+    /// This returns the weight and class of the extrinsic, along with an estimate for what it is
+    /// expected to cost to execute, less any tips. The actual amount paid can vary from block to
+    /// block based on node traffic and other factors.
+    pub async fn dispatch_info_estimate(&self) -> Result<RuntimeDispatchInfo, Error> {
+        self.call_transaction_payment_api("TransactionPaymentApi_query_info")
+            .await
+    }
+
+    /// This returns a breakdown of what the extrinsic is expected to cost to execute, including
+    /// any tip. The actual amount paid can vary from block to block based on node traffic and
+    /// other factors.
+    pub async fn fee_details_estimate(&self) -> Result<FeeDetails, Error> {
+        self.call_transaction_payment_api("TransactionPaymentApi_query_fee_details")
+            .await
+    }
+
+    // Call some `TransactionPaymentApi` method at the latest block, handing it this extrinsic.
+    async fn call_transaction_payment_api<R: Decode>(&self, method: &str) -> Result<R, Error> {
         let mut params = self.encoded().to_vec();
         (self.encoded().len() as u32).encode_to(&mut params);
         let latest_block_ref = self.client.backend().latest_best_block_ref().await?;
 
-        // destructuring RuntimeDispatchInfo, see type information <https://paritytech.github.io/substrate/master/pallet_transaction_payment_rpc_runtime_api/struct.RuntimeDispatchInfo.html>
-        // data layout: {weight_ref_time: Compact<u64>, weight_proof_size: Compact<u64>, class: u8, partial_fee: u128}
-        let (_, _, _, partial_fee) = self
-            .client
+        self.client
             .backend()
-            .call_decoding::<(Compact<u64>, Compact<u64>, u8, u128)>(
-                "TransactionPaymentApi_query_info",
-                Some(&params),
-                latest_block_ref.hash(),
-            )
-            .await?;
-        Ok(partial_fee)
+            .call_decoding::<R>(method, Some(&params), latest_block_ref.hash())
+            .await
     }
 }
 
@@ -824,4 +1121,82 @@ mod test {
             assert_eq!(decoded, validation_result);
         }
     }
+
+    mod async_signing {
+        use super::*;
+        use crate::backend::test_utils::{mock_tx_client, FixedSigner};
+        use crate::utils::{AccountId32, MultiSignature};
+        use crate::PolkadotConfig;
+
+        /// Fails to sign anything.
+        struct FailingSigner;
+
+        #[async_trait::async_trait]
+        impl AsyncSignerT<PolkadotConfig> for FailingSigner {
+            fn account_id(&self) -> AccountId32 {
+                AccountId32([1; 32])
+            }
+            fn address(&self) -> <PolkadotConfig as Config>::Address {
+                AccountId32([1; 32]).into()
+            }
+            async fn sign(&self, _signer_payload: &[u8]) -> Result<MultiSignature, Error> {
+                Err(Error::Signing("the device refused to sign".into()))
+            }
+        }
+
+        #[tokio::test]
+        async fn signers_can_be_used_asynchronously() {
+            let (backend, api) = mock_tx_client().await;
+            let call = crate::dynamic::tx("System", "remark", vec![vec![1u8, 2, 3]]);
+
+            let tx_client = api.tx();
+            let sync_tx = tx_client
+                .create_signed(&call, &FixedSigner, Default::default())
+                .await
+                .unwrap();
+            let async_tx = tx_client
+                .create_signed_async(&call, &FixedSigner, Default::default())
+                .await
+                .unwrap();
+            assert_eq!(sync_tx.encoded(), async_tx.encoded());
+
+            api.tx()
+                .sign_and_submit_default_async(&call, &FixedSigner)
+                .await
+                .unwrap();
+            assert_eq!(backend.submitted_extrinsics().len(), 1);
+        }
+
+        #[tokio::test]
+        async fn async_signing_can_be_spawned() {
+            let (backend, api) = mock_tx_client().await;
+            let call = crate::dynamic::tx("System", "remark", vec![vec![1u8, 2, 3]]);
+
+            tokio::spawn(async move {
+                api.tx()
+                    .sign_and_submit_default_async(&call, &FixedSigner)
+                    .await
+            })
+            .await
+            .unwrap()
+            .unwrap();
+            assert_eq!(backend.submitted_extrinsics().len(), 1);
+        }
+
+        #[tokio::test]
+        async fn signing_errors_are_returned() {
+            let (backend, api) = mock_tx_client().await;
+            let call = crate::dynamic::tx("System", "remark", vec![vec![1u8, 2, 3]]);
+
+            let err = api
+                .tx()
+                .sign_and_submit_default_async(&call, &FailingSigner)
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(err, Error::Signing(e) if e.to_string() == "the device refused to sign")
+            );
+            assert!(backend.submitted_extrinsics().is_empty());
+        }
+    }
 }
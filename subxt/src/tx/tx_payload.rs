@@ -6,11 +6,12 @@
 //! transactions that can be submitted.
 
 use crate::{
-    dynamic::Value,
+    dynamic::{DecodedValue, Value},
     error::{Error, MetadataError},
     metadata::Metadata,
 };
 use codec::Encode;
+use scale_decode::DecodeAsType;
 use scale_encode::EncodeAsFields;
 use scale_value::{Composite, ValueDef, Variant};
 use std::{borrow::Cow, sync::Arc};
@@ -47,6 +48,21 @@ pub struct ValidationDetails<'a> {
     pub hash: [u8; 32],
 }
 
+impl ValidationDetails<'_> {
+    /// Check that the hash of the call lines up with the call in the metadata given.
+    pub(crate) fn validate(&self, metadata: &Metadata) -> Result<(), Error> {
+        let expected_hash = metadata
+            .pallet_by_name_err(self.pallet_name)?
+            .call_hash(self.call_name)
+            .ok_or_else(|| MetadataError::CallNameNotFound(self.call_name.to_owned()))?;
+
+        if self.hash != expected_hash {
+            return Err(MetadataError::IncompatibleCodegen.into());
+        }
+        Ok(())
+    }
+}
+
 /// A transaction payload containing some generic `CallData`.
 #[derive(Clone, Debug)]
 pub struct Payload<CallData> {
@@ -181,6 +197,25 @@ impl<CallData: EncodeAsFields> TxPayload for Payload<CallData> {
     }
 }
 
+/// Encode some call and decode it back into a [`Value`] of the runtime's outer call enum type, so
+/// that it can be given as an argument to another call which dispatches it. Since the outer
+/// call isn't validated, this validates the given call first if possible.
+pub(crate) fn call_to_outer_enum_value<Call: TxPayload + ?Sized>(
+    call: &Call,
+    metadata: &Metadata,
+) -> Result<Value<()>, Error> {
+    if let Some(details) = call.validation_details() {
+        details.validate(metadata)?;
+    }
+    let call_data = call.encode_call_data(metadata)?;
+    let value = DecodedValue::decode_as_type(
+        &mut &*call_data,
+        metadata.outer_enums().call_enum_ty(),
+        metadata.types(),
+    )?;
+    Ok(value.remove_context())
+}
+
 /// Construct a transaction at runtime; essentially an alias to [`Payload::new()`]
 /// which provides a [`Composite`] value for the call data.
 pub fn dynamic(
@@ -6,22 +6,27 @@
 
 use std::task::Poll;
 
-use crate::utils::strip_compact_prefix;
+use crate::utils::{strip_compact_prefix, Era};
 use crate::{
-    backend::{StreamOfResults, TransactionStatus as BackendTxStatus},
+    backend::{BlockRef, StreamOfResults, TransactionStatus as BackendTxStatus},
+    blocks::Block,
     client::OnlineClientT,
+    config::{Hasher, Header},
     error::{DispatchError, Error, RpcError, TransactionError},
     events::EventsClient,
     Config,
 };
 use derivative::Derivative;
-use futures::{Stream, StreamExt};
+use futures::{stream::BoxStream, Stream, StreamExt};
 
 /// This struct represents a subscription to the progress of some transaction.
 pub struct TxProgress<T: Config, C> {
     sub: Option<StreamOfResults<BackendTxStatus<T::Hash>>>,
     ext_hash: T::Hash,
     client: C,
+    // If set, this is used to look for the transaction in finalized blocks
+    // if the subscription above ends early or fails.
+    fallback: Option<BoxStream<'static, Result<TxStatus<T, C>, Error>>>,
 }
 
 impl<T: Config, C> std::fmt::Debug for TxProgress<T, C> {
@@ -50,6 +55,7 @@ impl<T: Config, C> TxProgress<T, C> {
             sub: Some(sub),
             client,
             ext_hash,
+            fallback: None,
         }
     }
 
@@ -59,6 +65,154 @@ impl<T: Config, C> TxProgress<T, C> {
     }
 }
 
+impl<T, C> TxProgress<T, C>
+where
+    T: Config,
+    C: OnlineClientT<T>,
+{
+    /// If the subscription to the transaction status fails or ends before the transaction is
+    /// finalized (for instance because the connection to the node was lost), follow finalized
+    /// blocks to find out whether the transaction made it into one instead. This will emit a
+    /// [`TxStatus::InFinalizedBlock`] if the transaction is found, or a
+    /// [`TransactionError::Expired`] error once the mortality period given by `era`, which
+    /// began at block number `birth_block_number`, has ended without it being found.
+    ///
+    /// `birth_block_number` can be the number of any block within the mortality period, but
+    /// is normally the number of the block that the transaction was made mortal from.
+    ///
+    /// Finalized blocks from the start of the mortality period are also checked when this
+    /// starts, in case the transaction was finalized while the subscription was down.
+    ///
+    /// This has no effect for immortal transactions, since we could wait forever for them.
+    /// [`crate::tx::SubmittableExtrinsic::submit_and_watch`] enables this automatically for
+    /// mortal transactions.
+    pub fn with_finalized_block_fallback(self, era: Era, birth_block_number: u64) -> Self {
+        self.with_fallback(era, Some(birth_block_number))
+    }
+
+    /// Like [`TxProgress::with_finalized_block_fallback`], but the mortality period is worked
+    /// out from the best block at the time that the fallback begins, which saves fetching it
+    /// up front. The best block may have moved on to the next mortality period by then, so
+    /// the period before that is also checked for the transaction.
+    pub(crate) fn with_lazy_finalized_block_fallback(self, era: Era) -> Self {
+        self.with_fallback(era, None)
+    }
+
+    fn with_fallback(mut self, era: Era, birth_block_number: Option<u64>) -> Self {
+        if era == Era::Immortal {
+            return self;
+        }
+        let fut =
+            find_in_finalized_blocks(self.client.clone(), self.ext_hash, era, birth_block_number);
+        self.fallback = Some(futures::stream::once(fut).boxed());
+        self
+    }
+}
+
+/// Follow finalized blocks, looking for the given transaction, until it's found or the given
+/// era ends. If no `birth_block_number` is given, the era is measured from the current best
+/// block.
+async fn find_in_finalized_blocks<T, C>(
+    client: C,
+    ext_hash: T::Hash,
+    era: Era,
+    birth_block_number: Option<u64>,
+) -> Result<TxStatus<T, C>, Error>
+where
+    T: Config,
+    C: OnlineClientT<T>,
+{
+    let Era::Mortal { period, .. } = era else {
+        return Err(TransactionError::Expired.into());
+    };
+
+    let blocks = client.blocks();
+
+    // The transaction can only be in blocks from `oldest_number` up to `expires_at`.
+    let (oldest_number, expires_at) = match birth_block_number {
+        Some(n) => (era.birth(n), era.death(n)),
+        None => {
+            let best_number: u64 = blocks.at_latest().await?.number().into();
+            let oldest_number = era.birth(best_number).saturating_sub(period);
+            (oldest_number, era.death(best_number))
+        }
+    };
+
+    let mut finalized_blocks = blocks.subscribe_finalized().await?;
+    let Some(first_block) = finalized_blocks.next().await else {
+        return Err(RpcError::SubscriptionDropped.into());
+    };
+    let first_block = first_block?;
+
+    // Check back through the finalized blocks that the transaction could be in first, in
+    // case it was finalized while we weren't watching.
+    let first_number: u64 = first_block.number().into();
+    let mut block = first_block;
+    loop {
+        let number: u64 = block.number().into();
+        if number < expires_at {
+            if let Some(status) = find_in_block(&client, &block, ext_hash).await? {
+                return Ok(status);
+            }
+        }
+        if number <= oldest_number {
+            break;
+        }
+        let Some(parent_hash) = block.header().parent_hash() else {
+            break;
+        };
+        // The node may not have older blocks to hand, so just check what we can.
+        block = match blocks.at(parent_hash).await {
+            Ok(block) => block,
+            Err(e) => {
+                tracing::debug!("Stopped looking back through finalized blocks: {e}");
+                break;
+            }
+        };
+    }
+
+    if first_number >= expires_at {
+        return Err(TransactionError::Expired.into());
+    }
+
+    // Now, follow new finalized blocks until the transaction is found or expires.
+    while let Some(block) = finalized_blocks.next().await {
+        let block = block?;
+        if let Some(status) = find_in_block(&client, &block, ext_hash).await? {
+            return Ok(status);
+        }
+        if block.number().into() >= expires_at {
+            return Err(TransactionError::Expired.into());
+        }
+    }
+
+    Err(RpcError::SubscriptionDropped.into())
+}
+
+/// Return [`TxStatus::InFinalizedBlock`] if the transaction is in the given block.
+async fn find_in_block<T, C>(
+    client: &C,
+    block: &Block<T, C>,
+    ext_hash: T::Hash,
+) -> Result<Option<TxStatus<T, C>>, Error>
+where
+    T: Config,
+    C: OnlineClientT<T>,
+{
+    let extrinsics = block.extrinsics().await?;
+    let is_in_block = extrinsics
+        .iter()
+        .filter_map(|ext| ext.ok())
+        .any(|ext| T::Hasher::hash_of(&ext.bytes()) == ext_hash);
+
+    if !is_in_block {
+        return Ok(None);
+    }
+    let status =
+        TxStatus::InFinalizedBlock(TxInBlock::new(block.reference(), ext_hash, client.clone()));
+    Ok(Some(status))
+}
+
 impl<T, C> TxProgress<T, C>
 where
     T: Config,
@@ -158,12 +312,36 @@ impl<T: Config, C: Clone> Stream for TxProgress<T, C> {
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
-        let sub = match self.sub.as_mut() {
-            Some(sub) => sub,
-            None => return Poll::Ready(None),
+        if self.sub.is_none() {
+            return match self.fallback.as_mut() {
+                Some(fallback) => fallback.poll_next_unpin(cx),
+                None => Poll::Ready(None),
+            };
+        }
+        let sub = self.sub.as_mut().expect("checked above; qed");
+
+        let status = match sub.poll_next_unpin(cx) {
+            Poll::Ready(Some(Ok(status))) => Poll::Ready(Some(Ok(status))),
+            // The subscription failed or ended before the transaction was finalized, so
+            // fall back to looking for it in finalized blocks if we're able to.
+            Poll::Ready(Some(Err(e))) if self.fallback.is_some() => {
+                tracing::debug!(
+                    "Transaction status subscription failed ({e}); looking for the transaction in finalized blocks instead"
+                );
+                self.sub = None;
+                return self.poll_next(cx);
+            }
+            Poll::Ready(None) if self.fallback.is_some() => {
+                tracing::debug!(
+                    "Transaction status subscription ended early; looking for the transaction in finalized blocks instead"
+                );
+                self.sub = None;
+                return self.poll_next(cx);
+            }
+            other => other,
         };
 
-        sub.poll_next_unpin(cx).map_ok(|status| {
+        status.map_ok(|status| {
             match status {
                 BackendTxStatus::Validated => TxStatus::Validated,
                 BackendTxStatus::Broadcasted { num_peers } => TxStatus::Broadcasted { num_peers },
@@ -173,6 +351,7 @@ impl<T: Config, C: Clone> Stream for TxProgress<T, C> {
                 // These stream events mean that nothing further will be sent:
                 BackendTxStatus::InFinalizedBlock { hash } => {
                     self.sub = None;
+                    self.fallback = None;
                     TxStatus::InFinalizedBlock(TxInBlock::new(
                         hash,
                         self.ext_hash,
@@ -181,14 +360,17 @@ impl<T: Config, C: Clone> Stream for TxProgress<T, C> {
                 }
                 BackendTxStatus::Error { message } => {
                     self.sub = None;
+                    self.fallback = None;
                     TxStatus::Error { message }
                 }
                 BackendTxStatus::Invalid { message } => {
                     self.sub = None;
+                    self.fallback = None;
                     TxStatus::Invalid { message }
                 }
                 BackendTxStatus::Dropped { message } => {
                     self.sub = None;
+                    self.fallback = None;
                     TxStatus::Dropped { message }
                 }
             }
@@ -252,15 +434,15 @@ impl<T: Config, C> TxStatus<T, C> {
 #[derive(Derivative)]
 #[derivative(Debug(bound = "C: std::fmt::Debug"))]
 pub struct TxInBlock<T: Config, C> {
-    block_hash: T::Hash,
+    block_ref: BlockRef<T::Hash>,
     ext_hash: T::Hash,
     client: C,
 }
 
 impl<T: Config, C> TxInBlock<T, C> {
-    pub(crate) fn new(block_hash: T::Hash, ext_hash: T::Hash, client: C) -> Self {
+    pub(crate) fn new(block_ref: BlockRef<T::Hash>, ext_hash: T::Hash, client: C) -> Self {
         Self {
-            block_hash,
+            block_ref,
             ext_hash,
             client,
         }
@@ -268,7 +450,7 @@ impl<T: Config, C> TxInBlock<T, C> {
 
     /// Return the hash of the block that the transaction has made it into.
     pub fn block_hash(&self) -> T::Hash {
-        self.block_hash
+        self.block_ref.hash()
     }
 
     /// Return the hash of the extrinsic that was submitted.
@@ -317,7 +499,7 @@ impl<T: Config, C: OnlineClientT<T>> TxInBlock<T, C> {
         let block_body = self
             .client
             .backend()
-            .block_body(self.block_hash)
+            .block_body(self.block_ref.hash())
             .await?
             .ok_or(Error::Transaction(TransactionError::BlockNotFound))?;
 
@@ -336,7 +518,7 @@ impl<T: Config, C: OnlineClientT<T>> TxInBlock<T, C> {
             .ok_or(Error::Transaction(TransactionError::BlockNotFound))?;
 
         let events = EventsClient::new(self.client.clone())
-            .at(self.block_hash)
+            .at(self.block_ref.clone())
             .await?;
 
         Ok(crate::blocks::ExtrinsicEvents::new(
@@ -429,6 +611,135 @@ mod test {
         ));
     }
 
+    mod finalized_block_fallback {
+        use crate::backend::test_utils::{first_block_hash, header, mock_client, FixedSigner};
+        use crate::backend::{mock::MockBackend, TransactionStatus};
+        use crate::config::substrate::H256;
+        use crate::config::DefaultExtrinsicParamsBuilder;
+        use crate::error::TransactionError;
+        use crate::tx::{SubmittableExtrinsic, TxProgress};
+        use crate::utils::Era;
+        use crate::{Error, OnlineClient, PolkadotConfig};
+
+        type Client = OnlineClient<PolkadotConfig>;
+
+        /// Create a client whose best and finalized block is block 1, and a
+        /// transaction which is mortal from that block for the given number of blocks.
+        async fn setup(
+            mortal_for: u64,
+        ) -> (
+            MockBackend<PolkadotConfig>,
+            SubmittableExtrinsic<PolkadotConfig, Client>,
+        ) {
+            // This is the block that the mock client starts with.
+            let first_block = header(1, H256::zero());
+            let (backend, api) = mock_client("polkadot_metadata_small.scale").await;
+
+            let call = crate::dynamic::tx("System", "remark", vec![vec![1u8, 2, 3]]);
+            let params = DefaultExtrinsicParamsBuilder::new()
+                .mortal(&first_block, mortal_for)
+                .build();
+            let tx = api
+                .tx()
+                .create_signed_with_nonce(&call, &FixedSigner, 0, params)
+                .unwrap();
+            (backend, tx)
+        }
+
+        #[tokio::test]
+        async fn finds_transaction_after_subscription_is_lost() {
+            let (backend, tx) = setup(8).await;
+            backend.push_transaction_statuses_then_drop(vec![TransactionStatus::Validated]);
+
+            // The transaction is finalized in block 2 while the subscription is down,
+            // and so we'll have to look back from block 3 to find it.
+            let block_1 = first_block_hash();
+            let block_2 = backend.add_block(header(2, block_1), vec![tx.encoded().to_vec()]);
+            backend.add_block(header(3, block_2), vec![]);
+
+            let events = tx
+                .submit_and_watch()
+                .await
+                .unwrap()
+                .wait_for_finalized_success()
+                .await
+                .unwrap();
+            assert_eq!(events.block_hash(), block_2);
+            assert_eq!(events.extrinsic_hash(), tx.hash());
+        }
+
+        /// Submit the transaction, and look for it in finalized blocks if the subscription
+        /// is lost, telling the fallback that it was born at block 1.
+        async fn submit_born_at_block_1(
+            tx: &SubmittableExtrinsic<PolkadotConfig, Client>,
+            mortal_for: u64,
+        ) -> TxProgress<PolkadotConfig, Client> {
+            tx.submit_and_watch()
+                .await
+                .unwrap()
+                .with_finalized_block_fallback(Era::mortal(mortal_for, 1), 1)
+        }
+
+        #[tokio::test]
+        async fn gives_up_once_transaction_expires() {
+            let (backend, tx) = setup(4).await;
+            backend.push_transaction_statuses_then_drop(vec![TransactionStatus::Validated]);
+
+            let progress = submit_born_at_block_1(&tx, 4).await;
+            let wait_for_finalized = progress.wait_for_finalized();
+            let new_blocks = async {
+                let mut parent_hash = first_block_hash();
+                for n in 2..=6 {
+                    tokio::task::yield_now().await;
+                    parent_hash = backend.add_block(header(n, parent_hash), vec![]);
+                }
+            };
+            let (res, _) = futures::join!(wait_for_finalized, new_blocks);
+
+            assert!(matches!(
+                res,
+                Err(Error::Transaction(TransactionError::Expired))
+            ));
+        }
+
+        #[tokio::test]
+        async fn expiry_is_relative_to_the_birth_block() {
+            let (backend, tx) = setup(4).await;
+            backend.push_transaction_statuses_then_drop(vec![TransactionStatus::Validated]);
+            let progress = submit_born_at_block_1(&tx, 4).await;
+
+            // The transaction expires at block 5, and the chain has moved on past that by
+            // the time that we start looking through finalized blocks.
+            let mut parent_hash = first_block_hash();
+            for n in 2..=6 {
+                parent_hash = backend.add_block(header(n, parent_hash), vec![]);
+            }
+
+            assert!(matches!(
+                progress.wait_for_finalized().await,
+                Err(Error::Transaction(TransactionError::Expired))
+            ));
+        }
+        #[tokio::test]
+        async fn looks_back_to_the_birth_block() {
+            let (backend, tx) = setup(4).await;
+            backend.push_transaction_statuses_then_drop(vec![TransactionStatus::Validated]);
+            let progress = submit_born_at_block_1(&tx, 4).await;
+
+            // The transaction is finalized in block 2, and more than a mortality period
+            // goes by before we start looking through finalized blocks.
+            let block_1 = first_block_hash();
+            let block_2 = backend.add_block(header(2, block_1), vec![tx.encoded().to_vec()]);
+            let mut parent_hash = block_2;
+            for n in 3..=7 {
+                parent_hash = backend.add_block(header(n, parent_hash), vec![]);
+            }
+
+            let events = progress.wait_for_finalized_success().await.unwrap();
+            assert_eq!(events.block_hash(), block_2);
+        }
+    }
+
     fn mock_tx_progress(statuses: Vec<MockSubstrateTxStatus>) -> MockTxProgress {
         let sub = create_substrate_tx_status_subscription(statuses);
         TxProgress::new(sub, MockClient, Default::default())
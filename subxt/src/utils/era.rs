@@ -55,6 +55,25 @@ impl Era {
             phase: quantized_phase,
         }
     }
+
+    /// Get the block number of the start of the era whose properties this object
+    /// describes that `current` belongs to.
+    pub fn birth(self, current: u64) -> u64 {
+        match self {
+            Self::Immortal => 0,
+            Self::Mortal { period, phase } => {
+                (current.max(phase) - phase) / period * period + phase
+            }
+        }
+    }
+
+    /// Get the block number of the first block at which the era has ended.
+    pub fn death(self, current: u64) -> u64 {
+        match self {
+            Self::Immortal => u64::MAX,
+            Self::Mortal { period, .. } => self.birth(current) + period,
+        }
+    }
 }
 
 // Both copied from `sp_runtime::generic::Era`; this is the wire interface and so
@@ -90,3 +109,68 @@ impl codec::Decode for Era {
         }
     }
 }
+
+// Era is decoded by checking that the type we're targeting is called "Era" and then
+// deferring to `codec::Decode`, which allows it to be decoded from signed extensions.
+impl scale_decode::IntoVisitor for Era {
+    type Visitor = EraVisitor;
+    fn into_visitor() -> Self::Visitor {
+        EraVisitor
+    }
+}
+
+/// A visitor which decodes an [`Era`].
+pub struct EraVisitor;
+
+impl scale_decode::Visitor for EraVisitor {
+    type Value<'scale, 'info> = Era;
+    type Error = scale_decode::Error;
+
+    // Unwrap any newtype wrappers around the era, eg the `CheckMortality` extension (whose
+    // `PhantomData` field doesn't appear in the type information). This allows us to decode
+    // directly from `CheckMortality` into `Era`.
+    fn visit_composite<'scale, 'info>(
+        self,
+        value: &mut scale_decode::visitor::types::Composite<'scale, 'info>,
+        _type_id: scale_decode::visitor::TypeId,
+    ) -> Result<Self::Value<'scale, 'info>, Self::Error> {
+        if value.remaining() != 1 {
+            return Err(scale_decode::Error::custom_string(format!(
+                "Expected any wrapper around Era to have exactly one field, but got {} fields",
+                value.remaining()
+            )));
+        }
+
+        value
+            .decode_item(self)
+            .expect("1 field expected; checked above.")
+    }
+
+    fn unchecked_decode_as_type<'scale, 'info>(
+        self,
+        input: &mut &'scale [u8],
+        type_id: scale_decode::visitor::TypeId,
+        types: &'info scale_info::PortableRegistry,
+    ) -> scale_decode::visitor::DecodeAsTypeResult<
+        Self,
+        Result<Self::Value<'scale, 'info>, Self::Error>,
+    > {
+        use scale_decode::{
+            visitor::{DecodeAsTypeResult, DecodeError},
+            Error,
+        };
+
+        // The runtime `Era` type is an enum with 256 variants which encodes in the same way as
+        // our `Era`, so we check the type name and then defer to the codec implementation.
+        let Some(ty) = types.resolve(type_id.0) else {
+            return DecodeAsTypeResult::Skipped(self);
+        };
+        if ty.path.ident().as_deref() != Some("Era") {
+            return DecodeAsTypeResult::Skipped(self);
+        }
+
+        let decoded = <Era as codec::Decode>::decode(input)
+            .map_err(|e| Error::new(DecodeError::CodecError(e).into()));
+        DecodeAsTypeResult::Decoded(decoded)
+    }
+}
@@ -11,6 +11,7 @@ mod multi_address;
 mod multi_signature;
 mod static_type;
 mod unchecked_extrinsic;
+mod weight;
 mod wrapper_opaque;
 
 use codec::{Compact, Decode, Encode};
@@ -22,6 +23,7 @@ pub use multi_address::MultiAddress;
 pub use multi_signature::MultiSignature;
 pub use static_type::Static;
 pub use unchecked_extrinsic::UncheckedExtrinsic;
+pub use weight::Weight;
 pub use wrapper_opaque::WrapperKeepOpaque;
 
 // Used in codegen
@@ -47,6 +49,35 @@ pub(crate) fn strip_compact_prefix(bytes: &[u8]) -> Result<(u64, &[u8]), codec::
     Ok((val.0, *cursor))
 }
 
+/// Derive an account id from some entropy (typically a hash) in the same way that Substrate
+/// does when deriving keyless accounts, such as multisig and pure proxy accounts. This fails
+/// only if the account id type can't be decoded from any bytes at all.
+pub(crate) fn account_id_from_entropy<AccountId: Decode>(
+    entropy: &[u8],
+) -> Result<AccountId, codec::Error> {
+    AccountId::decode(&mut TrailingZeroInput(entropy))
+}
+
+/// Input that adds infinite number of zero after wrapped input. This is the same as
+/// `sp_runtime::TrailingZeroInput`, and is used to derive account ids from hashes.
+struct TrailingZeroInput<'a>(&'a [u8]);
+
+impl codec::Input for TrailingZeroInput<'_> {
+    fn remaining_len(&mut self) -> Result<Option<usize>, codec::Error> {
+        Ok(None)
+    }
+
+    fn read(&mut self, into: &mut [u8]) -> Result<(), codec::Error> {
+        let len_from_inner = into.len().min(self.0.len());
+        into[..len_from_inner].copy_from_slice(&self.0[..len_from_inner]);
+        for i in &mut into[len_from_inner..] {
+            *i = 0;
+        }
+        self.0 = &self.0[len_from_inner..];
+        Ok(())
+    }
+}
+
 /// A version of [`std::marker::PhantomData`] that is also Send and Sync (which is fine
 /// because regardless of the generic param, it is always possible to Send + Sync this
 /// 0 size type).
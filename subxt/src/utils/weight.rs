@@ -0,0 +1,50 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use codec::{Decode, Encode};
+use scale_value::Value;
+
+/// The weight of some call, or a limit on it. This is a simplified version of Substrate's
+/// `sp_weights::Weight`, and is SCALE encoded in the same way.
+#[derive(
+    Clone,
+    Copy,
+    Default,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Encode,
+    Decode,
+    Debug,
+    scale_encode::EncodeAsType,
+    scale_decode::DecodeAsType,
+)]
+pub struct Weight {
+    /// The weight of computational time used.
+    #[codec(compact)]
+    pub ref_time: u64,
+    /// The weight of storage space used by the proof of validity.
+    #[codec(compact)]
+    pub proof_size: u64,
+}
+
+impl Weight {
+    /// Create a new [`Weight`].
+    pub fn from_parts(ref_time: u64, proof_size: u64) -> Self {
+        Weight {
+            ref_time,
+            proof_size,
+        }
+    }
+}
+
+impl From<Weight> for Value {
+    fn from(weight: Weight) -> Self {
+        Value::named_composite([
+            ("ref_time", Value::u128(weight.ref_time as u128)),
+            ("proof_size", Value::u128(weight.proof_size as u128)),
+        ])
+    }
+}
@@ -128,6 +128,45 @@ async fn validation_fails() {
     );
 }
 
+#[tokio::test]
+async fn dry_run_reports_dispatch_errors() {
+    let ctx = test_context().await;
+    let api = ctx.client();
+
+    wait_for_blocks(&api).await;
+
+    let alice = dev::alice();
+    let bob = dev::bob();
+
+    // A transfer which succeeds:
+    let tx = node_runtime::tx()
+        .balances()
+        .transfer(bob.public_key().into(), 10_000);
+    let signed_extrinsic = api
+        .tx()
+        .create_signed(&tx, &alice, Default::default())
+        .await
+        .unwrap();
+    let result = signed_extrinsic.dry_run().await.unwrap();
+    assert!(result.is_success(), "{:?}", result.outcome());
+
+    // A transfer of more than Alice has, which fails to dispatch:
+    let tx = node_runtime::tx()
+        .balances()
+        .transfer(bob.public_key().into(), u128::MAX);
+    let signed_extrinsic = api
+        .tx()
+        .create_signed(&tx, &alice, Default::default())
+        .await
+        .unwrap();
+    let result = signed_extrinsic.dry_run().await.unwrap();
+    let subxt::tx::DryRunOutcome::DispatchError(DispatchError::Module(err)) = result.outcome()
+    else {
+        panic!("expected a module error, got {:?}", result.outcome());
+    };
+    assert_eq!(err.details().unwrap().pallet.name(), "Balances");
+}
+
 #[tokio::test]
 async fn external_signing() {
     let ctx = test_context().await;
@@ -349,3 +388,68 @@ async fn partial_fee_estimate_correct() {
     // Both methods should yield the same fee
     assert_eq!(partial_fee_1, partial_fee_2);
 }
+
+#[tokio::test]
+async fn fee_estimates_are_consistent() {
+    let ctx = test_context().await;
+    let api = ctx.client();
+
+    let alice = dev::alice();
+    let bob = dev::bob();
+    let tx = node_runtime::tx()
+        .balances()
+        .transfer(bob.public_key().into(), 1_000_000_000_000);
+
+    let signed_extrinsic = api
+        .tx()
+        .create_signed(&tx, &alice, Default::default())
+        .await
+        .unwrap();
+
+    let dispatch_info = signed_extrinsic.dispatch_info_estimate().await.unwrap();
+    let fee_details = signed_extrinsic.fee_details_estimate().await.unwrap();
+    assert_eq!(
+        fee_details.inclusion_fee.unwrap().inclusion_fee(),
+        dispatch_info.partial_fee
+    );
+    assert_eq!(dispatch_info.class, subxt::tx::DispatchClass::Normal);
+
+    // Estimating the fee for the bare call gives the same weight, but a
+    // smaller length fee since there is no signature and so on:
+    let call_dispatch_info = api.tx().call_dispatch_info_estimate(&tx).await.unwrap();
+    let call_fee_details = api.tx().call_fee_details_estimate(&tx).await.unwrap();
+    assert_eq!(call_dispatch_info.weight, dispatch_info.weight);
+    assert!(
+        call_fee_details.inclusion_fee.unwrap().len_fee
+            < fee_details.inclusion_fee.unwrap().len_fee
+    );
+}
+
+#[tokio::test]
+async fn unstable_backend_conforms_to_legacy_backend() {
+    use subxt::backend::{
+        conformance::ConformanceSuite, legacy::LegacyBackend, unstable::UnstableBackend,
+    };
+    use subxt::SubstrateConfig;
+
+    let ctx = test_context().await;
+
+    let reference = LegacyBackend::<SubstrateConfig>::new(ctx.rpc_client().await);
+    let (candidate, mut driver) =
+        UnstableBackend::<SubstrateConfig>::builder().build(ctx.rpc_client().await);
+    tokio::spawn(async move { while driver.next().await.is_some() {} });
+
+    let account_root = node_runtime::storage().system().account_iter();
+    let account_key = node_runtime::storage()
+        .system()
+        .account(dev::alice().public_key().to_account_id());
+    let api = ctx.client();
+
+    let report = ConformanceSuite::<SubstrateConfig, _, _>::new(&reference, &candidate)
+        .storage_key(api.storage().address_bytes(&account_key).unwrap())
+        .storage_prefix(account_root.to_root_bytes())
+        .run()
+        .await;
+
+    report.assert_ok();
+}
@@ -48,7 +48,8 @@ where
         unstable::UnstableRpcMethods::new(rpc_client)
     }
 
-    async fn rpc_client(&self) -> rpc::RpcClient {
+    /// Hand back a raw RPC client connected to the test node.
+    pub async fn rpc_client(&self) -> rpc::RpcClient {
         let url = format!("ws://127.0.0.1:{}", self.proc.ws_port());
         rpc::RpcClient::from_url(url)
             .await